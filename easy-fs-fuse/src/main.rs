@@ -68,10 +68,14 @@ fn easy_fs_pack() -> std::io::Result<()> {
     let apps: Vec<_> = read_dir(src_path)
         .unwrap()
         .into_iter()
-        .map(|dir_entry| {
-            let mut name_with_ext = dir_entry.unwrap().file_name().into_string().unwrap();
-            name_with_ext.drain(name_with_ext.find('.').unwrap()..name_with_ext.len());
-            name_with_ext
+        .filter_map(|dir_entry| {
+            // an app is named by its source file without the `.rs`, other
+            // dots being kept
+            let path = dir_entry.unwrap().path();
+            if path.extension()? != "rs" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
         })
         .collect();
     for app in apps {
@@ -99,7 +103,7 @@ fn efs_test() -> std::io::Result<()> {
             .write(true)
             .create(true)
             .open("target/fs.img")?;
-        f.set_len(BLOCK_NUM * BLOCK_SZ).unwrap();
+        f.set_len((BLOCK_NUM * BLOCK_SZ) as u64).unwrap();
         f
     })));
     EasyFileSystem::create(block_file.clone(), 4096, 1);
@@ -151,5 +155,103 @@ fn efs_test() -> std::io::Result<()> {
     random_str_test(1000 * BLOCK_SZ);
     random_str_test(2000 * BLOCK_SZ);
 
+    // shrinking frees the blocks past the new size, and growing reads zeros
+    let truncate_test = |len: usize, new_len: usize| {
+        filea.clear();
+        let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8 + 1).collect();
+        filea.write_at(0, data.as_slice());
+        filea.truncate(new_len as u32);
+        assert_eq!(filea.size(), new_len);
+        let mut read_data = vec![0u8; new_len];
+        assert_eq!(filea.read_at(0, &mut read_data), new_len);
+        assert_eq!(&read_data[..], &data[..new_len]);
+        filea.truncate(len as u32);
+        let mut read_data = vec![0u8; len];
+        assert_eq!(filea.read_at(0, &mut read_data), len);
+        assert_eq!(&read_data[..new_len], &data[..new_len]);
+        assert!(read_data[new_len..].iter().all(|byte| *byte == 0));
+    };
+    truncate_test(4 * BLOCK_SZ, BLOCK_SZ / 2);
+    truncate_test(100 * BLOCK_SZ, 20 * BLOCK_SZ + 3);
+    truncate_test(400 * BLOCK_SZ, 200 * BLOCK_SZ);
+    truncate_test(1000 * BLOCK_SZ, 0);
+    // the blocks freed go to another file without touching what is kept
+    let data: Vec<u8> = (0..2000 * BLOCK_SZ).map(|i| (i % 251) as u8 + 1).collect();
+    filea.write_at(0, data.as_slice());
+    filea.truncate(5);
+    let fileb = root_inode.find("fileb").unwrap();
+    let data = vec![0xffu8; 1000 * BLOCK_SZ];
+    fileb.write_at(0, data.as_slice());
+    let mut read_data = vec![0u8; 1000 * BLOCK_SZ];
+    assert_eq!(fileb.read_at(0, &mut read_data), data.len());
+    assert_eq!(read_data, data);
+    assert_eq!(filea.read_at(0, &mut buffer), 5);
+    assert_eq!(&buffer[..5], &[1, 2, 3, 4, 5]);
+    fileb.truncate(0);
+    assert_eq!(fileb.size(), 0);
+    // writing past the largest file or the free blocks writes nothing
+    assert_eq!(fileb.write_at(usize::MAX - 1, b"xy"), 0);
+    assert_eq!(fileb.write_at(u32::MAX as usize, b"x"), 0);
+    assert_eq!(fileb.write_at(0, &vec![0u8; 8000 * BLOCK_SZ]), 0);
+    assert_eq!(fileb.size(), 0);
+    // growing past the largest file or the free blocks fails and keeps it
+    assert!(!fileb.truncate(u32::MAX));
+    assert!(!fileb.truncate((8000 * BLOCK_SZ) as u32));
+    assert_eq!(fileb.size(), 0);
+    assert!(fileb.truncate(10));
+    assert_eq!(fileb.size(), 10);
+    fileb.truncate(0);
+
+    // rename within and across directories, replacing a file
+    let dir = root_inode.create_dir("dir").unwrap();
+    assert!(root_inode.rename("filea", &root_inode, "filec"));
+    assert!(root_inode.find("filea").is_none());
+    assert_eq!(root_inode.find("filec").unwrap().inode_id(), filea.inode_id());
+    assert!(root_inode.rename("filec", &dir, "filea"));
+    assert!(root_inode.find("filec").is_none());
+    assert_eq!(dir.find("filea").unwrap().inode_id(), filea.inode_id());
+    assert!(dir.rename("filea", &root_inode, "fileb"));
+    assert_eq!(root_inode.find("fileb").unwrap().inode_id(), filea.inode_id());
+    assert!(!root_inode.rename("missing", &root_inode, "filed"));
+    assert!(!root_inode.rename("fileb", &root_inode, "dir"));
+    let mut names = root_inode.ls();
+    names.sort();
+    assert_eq!(names, ["dir", "fileb"]);
+    assert!(dir.ls().is_empty());
+
+    // names take up to 255 bytes, dots and all
+    let long_name = "a".repeat(251) + ".txt";
+    let long = dir.create(&long_name).unwrap();
+    assert_eq!(dir.find(&long_name).unwrap().inode_id(), long.inode_id());
+    assert!(dir.create(&("b".repeat(256))).is_none());
+    assert!(dir.create("").is_none());
+    assert!(!dir.rename(&long_name, &dir, &"c".repeat(256)));
+    assert_eq!(dir.ls(), [long_name.clone()]);
+
+    // entries are found among many, also after some are moved out
+    let names: Vec<String> = (0..300).map(|i| format!("entry_{}.bin", i)).collect();
+    let ids: Vec<u32> = names
+        .iter()
+        .map(|name| dir.create(name).unwrap().inode_id())
+        .collect();
+    for i in (0..300).step_by(7) {
+        assert!(dir.rename(&names[i], &root_inode, &names[i]));
+    }
+    for (i, (name, id)) in names.iter().zip(ids.iter()).enumerate() {
+        let (kept, moved): (&easy_fs::Inode, &easy_fs::Inode) = if i % 7 == 0 {
+            (&root_inode, &dir)
+        } else {
+            (&dir, &root_inode)
+        };
+        assert_eq!(kept.find(name).unwrap().inode_id(), *id);
+        assert!(moved.find(name).is_none());
+    }
+    assert_eq!(dir.find(&long_name).unwrap().inode_id(), long.inode_id());
+    assert_eq!(dir.ls().len(), 1 + 300 - 43);
+
+    // the free blocks kept count of match the bitmap read when opened again
+    let free = efs.lock().free_data_blocks();
+    assert_eq!(EasyFileSystem::open(block_file.clone()).lock().free_data_blocks(), free);
+
     Ok(())
 }
@@ -80,12 +80,42 @@ impl Drop for BlockCache {
 const BLOCK_CACHE_SIZE: usize = 16;
 
 pub struct BlockCacheManager {
-    queue: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+    /// Block id, address of the block device and the cache
+    queue: VecDeque<(usize, usize, Arc<Mutex<BlockCache>>)>,
+    /// Number of lookups that found the block cached
+    hits: usize,
+    /// Number of lookups that had to load the block
+    misses: usize,
+}
+
+/// Statistics of the block cache
+#[derive(Clone, Copy, Debug)]
+pub struct BlockCacheStats {
+    /// Number of blocks the cache can hold
+    pub capacity: usize,
+    /// Number of blocks cached now
+    pub cached: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// The address identifying a block device, as several may be open at once
+fn device_addr(block_device: &Arc<dyn BlockDevice>) -> usize {
+    Arc::as_ptr(block_device) as *const u8 as usize
 }
 
 impl BlockCacheManager {
     pub fn new() -> Self {
-        Self { queue: VecDeque::new() }
+        Self { queue: VecDeque::new(), hits: 0, misses: 0 }
+    }
+
+    pub fn stats(&self) -> BlockCacheStats {
+        BlockCacheStats {
+            capacity: BLOCK_CACHE_SIZE,
+            cached: self.queue.len(),
+            hits: self.hits,
+            misses: self.misses,
+        }
     }
 
     pub fn get_block_cache(
@@ -93,18 +123,21 @@ impl BlockCacheManager {
         block_id: usize,
         block_device: Arc<dyn BlockDevice>,
     ) -> Arc<Mutex<BlockCache>> {
+        let device = device_addr(&block_device);
         if let Some(pair) = self.queue
             .iter()
-            .find(|pair| pair.0 == block_id) {
-                Arc::clone(&pair.1)
+            .find(|pair| pair.0 == block_id && pair.1 == device) {
+                self.hits += 1;
+                Arc::clone(&pair.2)
         } else {
+            self.misses += 1;
             // substitute
             if self.queue.len() == BLOCK_CACHE_SIZE {
                 // from front to tail
                 if let Some((idx, _)) = self.queue
                     .iter()
                     .enumerate()
-                    .find(|(_, pair)| Arc::strong_count(&pair.1) == 1) {
+                    .find(|(_, pair)| Arc::strong_count(&pair.2) == 1) {
                     self.queue.drain(idx..=idx);
                 } else {
                     panic!("Run out of BlockCache!");
@@ -114,7 +147,7 @@ impl BlockCacheManager {
             let block_cache = Arc::new(Mutex::new(
                 BlockCache::new(block_id, Arc::clone(&block_device))
             ));
-            self.queue.push_back((block_id, Arc::clone(&block_cache)));
+            self.queue.push_back((block_id, device, Arc::clone(&block_cache)));
             block_cache
         }
     }
@@ -135,10 +168,15 @@ pub fn get_block_cache(
     BLOCK_CACHE_MANAGER.lock().get_block_cache(block_id, block_device)
 }
 
+/// Get the statistics of the block cache
+pub fn block_cache_stats() -> BlockCacheStats {
+    BLOCK_CACHE_MANAGER.lock().stats()
+}
+
 /// Sync all block cache to block device
 pub fn block_cache_sync_all() {
     let manager = BLOCK_CACHE_MANAGER.lock();
-    for (_, cache) in manager.queue.iter() {
+    for (_, _, cache) in manager.queue.iter() {
         cache.lock().sync();
     }
 }
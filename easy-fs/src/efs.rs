@@ -19,6 +19,8 @@ pub struct EasyFileSystem {
     pub data_bitmap: Bitmap,
     inode_area_start_block: u32,
     data_area_start_block: u32,
+    /// Number of data blocks left to allocate, counted once when opened
+    free_data_blocks: u32,
 }
 
 /// A data block of block size
@@ -50,6 +52,7 @@ impl EasyFileSystem {
             data_bitmap,
             inode_area_start_block: 1 + inode_bitmap_blocks,
             data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+            free_data_blocks: data_area_blocks,
         };
         // clear all blocks
         for i in 0..total_blocks {
@@ -91,27 +94,37 @@ impl EasyFileSystem {
     }
     /// Open a block device as a filesystem
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<Self>> {
+        Self::try_open(block_device).expect("Error loading EFS!")
+    }
+    /// Open a block device as a filesystem, `None` if it does not hold one
+    pub fn try_open(block_device: Arc<dyn BlockDevice>) -> Option<Arc<Mutex<Self>>> {
         // read SuperBlock
         get_block_cache(0, Arc::clone(&block_device))
             .lock()
             .read(0, |super_block: &SuperBlock| {
-                assert!(super_block.is_valid(), "Error loading EFS!");
+                if !super_block.is_valid() {
+                    return None;
+                }
                 let inode_total_blocks =
                     super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
+                let data_bitmap = Bitmap::new(
+                    (1 + inode_total_blocks) as usize,
+                    super_block.data_bitmap_blocks as usize,
+                );
+                let free_data_blocks = super_block.data_area_blocks
+                    - data_bitmap.allocated(&block_device) as u32;
                 let efs = Self {
                     block_device,
                     inode_bitmap: Bitmap::new(
                         1,
                         super_block.inode_bitmap_blocks as usize
                     ),
-                    data_bitmap: Bitmap::new(
-                        (1 + inode_total_blocks) as usize,
-                        super_block.data_bitmap_blocks as usize,
-                    ),
+                    data_bitmap,
                     inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
                     data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
+                    free_data_blocks,
                 };
-                Arc::new(Mutex::new(efs))
+                Some(Arc::new(Mutex::new(efs)))
             })
     }
     /// Get the root inode of the filesystem
@@ -134,6 +147,13 @@ impl EasyFileSystem {
         let block_id = self.inode_area_start_block + inode_id / inodes_per_block;
         (block_id, (inode_id % inodes_per_block) as usize * inode_size)
     }
+    /// Get the id of the inode at a position
+    pub fn get_inode_id(&self, block_id: u32, block_offset: usize) -> u32 {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        (block_id - self.inode_area_start_block) * inodes_per_block
+            + (block_offset / inode_size) as u32
+    }
     /// Get data block by id
     pub fn get_data_block_id(&self, data_block_id: u32) -> u32 {
         self.data_area_start_block + data_block_id
@@ -142,10 +162,19 @@ impl EasyFileSystem {
     pub fn alloc_inode(&mut self) -> u32 {
         self.inode_bitmap.alloc(&self.block_device).unwrap() as u32
     }
+    /// Deallocate an inode
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap.dealloc(&self.block_device, inode_id as usize)
+    }
     /// Allocate a data block
     pub fn alloc_data(&mut self) -> u32 {
+        self.free_data_blocks -= 1;
         self.data_bitmap.alloc(&self.block_device).unwrap() as u32 + self.data_area_start_block
     }
+    /// Get the number of data blocks left to allocate
+    pub fn free_data_blocks(&self) -> u32 {
+        self.free_data_blocks
+    }
     /// Deallocate a data block
     pub fn dealloc_data(&mut self, block_id: u32) {
         get_block_cache(
@@ -159,6 +188,7 @@ impl EasyFileSystem {
         self.data_bitmap.dealloc(
             &self.block_device,
             (block_id - self.data_area_start_block) as usize
-        )
+        );
+        self.free_data_blocks += 1;
     }
 }
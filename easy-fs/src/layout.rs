@@ -8,11 +8,11 @@ use alloc::sync::Arc;
 use alloc::vec::Vec;
 
 /// Magic number for sanity check
-const EFS_MAGIC: u32 = 0x3b800001;
+const EFS_MAGIC: u32 = 0x3b800002;
 /// The max number of direct inodes
 const INODE_DIRECT_COUNT: usize = 28;
 /// The max length of inode name
-const NAME_LENGTH_LIMIT: usize = 27;
+pub const NAME_LENGTH_LIMIT: usize = 255;
 /// The max number of indirect1 inodes
 const INODE_INDIRECT1_COUNT: usize = BLOCK_SZ / 4;
 /// The max number of indirect2 inodes
@@ -22,8 +22,9 @@ const DIRECT_BOUND: usize = INODE_DIRECT_COUNT;
 /// The upper bound of indirect1 inode index
 const INDIRECT1_BOUND: usize = DIRECT_BOUND + INODE_INDIRECT1_COUNT;
 /// The upper bound of indirect2 inode index
-#[allow(unused)]
 const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INODE_INDIRECT2_COUNT;
+/// The max size of an inode in bytes
+pub const MAX_FILE_SIZE: u32 = (INDIRECT2_BOUND * BLOCK_SZ) as u32;
 
 /// Super block of a filesystem
 #[repr(C)]
@@ -74,10 +75,14 @@ impl SuperBlock {
 }
 
 /// Type of a disk inode
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum DiskInodeType {
     File,
     Directory,
+    /// A named pipe, which keeps no data on disk
+    Fifo,
+    /// A symbolic link, whose data is the path it points to
+    Symlink,
 }
 
 /// A indirect block
@@ -114,6 +119,53 @@ impl DiskInode {
     pub fn is_file(&self) -> bool {
         self.type_ == DiskInodeType::File
     }
+    /// Get the type of this inode
+    pub fn inode_type(&self) -> DiskInodeType {
+        self.type_
+    }
+    /// Whether this inode is a named pipe
+    pub fn is_fifo(&self) -> bool {
+        self.type_ == DiskInodeType::Fifo
+    }
+    /// Read the directory entry at `offset` of this directory
+    pub fn read_dirent(&self, offset: usize, block_device: &Arc<dyn BlockDevice>) -> DirEntry {
+        let mut dirent = DirEntry::empty();
+        let bytes = dirent.as_bytes_mut();
+        assert_eq!(
+            self.read_at(offset, &mut bytes[..DIRENT_HEADER_SZ], block_device),
+            DIRENT_HEADER_SZ,
+        );
+        let len = dirent.len();
+        let bytes = dirent.as_bytes_mut();
+        assert_eq!(
+            self.read_at(
+                offset + DIRENT_HEADER_SZ,
+                &mut bytes[DIRENT_HEADER_SZ..len],
+                block_device,
+            ),
+            len - DIRENT_HEADER_SZ,
+        );
+        dirent
+    }
+    /// Get the offset of the first entry in a hash bucket of this directory,
+    /// 0 if there is none
+    pub fn dir_bucket_head(&self, bucket: usize, block_device: &Arc<dyn BlockDevice>) -> u32 {
+        if (self.size as usize) < DIR_INDEX_SZ {
+            return 0;
+        }
+        let mut head = [0u8; 4];
+        self.read_at(bucket * 4, &mut head, block_device);
+        u32::from_le_bytes(head)
+    }
+    /// Set the offset of the first entry in a hash bucket of this directory
+    pub fn set_dir_bucket_head(
+        &mut self,
+        bucket: usize,
+        head: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) {
+        self.write_at(bucket * 4, &head.to_le_bytes(), block_device);
+    }
     /// Get the number of data blocks corresponding to size
     pub fn data_blocks(&self) -> u32 {
         Self::_data_blocks(self.size)
@@ -255,6 +307,52 @@ impl DiskInode {
             }
         });
     }
+    /// Decrease the size of current disk inode and return blocks that should
+    /// be deallocated, the index blocks no longer needed as well
+    pub fn decrease_size(
+        &mut self,
+        new_size: u32,
+        block_device: &Arc<dyn BlockDevice>,
+    ) -> Vec<u32> {
+        assert!(new_size <= self.size);
+        let data_blocks = self.data_blocks() as usize;
+        let new_data_blocks = Self::_data_blocks(new_size) as usize;
+        let mut v: Vec<u32> = Vec::new();
+        for inner_id in new_data_blocks..data_blocks {
+            v.push(self.get_block_id(inner_id as u32, block_device));
+        }
+        for inner_id in new_data_blocks..data_blocks.min(INODE_DIRECT_COUNT) {
+            self.direct[inner_id] = 0;
+        }
+        // indirect1 block
+        if data_blocks > INODE_DIRECT_COUNT && new_data_blocks <= INODE_DIRECT_COUNT {
+            v.push(self.indirect1);
+            self.indirect1 = 0;
+        }
+        // indirect2 block, and the indirect1 blocks under it
+        if data_blocks > INDIRECT1_BOUND {
+            let indirect1_blocks = |blocks: usize| {
+                (blocks.saturating_sub(INDIRECT1_BOUND) + INODE_INDIRECT1_COUNT - 1)
+                    / INODE_INDIRECT1_COUNT
+            };
+            get_block_cache(
+                self.indirect2 as usize,
+                Arc::clone(block_device),
+            )
+            .lock()
+            .read(0, |indirect2: &IndirectBlock| {
+                v.extend_from_slice(
+                    &indirect2[indirect1_blocks(new_data_blocks)..indirect1_blocks(data_blocks)],
+                );
+            });
+            if new_data_blocks <= INDIRECT1_BOUND {
+                v.push(self.indirect2);
+                self.indirect2 = 0;
+            }
+        }
+        self.size = new_size;
+        v
+    }
     /// Clear size to zero and return blocks that should be deallocated
     /// and clear the block contents to zero later
     pub fn clear_size(&mut self, block_device: &Arc<dyn BlockDevice>) -> Vec<u32> {
@@ -351,7 +449,7 @@ impl DiskInode {
         block_device: &Arc<dyn BlockDevice>,
     ) -> usize {
         let mut start = offset;
-        let end = (offset + buf.len()).min(self.size as usize);
+        let end = offset.saturating_add(buf.len()).min(self.size as usize);
         if start >= end {
             return 0;
         }
@@ -390,7 +488,7 @@ impl DiskInode {
         block_device: &Arc<dyn BlockDevice>,
     ) -> usize {
         let mut start = offset;
-        let end = (offset + buf.len()).min(self.size as usize);
+        let end = offset.saturating_add(buf.len()).min(self.size as usize);
         assert!(start <= end);
         let mut start_block = start / BLOCK_SZ;
         let mut write_size = 0usize;
@@ -421,57 +519,101 @@ impl DiskInode {
 }
 
 /// A directory entry
+///
+/// A directory starts with an index block of [`DIR_BUCKETS`] hash buckets,
+/// each the offset of the first entry whose name hashes to it, or 0. The
+/// entries follow one after another, each [`DIRENT_HEADER_SZ`] bytes and
+/// then its name, and link to the next entry of their bucket.
 #[repr(C)]
 pub struct DirEntry {
-    name: [u8; NAME_LENGTH_LIMIT + 1],
     inode_number: u32,
+    /// Offset of the next entry in the same bucket, 0 if there is none
+    next: u32,
+    name_len: u32,
+    name: [u8; NAME_LENGTH_LIMIT],
 }
 
-/// Size of a directory entry
-pub const DIRENT_SZ: usize = 32;
+/// Size of a directory entry without its name
+pub const DIRENT_HEADER_SZ: usize = 12;
+/// Number of hash buckets of a directory
+pub const DIR_BUCKETS: usize = BLOCK_SZ / 4;
+/// Size of the index block at the start of a directory
+pub const DIR_INDEX_SZ: usize = BLOCK_SZ;
 
 impl DirEntry {
     /// Create an empty directory entry
     pub fn empty() -> Self {
         Self {
-            name: [0u8; NAME_LENGTH_LIMIT + 1],
             inode_number: 0,
+            next: 0,
+            name_len: 0,
+            name: [0u8; NAME_LENGTH_LIMIT],
         }
     }
-    /// Crate a directory entry from name and inode number
-    pub fn new(name: &str, inode_number: u32) -> Self {
-        let mut bytes = [0u8; NAME_LENGTH_LIMIT + 1];
+    /// Crate a directory entry from name and inode number, `None` if the
+    /// name is empty or too long
+    pub fn new(name: &str, inode_number: u32) -> Option<Self> {
+        if name.is_empty() || name.len() > NAME_LENGTH_LIMIT {
+            return None;
+        }
+        let mut bytes = [0u8; NAME_LENGTH_LIMIT];
         bytes[..name.len()].copy_from_slice(name.as_bytes());
-        Self {
-            name: bytes,
+        Some(Self {
             inode_number,
-        }
+            next: 0,
+            name_len: name.len() as u32,
+            name: bytes,
+        })
+    }
+    /// Size of the entry on disk
+    pub fn len(&self) -> usize {
+        DIRENT_HEADER_SZ + self.name_len as usize
     }
-    /// Serialize into bytes
+    /// Serialize into bytes, as many as the entry takes on disk
     pub fn as_bytes(&self) -> &[u8] {
         unsafe {
             core::slice::from_raw_parts(
                 self as *const _ as usize as *const u8,
-                DIRENT_SZ,
+                self.len(),
             )
         }
     }
-    /// Serialize into mutable bytes
+    /// Serialize into mutable bytes, as many as the longest entry takes
     pub fn as_bytes_mut(&mut self) -> &mut [u8] {
         unsafe {
             core::slice::from_raw_parts_mut(
                 self as *mut _ as usize as *mut u8,
-                DIRENT_SZ,
+                DIRENT_HEADER_SZ + NAME_LENGTH_LIMIT,
             )
         }
     }
     /// Get name of the entry
     pub fn name(&self) -> &str {
-        let len = (0usize..).find(|i| self.name[*i] == 0).unwrap();
-        core::str::from_utf8(&self.name[..len]).unwrap()
+        core::str::from_utf8(&self.name[..self.name_len as usize]).unwrap()
     }
     /// Get inode number of the entry
     pub fn inode_number(&self) -> u32 {
         self.inode_number
     }
+    pub fn set_inode_number(&mut self, inode_number: u32) {
+        self.inode_number = inode_number;
+    }
+    /// Get offset of the next entry in the same bucket
+    pub fn next(&self) -> u32 {
+        self.next
+    }
+    pub fn set_next(&mut self, next: u32) {
+        self.next = next;
+    }
+}
+
+/// Get the hash bucket of a name
+pub fn dir_bucket(name: &str) -> usize {
+    // FNV-1a
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in name.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash as usize % DIR_BUCKETS
 }
@@ -14,6 +14,8 @@ pub const BLOCK_SZ: usize = 512;
 pub use block_dev::BlockDevice;
 pub use efs::EasyFileSystem;
 pub use vfs::Inode;
+pub use layout::{DiskInodeType, MAX_FILE_SIZE};
 use layout::*;
 use bitmap::Bitmap;
 use block_cache::{get_block_cache, block_cache_sync_all};
+pub use block_cache::{block_cache_stats, BlockCacheStats};
@@ -4,12 +4,18 @@ use super::{
     DiskInodeType,
     DirEntry,
     EasyFileSystem,
-    DIRENT_SZ,
+    BLOCK_SZ,
+    DIR_INDEX_SZ,
+    DIR_BUCKETS,
+    NAME_LENGTH_LIMIT,
+    MAX_FILE_SIZE,
+    dir_bucket,
     get_block_cache,
     block_cache_sync_all,
 };
 use alloc::sync::Arc;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard};
 
@@ -56,29 +62,105 @@ impl Inode {
         name: &str,
         disk_inode: &DiskInode,
     ) -> Option<u32> {
+        self.find_dirent(name, disk_inode).map(|(_, inode_id)| inode_id)
+    }
+    /// Find the offset of the entry named `name` in a directory disk inode,
+    /// and the inode id it holds
+    fn find_dirent(
+        &self,
+        name: &str,
+        disk_inode: &DiskInode,
+    ) -> Option<(usize, u32)> {
         // assert it is a directory
         assert!(disk_inode.is_dir());
-        let file_count = (disk_inode.size as usize) / DIRENT_SZ;
-        let mut dirent = DirEntry::empty();
-        for i in 0..file_count {
-            assert_eq!(
-                disk_inode.read_at(
-                    DIRENT_SZ * i,
-                    dirent.as_bytes_mut(),
-                    &self.block_device,
-                ),
-                DIRENT_SZ,
-            );
+        // only the entries in the bucket of the name are looked at
+        let mut offset = disk_inode.dir_bucket_head(dir_bucket(name), &self.block_device);
+        while offset != 0 {
+            let dirent = disk_inode.read_dirent(offset as usize, &self.block_device);
             if dirent.name() == name {
-                return Some(dirent.inode_number() as u32);
+                return Some((offset as usize, dirent.inode_number()));
             }
+            offset = dirent.next();
         }
         None
     }
-    /// Find inode under current inode by name
+    /// List the names and inode ids of the entries in a directory disk inode
+    fn list_dirents(&self, disk_inode: &DiskInode) -> Vec<(String, u32)> {
+        let mut v: Vec<(String, u32)> = Vec::new();
+        let mut offset = DIR_INDEX_SZ;
+        while offset < disk_inode.size as usize {
+            let dirent = disk_inode.read_dirent(offset, &self.block_device);
+            v.push((String::from(dirent.name()), dirent.inode_number()));
+            offset += dirent.len();
+        }
+        v
+    }
+    /// Append an entry to a directory disk inode, `name` being checked to
+    /// fit beforehand, return false if the directory cannot grow
+    fn append_dirent(
+        &self,
+        name: &str,
+        inode_id: u32,
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> bool {
+        let mut offset = disk_inode.size as usize;
+        let mut dirent = DirEntry::new(name, inode_id).unwrap();
+        // the index block comes with the first entry, its buckets empty as
+        // new blocks are zeroed
+        if offset < DIR_INDEX_SZ {
+            offset = DIR_INDEX_SZ;
+        }
+        // increase size
+        if !self.increase_size((offset + dirent.len()) as u32, disk_inode, fs) {
+            return false;
+        }
+        let bucket = dir_bucket(name);
+        dirent.set_next(disk_inode.dir_bucket_head(bucket, &self.block_device));
+        // write dirent
+        disk_inode.write_at(offset, dirent.as_bytes(), &self.block_device);
+        disk_inode.set_dir_bucket_head(bucket, offset as u32, &self.block_device);
+        true
+    }
+    /// Remove the entry at `offset` from a directory disk inode
+    ///
+    /// The entries after it move down to fill the gap, so every bucket is
+    /// linked again. This is as slow as listing the directory, but only
+    /// rename removes entries.
+    fn remove_dirent(
+        &self,
+        offset: usize,
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) {
+        let size = disk_inode.size as usize;
+        let len = disk_inode.read_dirent(offset, &self.block_device).len();
+        let mut rest = vec![0u8; size - offset - len];
+        disk_inode.read_at(offset + len, &mut rest, &self.block_device);
+        disk_inode.write_at(offset, &rest, &self.block_device);
+        for data_block in disk_inode.decrease_size((size - len) as u32, &self.block_device) {
+            fs.dealloc_data(data_block);
+        }
+        for bucket in 0..DIR_BUCKETS {
+            disk_inode.set_dir_bucket_head(bucket, 0, &self.block_device);
+        }
+        let mut offset = DIR_INDEX_SZ;
+        while offset < disk_inode.size as usize {
+            let mut dirent = disk_inode.read_dirent(offset, &self.block_device);
+            let bucket = dir_bucket(dirent.name());
+            dirent.set_next(disk_inode.dir_bucket_head(bucket, &self.block_device));
+            disk_inode.write_at(offset, dirent.as_bytes(), &self.block_device);
+            disk_inode.set_dir_bucket_head(bucket, offset as u32, &self.block_device);
+            offset += dirent.len();
+        }
+    }
+    /// Find inode under current inode by name, `None` if it is not a directory
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
         let fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
             self.find_inode_id(name, disk_inode)
             .map(|inode_id| {
                 let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
@@ -91,32 +173,60 @@ impl Inode {
             })
         })
     }
-    /// Increase the size of a disk inode
+    /// Increase the size of a disk inode, return false and leave it as it
+    /// is if the size is too large or there are not enough free blocks
     fn increase_size(
         &self,
         new_size: u32,
         disk_inode: &mut DiskInode,
         fs: &mut MutexGuard<EasyFileSystem>,
-    ) {
+    ) -> bool {
         if new_size < disk_inode.size {
-            return;
+            return true;
+        }
+        if new_size > MAX_FILE_SIZE {
+            return false;
         }
         let blocks_needed = disk_inode.blocks_num_needed(new_size);
+        if blocks_needed > fs.free_data_blocks() {
+            return false;
+        }
         let mut v: Vec<u32> = Vec::new();
         for _ in 0..blocks_needed {
             v.push(fs.alloc_data());
         }
         disk_inode.increase_size(new_size, v, &self.block_device);
+        true
     }
     /// Create inode under current inode by name
     pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::File)
+    }
+    /// Create a directory under current inode by name
+    pub fn create_dir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::Directory)
+    }
+    /// Create a named pipe under current inode by name
+    pub fn create_fifo(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::Fifo)
+    }
+    /// Create a symbolic link to `target` under current inode by name
+    pub fn create_symlink(&self, name: &str, target: &str) -> Option<Arc<Inode>> {
+        let inode = self.create_inode(name, DiskInodeType::Symlink)?;
+        inode.write_at(0, target.as_bytes());
+        Some(inode)
+    }
+    /// Create an inode of a type under current inode by name
+    fn create_inode(&self, name: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
+        if name.is_empty() || name.len() > NAME_LENGTH_LIMIT {
+            return None;
+        }
         let mut fs = self.fs.lock();
         if self.modify_disk_inode(|root_inode| {
-            // assert it is a directory
-            assert!(root_inode.is_dir());
+            // only a directory holds other inodes, and
             // has the file been created?
-            self.find_inode_id(name, root_inode)
-        }).is_some() {
+            !root_inode.is_dir() || self.find_inode_id(name, root_inode).is_some()
+        }) {
             return None;
         }
         // create a new file
@@ -129,22 +239,15 @@ impl Inode {
             new_inode_block_id as usize,
             Arc::clone(&self.block_device)
         ).lock().modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
-            new_inode.initialize(DiskInodeType::File);
+            new_inode.initialize(type_);
         });
-        self.modify_disk_inode(|root_inode| {
+        if !self.modify_disk_inode(|root_inode| {
             // append file in the dirent
-            let file_count = (root_inode.size as usize) / DIRENT_SZ;
-            let new_size = (file_count + 1) * DIRENT_SZ;
-            // increase size
-            self.increase_size(new_size as u32, root_inode, &mut fs);
-            // write dirent
-            let dirent = DirEntry::new(name, new_inode_id);
-            root_inode.write_at(
-                file_count * DIRENT_SZ,
-                dirent.as_bytes(),
-                &self.block_device,
-            );
-        });
+            self.append_dirent(name, new_inode_id, root_inode, &mut fs)
+        }) {
+            fs.dealloc_inode(new_inode_id);
+            return None;
+        }
 
         let (block_id, block_offset) = fs.get_disk_inode_pos(new_inode_id);
         block_cache_sync_all();
@@ -157,27 +260,134 @@ impl Inode {
         )))
         // release efs lock automatically by compiler
     }
+    /// Move the inode named `old_name` under current inode to `new_name`
+    /// under `new_dir`, which is on the same filesystem and may be current
+    /// inode
+    ///
+    /// An inode named `new_name` already is replaced and freed, unless it
+    /// or the inode moved is a directory, so it must not be in use. Return
+    /// false if there is no `old_name` or it cannot be moved.
+    pub fn rename(&self, old_name: &str, new_dir: &Inode, new_name: &str) -> bool {
+        if new_name.is_empty() || new_name.len() > NAME_LENGTH_LIMIT {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        let old = self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            self.find_dirent(old_name, disk_inode)
+        });
+        let (old_offset, inode_id) = match old {
+            Some(old) => old,
+            None => return false,
+        };
+        let target = new_dir.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            Some(new_dir.find_dirent(new_name, disk_inode))
+        });
+        let inode_type = |inode_id: u32| {
+            let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+            get_block_cache(
+                block_id as usize,
+                Arc::clone(&self.block_device)
+            ).lock().read(block_offset, |disk_inode: &DiskInode| disk_inode.inode_type())
+        };
+        match target {
+            // new_dir is not a directory
+            None => return false,
+            Some(Some((_, target_id))) if target_id == inode_id => return true,
+            Some(Some((target_offset, target_id))) => {
+                if inode_type(inode_id) == DiskInodeType::Directory
+                    || inode_type(target_id) == DiskInodeType::Directory
+                {
+                    return false;
+                }
+                // free the inode replaced
+                let (block_id, block_offset) = fs.get_disk_inode_pos(target_id);
+                let data_blocks_dealloc = get_block_cache(
+                    block_id as usize,
+                    Arc::clone(&self.block_device)
+                ).lock().modify(block_offset, |disk_inode: &mut DiskInode| {
+                    disk_inode.clear_size(&self.block_device)
+                });
+                for data_block in data_blocks_dealloc.into_iter() {
+                    fs.dealloc_data(data_block);
+                }
+                fs.dealloc_inode(target_id);
+                new_dir.modify_disk_inode(|disk_inode| {
+                    let mut dirent = disk_inode.read_dirent(target_offset, &self.block_device);
+                    dirent.set_inode_number(inode_id);
+                    disk_inode.write_at(target_offset, dirent.as_bytes(), &self.block_device);
+                });
+            }
+            Some(None) => {
+                if !new_dir.modify_disk_inode(|disk_inode| {
+                    new_dir.append_dirent(new_name, inode_id, disk_inode, &mut fs)
+                }) {
+                    return false;
+                }
+            }
+        }
+        // the new entry, if appended to current inode, comes after the old one
+        self.modify_disk_inode(|disk_inode| {
+            self.remove_dirent(old_offset, disk_inode, &mut fs);
+        });
+        block_cache_sync_all();
+        true
+    }
     /// List inodes under current inode
     pub fn ls(&self) -> Vec<String> {
         let _fs = self.fs.lock();
         self.read_disk_inode(|disk_inode| {
-            let file_count = (disk_inode.size as usize) / DIRENT_SZ;
-            let mut v: Vec<String> = Vec::new();
-            for i in 0..file_count {
-                let mut dirent = DirEntry::empty();
-                assert_eq!(
-                    disk_inode.read_at(
-                        i * DIRENT_SZ,
-                        dirent.as_bytes_mut(),
-                        &self.block_device,
-                    ),
-                    DIRENT_SZ,
-                );
-                v.push(String::from(dirent.name()));
-            }
-            v
+            self.list_dirents(disk_inode)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
         })
     }
+    /// Get the id of current inode
+    pub fn inode_id(&self) -> u32 {
+        self.fs.lock().get_inode_id(self.block_id as u32, self.block_offset)
+    }
+    /// Get the size of current inode in bytes
+    pub fn size(&self) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+    /// Whether current inode is a named pipe
+    pub fn is_fifo(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_fifo())
+    }
+    /// List the names, inode ids and types of the inodes under current inode
+    pub fn read_dir(&self) -> Vec<(String, u32, DiskInodeType)> {
+        let fs = self.fs.lock();
+        let entries: Vec<(String, u32)> =
+            self.read_disk_inode(|disk_inode| self.list_dirents(disk_inode));
+        entries
+            .into_iter()
+            .map(|(name, inode_id)| {
+                let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+                let type_ = get_block_cache(
+                    block_id as usize,
+                    Arc::clone(&self.block_device)
+                ).lock().read(block_offset, |disk_inode: &DiskInode| disk_inode.inode_type());
+                (name, inode_id, type_)
+            })
+            .collect()
+    }
+    /// Get the type of current inode
+    pub fn inode_type(&self) -> DiskInodeType {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.inode_type())
+    }
+    /// Whether current inode is a directory
+    pub fn is_dir(&self) -> bool {
+        self.inode_type() == DiskInodeType::Directory
+    }
     /// Read data from current inode
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
         let _fs = self.fs.lock();
@@ -186,15 +396,55 @@ impl Inode {
         })
     }
     /// Write data to current inode
+    ///
+    /// If it cannot grow past `offset + buf.len()`, only the data up to its
+    /// size is written.
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
         let mut fs = self.fs.lock();
         let size = self.modify_disk_inode(|disk_inode| {
-            self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
+            let end = offset.saturating_add(buf.len()).min(u32::MAX as usize);
+            if !self.increase_size(end as u32, disk_inode, &mut fs)
+                && offset >= disk_inode.size as usize
+            {
+                return 0;
+            }
             disk_inode.write_at(offset, buf, &self.block_device)
         });
         block_cache_sync_all();
         size
     }
+    /// Change the size of current inode to `new_size`, freeing the blocks
+    /// past it or filling the space added with zeros
+    ///
+    /// Return false if it cannot grow that large.
+    pub fn truncate(&self, new_size: u32) -> bool {
+        let mut fs = self.fs.lock();
+        let truncated = self.modify_disk_inode(|disk_inode| {
+            if new_size >= disk_inode.size {
+                // new blocks are zeroed when deallocated
+                return self.increase_size(new_size, disk_inode, &mut fs);
+            }
+            // zero the rest of the last block kept, or growing again shows it
+            let block_end = (new_size as usize + BLOCK_SZ - 1) / BLOCK_SZ * BLOCK_SZ;
+            let tail = block_end.min(disk_inode.size as usize) - new_size as usize;
+            if tail > 0 {
+                let zeros = [0u8; BLOCK_SZ];
+                disk_inode.write_at(new_size as usize, &zeros[..tail], &self.block_device);
+            }
+            let size = disk_inode.size;
+            let data_blocks_dealloc = disk_inode.decrease_size(new_size, &self.block_device);
+            assert!(
+                data_blocks_dealloc.len()
+                    == (DiskInode::total_blocks(size) - DiskInode::total_blocks(new_size)) as usize
+            );
+            for data_block in data_blocks_dealloc.into_iter() {
+                fs.dealloc_data(data_block);
+            }
+            true
+        });
+        block_cache_sync_all();
+        truncated
+    }
     /// Clear the data in current inode
     pub fn clear(&self) {
         let mut fs = self.fs.lock();
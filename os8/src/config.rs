@@ -7,8 +7,14 @@ pub const MEMORY_END: usize = 0x88000000;
 pub const PAGE_SIZE: usize = 0x1000;
 pub const PAGE_SIZE_BITS: usize = 0xc;
 pub const MAX_SYSCALL_NUM: usize = 500;
+/// Number of fds a process can have open
+pub const MAX_FD: usize = 128;
+/// Priority of a thread that never called set_priority
+pub const DEFAULT_PRIORITY: usize = 16;
+/// A thread advances its pass by `BIG_STRIDE / priority` each time it is scheduled
+pub const BIG_STRIDE: usize = 1 << 20;
 
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 pub const CLOCK_FREQ: usize = 12500000;
-pub const MMIO: &[(usize, usize)] = &[(0x10001000, 0x1000)];
+pub const MMIO: &[(usize, usize)] = &[(0x10001000, 0x2000)];
@@ -7,6 +7,20 @@ type BlockDeviceImpl = virtio_blk::VirtIOBlock;
 
 lazy_static! {
     pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = Arc::new(BlockDeviceImpl::new());
+    /// The second disk, if there is one
+    static ref EXTRA_BLOCK_DEVICE: Option<Arc<dyn BlockDevice>> =
+        BlockDeviceImpl::probe(virtio_blk::VIRTIO1)
+            .map(|device| Arc::new(device) as Arc<dyn BlockDevice>);
+}
+
+/// The block device named `name`, `vda` for the root disk or `vdb` for the
+/// second one
+pub fn block_device(name: &str) -> Option<Arc<dyn BlockDevice>> {
+    match name {
+        "vda" => Some(BLOCK_DEVICE.clone()),
+        "vdb" => EXTRA_BLOCK_DEVICE.clone(),
+        _ => None,
+    }
 }
 
 #[allow(unused)]
@@ -18,6 +18,12 @@ use lazy_static::*;
 
 #[allow(unused)]
 const VIRTIO0: usize = 0x10001000;
+/// The virtio-mmio slot of a second disk
+pub const VIRTIO1: usize = 0x10002000;
+/// Magic value at the start of a virtio-mmio header, "virt"
+const VIRTIO_MAGIC: u32 = 0x74726976;
+/// Device id of a block device in a virtio-mmio header
+const VIRTIO_DEVICE_BLOCK: u32 = 2;
 
 pub struct VirtIOBlock(UPSafeCell<VirtIOBlk<'static>>);
 
@@ -49,6 +55,23 @@ impl VirtIOBlock {
             ).unwrap()))
         }
     }
+    /// Set up the virtio-mmio slot at `base`, `None` if it holds no block device
+    pub fn probe(base: usize) -> Option<Self> {
+        let (magic, device_id) = unsafe {
+            (
+                core::ptr::read_volatile(base as *const u32),
+                core::ptr::read_volatile((base + 8) as *const u32),
+            )
+        };
+        if magic != VIRTIO_MAGIC || device_id != VIRTIO_DEVICE_BLOCK {
+            return None;
+        }
+        unsafe {
+            VirtIOBlk::new(&mut *(base as *mut VirtIOHeader))
+                .ok()
+                .map(|blk| Self(UPSafeCell::new(blk)))
+        }
+    }
 }
 
 #[no_mangle]
@@ -0,0 +1,205 @@
+//! Device files, mounted at `/dev`
+//!
+//! The directory holds a fixed set of character devices. Opening one gives
+//! a [`File`] of its own rather than an [`OSInode`](super::OSInode).
+
+use super::vfs::{alloc_dev, DirEntry, FileSystem, InodeType, VfsInode};
+use super::{File, PollEvents, Stdin};
+use crate::mm::UserBuffer;
+use crate::sbi::console_putchar;
+use crate::sync::UPSafeCell;
+use crate::timer::get_time;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use lazy_static::*;
+
+/// A character device
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Device {
+    /// Reads nothing and takes any write
+    Null,
+    /// Reads zeros and takes any write
+    Zero,
+    /// Reads pseudo-random bytes and takes any write
+    Random,
+    /// The terminal behind stdin and stdout
+    Console,
+}
+
+/// The devices by name, whose inode numbers count from 1
+const DEVICES: [(&str, Device); 4] = [
+    ("null", Device::Null),
+    ("zero", Device::Zero),
+    ("random", Device::Random),
+    ("console", Device::Console),
+];
+
+/// A devfs filesystem
+pub struct DevFs {
+    dev: usize,
+}
+
+impl DevFs {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { dev: alloc_dev() })
+    }
+}
+
+impl FileSystem for DevFs {
+    fn fs_type(&self) -> &'static str {
+        "devfs"
+    }
+    fn root_inode(self: Arc<Self>) -> Arc<dyn VfsInode> {
+        Arc::new(DevFsInode {
+            fs: self,
+            device: None,
+        })
+    }
+}
+
+/// The root directory of a devfs, or one of its devices
+struct DevFsInode {
+    fs: Arc<DevFs>,
+    device: Option<Device>,
+}
+
+impl VfsInode for DevFsInode {
+    fn dev(&self) -> usize {
+        self.fs.dev
+    }
+    fn ino(&self) -> usize {
+        match self.device {
+            Some(device) => DEVICES.iter().position(|(_, d)| *d == device).unwrap() + 1,
+            None => 0,
+        }
+    }
+    fn inode_type(&self) -> InodeType {
+        match self.device {
+            Some(_) => InodeType::CharDevice,
+            None => InodeType::Directory,
+        }
+    }
+    fn size(&self) -> usize {
+        0
+    }
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> usize {
+        0
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> usize {
+        0
+    }
+    fn clear(&self) {}
+    fn find(&self, name: &str) -> Option<Arc<dyn VfsInode>> {
+        if self.device.is_some() {
+            return None;
+        }
+        let (_, device) = DEVICES.iter().find(|(device_name, _)| *device_name == name)?;
+        Some(Arc::new(Self {
+            fs: Arc::clone(&self.fs),
+            device: Some(*device),
+        }))
+    }
+    fn create(&self, _name: &str, _type_: InodeType) -> Option<Arc<dyn VfsInode>> {
+        None
+    }
+    fn read_dir(&self) -> Vec<DirEntry> {
+        if self.device.is_some() {
+            return Vec::new();
+        }
+        DEVICES
+            .iter()
+            .enumerate()
+            .map(|(idx, (name, _))| DirEntry {
+                name: String::from(*name),
+                ino: idx + 1,
+                type_: InodeType::CharDevice,
+            })
+            .collect()
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn open_device(&self, readable: bool, writable: bool) -> Option<Arc<dyn File + Send + Sync>> {
+        Some(Arc::new(DeviceFile {
+            device: self.device?,
+            readable,
+            writable,
+        }))
+    }
+}
+
+lazy_static! {
+    /// State of the xorshift generator behind `/dev/random`
+    static ref RANDOM_STATE: UPSafeCell<u64> = unsafe { UPSafeCell::new(get_time() as u64 | 1) };
+}
+
+/// Take the next pseudo-random number
+fn next_random() -> u64 {
+    let mut state = RANDOM_STATE.exclusive_access();
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// An open device
+struct DeviceFile {
+    device: Device,
+    readable: bool,
+    writable: bool,
+}
+
+impl File for DeviceFile {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        match self.device {
+            Device::Null => 0,
+            Device::Zero => {
+                for slice in buf.buffers.iter_mut() {
+                    slice.fill(0);
+                }
+                buf.len()
+            }
+            Device::Random => {
+                for slice in buf.buffers.iter_mut() {
+                    for chunk in slice.chunks_mut(8) {
+                        let bytes = next_random().to_le_bytes();
+                        chunk.copy_from_slice(&bytes[..chunk.len()]);
+                    }
+                }
+                buf.len()
+            }
+            Device::Console => Stdin.read(buf),
+        }
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        if self.device == Device::Console {
+            for slice in buf.buffers.iter() {
+                for byte in slice.iter() {
+                    console_putchar(*byte as usize);
+                }
+            }
+        }
+        buf.len()
+    }
+    fn poll(&self) -> PollEvents {
+        let mut events = PollEvents::empty();
+        if self.readable {
+            events |= match self.device {
+                Device::Console => Stdin.poll(),
+                _ => PollEvents::IN,
+            };
+        }
+        if self.writable {
+            events |= PollEvents::OUT;
+        }
+        events
+    }
+}
@@ -0,0 +1,140 @@
+//! easy-fs behind the virtual filesystem layer
+
+use super::inode::is_open;
+use super::vfs::{alloc_dev, DirEntry, FileSystem, InodeType, VfsInode};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use easy_fs::{BlockDevice, DiskInodeType, EasyFileSystem, Inode, MAX_FILE_SIZE};
+
+/// An easy-fs filesystem on a block device
+pub struct EasyFs {
+    dev: usize,
+    root: Arc<Inode>,
+}
+
+impl EasyFs {
+    /// Open the filesystem on `block_device`, `None` if it holds none
+    pub fn open(block_device: Arc<dyn BlockDevice>) -> Option<Arc<Self>> {
+        let efs = EasyFileSystem::try_open(block_device)?;
+        Some(Arc::new(Self {
+            dev: alloc_dev(),
+            root: Arc::new(EasyFileSystem::root_inode(&efs)),
+        }))
+    }
+}
+
+impl FileSystem for EasyFs {
+    fn fs_type(&self) -> &'static str {
+        "easyfs"
+    }
+    fn root_inode(self: Arc<Self>) -> Arc<dyn VfsInode> {
+        let inode = Arc::clone(&self.root);
+        Arc::new(EasyFsInode { fs: self, inode })
+    }
+}
+
+/// An inode of easy-fs
+struct EasyFsInode {
+    fs: Arc<EasyFs>,
+    inode: Arc<Inode>,
+}
+
+impl EasyFsInode {
+    fn wrap(&self, inode: Arc<Inode>) -> Arc<dyn VfsInode> {
+        Arc::new(Self {
+            fs: Arc::clone(&self.fs),
+            inode,
+        })
+    }
+}
+
+fn inode_type(type_: DiskInodeType) -> InodeType {
+    match type_ {
+        DiskInodeType::File => InodeType::File,
+        DiskInodeType::Directory => InodeType::Directory,
+        DiskInodeType::Fifo => InodeType::Fifo,
+        DiskInodeType::Symlink => InodeType::Symlink,
+    }
+}
+
+impl VfsInode for EasyFsInode {
+    fn dev(&self) -> usize {
+        self.fs.dev
+    }
+    fn ino(&self) -> usize {
+        self.inode.inode_id() as usize
+    }
+    fn inode_type(&self) -> InodeType {
+        inode_type(self.inode.inode_type())
+    }
+    fn size(&self) -> usize {
+        self.inode.size()
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        self.inode.read_at(offset, buf)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        self.inode.write_at(offset, buf)
+    }
+    fn clear(&self) {
+        self.inode.clear();
+    }
+    fn truncate(&self, size: usize) -> bool {
+        if self.inode_type() != InodeType::File || size > MAX_FILE_SIZE as usize {
+            return false;
+        }
+        self.inode.truncate(size as u32)
+    }
+    fn max_size(&self) -> usize {
+        MAX_FILE_SIZE as usize
+    }
+    fn find(&self, name: &str) -> Option<Arc<dyn VfsInode>> {
+        self.inode.find(name).map(|inode| self.wrap(inode))
+    }
+    fn create(&self, name: &str, type_: InodeType) -> Option<Arc<dyn VfsInode>> {
+        let inode = match type_ {
+            InodeType::File => self.inode.create(name),
+            InodeType::Directory => self.inode.create_dir(name),
+            InodeType::Fifo => self.inode.create_fifo(name),
+            InodeType::Symlink | InodeType::CharDevice => None,
+        };
+        inode.map(|inode| self.wrap(inode))
+    }
+    fn symlink(&self, name: &str, target: &str) -> bool {
+        self.inode.create_symlink(name, target).is_some()
+    }
+    fn rename(&self, old_name: &str, new_dir: &dyn VfsInode, new_name: &str) -> bool {
+        match new_dir.as_any().downcast_ref::<Self>() {
+            Some(new_dir) if Arc::ptr_eq(&new_dir.fs, &self.fs) => {
+                // a file replaced is freed at once, so not while it is open
+                let moved = self.inode.find(old_name).map(|inode| inode.inode_id());
+                if let Some(target) = new_dir.inode.find(new_name) {
+                    let target = target.inode_id();
+                    if Some(target) != moved && is_open((self.fs.dev, target as usize)) {
+                        return false;
+                    }
+                }
+                self.inode.rename(old_name, &new_dir.inode, new_name)
+            }
+            _ => false,
+        }
+    }
+    fn read_dir(&self) -> Vec<DirEntry> {
+        if !self.inode.is_dir() {
+            return Vec::new();
+        }
+        self.inode
+            .read_dir()
+            .into_iter()
+            .map(|(name, ino, type_)| DirEntry {
+                name,
+                ino: ino as usize,
+                type_: inode_type(type_),
+            })
+            .collect()
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,92 @@
+//! Named pipes
+//!
+//! A FIFO inode keeps no data on disk. While it is open, its ends share a
+//! [`PipeRingBuffer`] found by the device id and inode number, and all opens of the same end
+//! share one [`Pipe`], so that the pipe ends once all of them are closed.
+
+use super::{Pipe, PipeRingBuffer};
+use crate::sync::UPSafeCell;
+use crate::task::{
+    block_current_and_run_next, current_task, current_task_killed, TaskControlBlock,
+};
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use lazy_static::*;
+
+lazy_static! {
+    /// Buffers of open FIFOs by device id and inode number
+    static ref FIFO_BUFFERS: UPSafeCell<BTreeMap<(usize, usize), Weak<UPSafeCell<PipeRingBuffer>>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// The buffer of the FIFO `inode_id`, a new one if it is not open
+fn fifo_buffer(inode_id: (usize, usize)) -> Arc<UPSafeCell<PipeRingBuffer>> {
+    let mut buffers = FIFO_BUFFERS.exclusive_access();
+    if let Some(buffer) = buffers.get(&inode_id).and_then(|buffer| buffer.upgrade()) {
+        return buffer;
+    }
+    buffers.retain(|_, buffer| buffer.strong_count() > 0);
+    let buffer = Arc::new(unsafe { UPSafeCell::new(PipeRingBuffer::new()) });
+    buffers.insert(inode_id, Arc::downgrade(&buffer));
+    buffer
+}
+
+/// Stop `task` waiting in [`open_fifo`] for the other end of any FIFO,
+/// return false if it was not
+pub fn remove_fifo_waiter(task: &Arc<TaskControlBlock>) -> bool {
+    FIFO_BUFFERS
+        .exclusive_access()
+        .values()
+        .filter_map(|buffer| buffer.upgrade())
+        .any(|buffer| buffer.exclusive_access().remove_waiter(task))
+}
+
+/// Open the read or the write end of a FIFO
+///
+/// Wait until the other end is opened as well, unless `nonblock` is set,
+/// which counts even if it is closed again before this task runs. Then
+/// the read end is returned at once, and opening the write end fails if
+/// there is no reader. Return `None` if that fails or the task is killed.
+pub fn open_fifo(inode_id: (usize, usize), writable: bool, nonblock: bool) -> Option<Arc<Pipe>> {
+    let buffer = fifo_buffer(inode_id);
+    let mut ring_buffer = buffer.exclusive_access();
+    let end = if writable {
+        if nonblock && ring_buffer.all_read_ends_closed() {
+            return None;
+        }
+        ring_buffer.write_end().unwrap_or_else(|| {
+            let write_end = Arc::new(Pipe::write_end_with_buffer(Arc::clone(&buffer)));
+            ring_buffer.set_write_end(&write_end);
+            write_end
+        })
+    } else {
+        ring_buffer.read_end().unwrap_or_else(|| {
+            let read_end = Arc::new(Pipe::read_end_with_buffer(Arc::clone(&buffer)));
+            ring_buffer.set_read_end(&read_end);
+            read_end
+        })
+    };
+    ring_buffer.count_open(writable);
+    let peer_opens = ring_buffer.opens(!writable);
+    drop(ring_buffer);
+    if nonblock {
+        return Some(end);
+    }
+    loop {
+        let mut ring_buffer = buffer.exclusive_access();
+        let peer_open = if writable {
+            !ring_buffer.all_read_ends_closed()
+        } else {
+            !ring_buffer.all_write_ends_closed()
+        };
+        if peer_open || ring_buffer.opens(!writable) != peer_opens {
+            return Some(end);
+        }
+        if current_task_killed() {
+            return None;
+        }
+        ring_buffer.add_waiter(current_task().unwrap());
+        drop(ring_buffer);
+        block_current_and_run_next();
+    }
+}
@@ -1,28 +1,42 @@
-use easy_fs::{
-    EasyFileSystem,
-    Inode,
-};
-use crate::drivers::BLOCK_DEVICE;
 use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::Arc;
-use lazy_static::*;
 use bitflags::*;
 use alloc::vec::Vec;
-use super::File;
+use lazy_static::*;
+use super::{File, SeekFrom, Stat, StatMode};
+use super::fifo::open_fifo;
+use super::mount::mounted_root;
+use super::path::{find_inode, find_inode_nofollow, find_parent, find_parent_trail};
+use super::vfs::{InodeType, VfsInode};
 use crate::mm::UserBuffer;
 
+lazy_static! {
+    /// Numbers of open files by device id and inode number
+    static ref OPEN_INODES: UPSafeCell<BTreeMap<(usize, usize), usize>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Whether the inode `inode_id`, a device id and inode number, is open
+pub fn is_open(inode_id: (usize, usize)) -> bool {
+    OPEN_INODES.exclusive_access().contains_key(&inode_id)
+}
+
 /// A wrapper around a filesystem inode
 /// to implement File trait atop
 pub struct OSInode {
     readable: bool,
     writable: bool,
+    /// Every write goes to the end of the file
+    append: bool,
     inner: UPSafeCell<OSInodeInner>,
 }
 
 /// The OS inode inner in 'UPSafeCell'
 pub struct OSInodeInner {
     offset: usize,
-    inode: Arc<Inode>,
+    inode: Arc<dyn VfsInode>,
 }
 
 impl OSInode {
@@ -30,17 +44,49 @@ impl OSInode {
     pub fn new(
         readable: bool,
         writable: bool,
-        inode: Arc<Inode>,
+        append: bool,
+        inode: Arc<dyn VfsInode>,
     ) -> Self {
+        *OPEN_INODES
+            .exclusive_access()
+            .entry((inode.dev(), inode.ino()))
+            .or_insert(0) += 1;
         Self {
             readable,
             writable,
+            append,
             inner: unsafe { UPSafeCell::new(OSInodeInner {
                 offset: 0,
                 inode,
             })},
         }
     }
+    /// Whether the inode is a directory
+    pub fn is_dir(&self) -> bool {
+        self.inner.exclusive_access().inode.is_dir()
+    }
+    /// Pass the entries of a directory from the offset on to `take` with
+    /// their index until it refuses one, return false if the inode is not a
+    /// directory
+    pub fn read_dir(&self, mut take: impl FnMut(usize, &str, usize, InodeType) -> bool) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if !inner.inode.is_dir() {
+            return false;
+        }
+        let entries = inner.inode.read_dir();
+        for entry in entries.iter().skip(inner.offset) {
+            if !take(inner.offset, &entry.name, entry.ino, entry.type_) {
+                break;
+            }
+            inner.offset += 1;
+        }
+        true
+    }
+    /// Change the size of the file to `size`, return false if it is not a
+    /// regular file or the filesystem cannot
+    pub fn truncate(&self, size: usize) -> bool {
+        self.inner.exclusive_access().inode.truncate(size)
+    }
     /// Read all data inside a inode into vector
     pub fn read_all(&self) -> Vec<u8> {
         let mut inner = self.inner.exclusive_access();
@@ -58,19 +104,24 @@ impl OSInode {
     }
 }
 
-lazy_static! {
-    /// The root of all inodes, or '/' in short
-    pub static ref ROOT_INODE: Arc<Inode> = {
-        let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
-        Arc::new(EasyFileSystem::root_inode(&efs))
-    };
+impl Drop for OSInode {
+    fn drop(&mut self) {
+        let inner = self.inner.exclusive_access();
+        let inode_id = (inner.inode.dev(), inner.inode.ino());
+        let mut open_inodes = OPEN_INODES.exclusive_access();
+        let count = open_inodes.get_mut(&inode_id).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            open_inodes.remove(&inode_id);
+        }
+    }
 }
 
 /// List all files in the filesystems
 pub fn list_apps() {
     println!("/**** APPS ****");
-    for app in ROOT_INODE.ls() {
-        println!("{}", app);
+    for entry in find_inode("/").unwrap().read_dir() {
+        println!("{}", entry.name);
     }
     println!("**************/");
 }
@@ -81,8 +132,14 @@ bitflags! {
         const RDONLY = 0;
         const WRONLY = 1 << 0;
         const RDWR = 1 << 1;
+        const EXCL = 1 << 7;
         const CREATE = 1 << 9;
         const TRUNC = 1 << 10;
+        const NONBLOCK = 1 << 11;
+        const APPEND = 1 << 12;
+        /// a symbolic link at the path is not followed, so it fails to open
+        const NOFOLLOW = 1 << 17;
+        const CLOEXEC = 1 << 19;
     }
 }
 
@@ -91,7 +148,7 @@ impl OpenFlags {
     /// does not check validity for simplicity
     /// returns (readable, writable)
     pub fn read_write(&self) -> (bool, bool) {
-        if self.is_empty() {
+        if !self.intersects(Self::WRONLY | Self::RDWR) {
             (true, false)
         } else if self.contains(Self::WRONLY) {
             (false, true)
@@ -99,71 +156,258 @@ impl OpenFlags {
             (true, true)
         }
     }
+    /// Find the inode to open at a normalized path
+    fn find(&self, name: &str) -> Option<Arc<dyn VfsInode>> {
+        if self.contains(Self::NOFOLLOW) {
+            find_inode_nofollow(name)
+        } else {
+            find_inode(name)
+        }
+    }
 }
 
-/// Open a file by path
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
-    let (readable, writable) = flags.read_write();
-    if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
-            // clear size
-            inode.clear();
-            Some(Arc::new(OSInode::new(
-                readable,
-                writable,
-                inode,
-            )))
-        } else {
-            // create file
-            ROOT_INODE.create(name)
-                .map(|inode| {
-                    Arc::new(OSInode::new(
-                        readable,
-                        writable,
-                        inode,
-                    ))
-                })
+/// Open a file by normalized path
+///
+/// A named pipe is opened for either reading or writing, and gives an end
+/// of the pipe. A device gives a file of its own.
+pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<dyn File + Send + Sync>> {
+    let inode = flags.find(name);
+    let inode_type = inode.as_ref().map(|inode| inode.inode_type());
+    if inode_type == Some(InodeType::CharDevice) {
+        if flags.contains(OpenFlags::CREATE | OpenFlags::EXCL) {
+            return None;
         }
+        let (readable, writable) = flags.read_write();
+        return inode.unwrap().open_device(readable, writable);
+    }
+    if inode_type == Some(InodeType::Fifo) {
+        let inode = inode.unwrap();
+        let (readable, writable) = flags.read_write();
+        if readable == writable || flags.contains(OpenFlags::CREATE | OpenFlags::EXCL) {
+            return None;
+        }
+        let nonblock = flags.contains(OpenFlags::NONBLOCK);
+        return open_fifo((inode.dev(), inode.ino()), writable, nonblock)
+            .map(|pipe| pipe as Arc<dyn File + Send + Sync>);
+    }
+    open_inode(name, flags).map(|inode| inode as Arc<dyn File + Send + Sync>)
+}
+
+/// Create a named pipe at a normalized path, return false if it is taken
+pub fn make_fifo(name: &str) -> bool {
+    find_parent(name).map_or(false, |(dir, name)| dir.create(name, InodeType::Fifo).is_some())
+}
+
+/// Create a symbolic link to `target` at a normalized path, return false if
+/// it is taken
+pub fn make_symlink(target: &str, name: &str) -> bool {
+    find_parent(name).map_or(false, |(dir, name)| dir.symlink(name, target))
+}
+
+/// The path the symbolic link at a normalized path points to, `None` if
+/// there is no symbolic link there
+pub fn read_link(name: &str) -> Option<String> {
+    find_inode_nofollow(name)?.read_link()
+}
+
+/// The stat of the inode at a normalized path, of a symbolic link there
+/// itself unless `follow`
+pub fn stat(name: &str, follow: bool) -> Option<Stat> {
+    let inode = if follow {
+        find_inode(name)?
     } else {
-        ROOT_INODE.find(name)
-            .map(|inode| {
-                if flags.contains(OpenFlags::TRUNC) {
-                    inode.clear();
-                }
-                Arc::new(OSInode::new(
-                    readable,
-                    writable,
-                    inode
-                ))
-            })
+        find_inode_nofollow(name)?
+    };
+    let mode = match inode.inode_type() {
+        InodeType::File => StatMode::FILE,
+        InodeType::Directory => StatMode::DIR,
+        InodeType::Fifo => StatMode::FIFO,
+        InodeType::CharDevice => StatMode::CHR,
+        InodeType::Symlink => StatMode::LNK,
+    };
+    Some(Stat::new(inode.dev() as u64, inode.ino() as u64, mode, 1))
+}
+
+/// Create a directory at a normalized path, return false if it is taken
+pub fn make_dir(name: &str) -> bool {
+    find_parent(name).map_or(false, |(dir, name)| dir.create(name, InodeType::Directory).is_some())
+}
+
+/// Remove the file or, with `dir`, the empty directory at a normalized path
+///
+/// A symbolic link is removed itself, not what it points to.
+///
+/// Return false if there is no such file, it is of the other kind or a mount
+/// point, or its filesystem cannot remove it.
+pub fn unlink(name: &str, dir: bool) -> bool {
+    let is_dir = match find_inode_nofollow(name) {
+        Some(inode) => inode.is_dir(),
+        None => return false,
+    };
+    if is_dir != dir || mounted_root(name).is_some() {
+        return false;
+    }
+    find_parent(name).map_or(false, |(parent, name)| parent.unlink(name))
+}
+
+/// Move the file or directory at the normalized path `old` to `new`,
+/// replacing a file there
+///
+/// Return false if there is nothing at `old`, either path is a mount point,
+/// the paths are in different filesystems, a directory would move inside
+/// itself, or the filesystem refuses.
+pub fn rename(old: &str, new: &str) -> bool {
+    let moved = match find_inode_nofollow(old) {
+        Some(inode) => inode,
+        None => return false,
+    };
+    if mounted_root(old).is_some() || mounted_root(new).is_some() {
+        return false;
+    }
+    // the new directory may be inside through a symbolic link, so look at
+    // the inodes on the way there, not at the path
+    let moved_id = (moved.dev(), moved.ino());
+    match find_parent_trail(new) {
+        Some(trail) if trail.iter().all(|inode| (inode.dev(), inode.ino()) != moved_id) => {}
+        _ => return false,
+    }
+    let ((old_dir, old_name), (new_dir, new_name)) = match (find_parent(old), find_parent(new)) {
+        (Some(old), Some(new)) => (old, new),
+        _ => return false,
+    };
+    old_dir.dev() == new_dir.dev() && old_dir.rename(old_name, &*new_dir, new_name)
+}
+
+/// Open a regular file or a directory by normalized path
+///
+/// With `CREATE` a missing file is created, and with `EXCL` as well an
+/// existing one is not opened. A directory or a read-only file can only be
+/// opened for reading.
+///
+/// A symbolic link is followed, unless `NOFOLLOW` is given and the open
+/// fails.
+pub fn open_inode(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+    let (readable, writable) = flags.read_write();
+    let inode = match flags.find(name) {
+        Some(inode) => {
+            let special = !matches!(inode.inode_type(), InodeType::File | InodeType::Directory);
+            if special || flags.contains(OpenFlags::CREATE | OpenFlags::EXCL) {
+                return None;
+            }
+            let read_only = inode.is_dir() || inode.read_only();
+            if read_only && (writable || flags.contains(OpenFlags::TRUNC)) {
+                return None;
+            }
+            if flags.contains(OpenFlags::TRUNC) {
+                // clear size
+                inode.clear();
+            }
+            inode
+        }
+        // create file
+        None if flags.contains(OpenFlags::CREATE) => {
+            let (dir, name) = find_parent(name)?;
+            dir.create(name, InodeType::File)?
+        }
+        None => return None,
+    };
+    Some(Arc::new(OSInode::new(
+        readable,
+        writable,
+        flags.contains(OpenFlags::APPEND),
+        inode,
+    )))
+}
+
+/// Read from an inode at `offset` into `buf`
+fn read_buf(inode: &Arc<dyn VfsInode>, mut offset: usize, mut buf: UserBuffer) -> usize {
+    let mut total_read_size = 0usize;
+    for slice in buf.buffers.iter_mut() {
+        let read_size = inode.read_at(offset, *slice);
+        if read_size == 0 {
+            break;
+        }
+        offset += read_size;
+        total_read_size += read_size;
+    }
+    total_read_size
+}
+
+/// Write `buf` into an inode at `offset`
+fn write_buf(inode: &Arc<dyn VfsInode>, mut offset: usize, buf: UserBuffer) -> usize {
+    let mut total_write_size = 0usize;
+    for slice in buf.buffers.iter() {
+        let write_size = inode.write_at(offset, *slice);
+        offset += write_size;
+        total_write_size += write_size;
+        // the filesystem is full
+        if write_size < slice.len() {
+            break;
+        }
     }
+    total_write_size
 }
 
 impl File for OSInode {
     fn readable(&self) -> bool { self.readable }
     fn writable(&self) -> bool { self.writable }
-    fn read(&self, mut buf: UserBuffer) -> usize {
+    fn read(&self, buf: UserBuffer) -> usize {
         let mut inner = self.inner.exclusive_access();
-        let mut total_read_size = 0usize;
-        for slice in buf.buffers.iter_mut() {
-            let read_size = inner.inode.read_at(inner.offset, *slice);
-            if read_size == 0 {
-                break;
-            }
-            inner.offset += read_size;
-            total_read_size += read_size;
+        // the offset of a directory counts entries
+        if inner.inode.is_dir() {
+            return 0;
         }
-        total_read_size
+        let read_size = read_buf(&inner.inode, inner.offset, buf);
+        inner.offset += read_size;
+        read_size
     }
     fn write(&self, buf: UserBuffer) -> usize {
         let mut inner = self.inner.exclusive_access();
-        let mut total_write_size = 0usize;
-        for slice in buf.buffers.iter() {
-            let write_size = inner.inode.write_at(inner.offset, *slice);
-            assert_eq!(write_size, slice.len());
-            inner.offset += write_size;
-            total_write_size += write_size;
-        }
-        total_write_size
+        if self.append {
+            inner.offset = inner.inode.size();
+        }
+        let write_size = write_buf(&inner.inode, inner.offset, buf);
+        inner.offset += write_size;
+        write_size
+    }
+    fn read_at(&self, offset: usize, buf: UserBuffer) -> Option<usize> {
+        let inner = self.inner.exclusive_access();
+        if inner.inode.is_dir() {
+            return None;
+        }
+        Some(read_buf(&inner.inode, offset, buf))
+    }
+    fn as_inode(&self) -> Option<&OSInode> {
+        Some(self)
+    }
+    fn write_at(&self, offset: usize, buf: UserBuffer) -> Option<usize> {
+        let inner = self.inner.exclusive_access();
+        if offset > inner.inode.max_size() {
+            return None;
+        }
+        Some(write_buf(&inner.inode, offset, buf))
+    }
+    fn seek(&self, pos: SeekFrom) -> Option<usize> {
+        let mut inner = self.inner.exclusive_access();
+        if inner.inode.is_dir() {
+            // only rewinding is meaningful for entries
+            return match pos {
+                SeekFrom::Start(0) => {
+                    inner.offset = 0;
+                    Some(0)
+                }
+                _ => None,
+            };
+        }
+        let offset = match pos {
+            SeekFrom::Start(offset) => offset as isize,
+            SeekFrom::Current(delta) => inner.offset as isize + delta,
+            SeekFrom::End(delta) => inner.inode.size() as isize + delta,
+        };
+        if offset < 0 || offset as usize > inner.inode.max_size() {
+            return None;
+        }
+        inner.offset = offset as usize;
+        Some(inner.offset)
     }
 }
@@ -1,8 +1,20 @@
 mod stdio;
+mod devfs;
+mod easyfs;
+mod fifo;
 mod inode;
+mod mount;
+mod path;
 mod pipe;
+mod poll;
+mod procfs;
+mod socket;
+mod tmpfs;
+mod vfs;
 
 use crate::mm::UserBuffer;
+use crate::task::TaskControlBlock;
+use alloc::sync::Arc;
 
 /// The common abstraction of all IO resources
 pub trait File : Send + Sync {
@@ -10,6 +22,145 @@ pub trait File : Send + Sync {
     fn writable(&self) -> bool;
     fn read(&self, buf: UserBuffer) -> usize;
     fn write(&self, buf: UserBuffer) -> usize;
+    /// Read without waiting, `None` if nothing can be read yet
+    ///
+    /// Files whose `read` may wait after reading part of `buf` should
+    /// override this.
+    fn read_nonblock(&self, buf: UserBuffer) -> Option<usize> {
+        if self.poll().intersects(PollEvents::IN | PollEvents::HUP | PollEvents::ERR) {
+            Some(self.read(buf))
+        } else {
+            None
+        }
+    }
+    /// Write without waiting, `None` if nothing can be written yet
+    ///
+    /// Files whose `write` may wait after writing part of `buf` should
+    /// override this.
+    fn write_nonblock(&self, buf: UserBuffer) -> Option<usize> {
+        if self.poll().intersects(PollEvents::OUT | PollEvents::ERR) {
+            Some(self.write(buf))
+        } else {
+            None
+        }
+    }
+    /// Read from `offset` without moving the offset of the file, `None` if
+    /// the file is not seekable
+    fn read_at(&self, _offset: usize, _buf: UserBuffer) -> Option<usize> {
+        None
+    }
+    /// Write at `offset` without moving the offset of the file, `None` if
+    /// the file is not seekable or cannot grow past `offset`
+    fn write_at(&self, _offset: usize, _buf: UserBuffer) -> Option<usize> {
+        None
+    }
+    /// Move the offset of the file, return the new offset or `None` if it
+    /// would be negative or past the largest size of the file, or the file
+    /// is not seekable
+    fn seek(&self, _pos: SeekFrom) -> Option<usize> {
+        None
+    }
+    /// The socket behind the file, if it is one
+    fn as_socket(&self) -> Option<&Socket> {
+        None
+    }
+    /// The inode behind the file, if it is one
+    fn as_inode(&self) -> Option<&OSInode> {
+        None
+    }
+    /// The pipe behind the file, if it is one
+    fn as_pipe(&self) -> Option<&Pipe> {
+        None
+    }
+    /// The events the file is ready for right now
+    fn poll(&self) -> PollEvents {
+        let mut events = PollEvents::empty();
+        if self.readable() {
+            events |= PollEvents::IN;
+        }
+        if self.writable() {
+            events |= PollEvents::OUT;
+        }
+        events
+    }
+    /// Wake `task` up once the readiness of the file may have changed,
+    /// return false if the file cannot tell and has to be polled again
+    fn register_waiter(&self, _task: Arc<TaskControlBlock>) -> bool {
+        false
+    }
+    /// Stop waking `task` up, return whether it was registered
+    fn unregister_waiter(&self, _task: &Arc<TaskControlBlock>) -> bool {
+        false
+    }
+}
+
+/// Where to move the offset of a file to
+#[derive(Clone, Copy)]
+pub enum SeekFrom {
+    Start(usize),
+    Current(isize),
+    End(isize),
+}
+
+bitflags! {
+    /// Flags of a file descriptor, with the bits of the matching [`OpenFlags`]
+    pub struct FdFlags: u32 {
+        /// reads and writes return instead of waiting
+        const NONBLOCK = 1 << 11;
+        /// the fd is closed by exec
+        const CLOEXEC = 1 << 19;
+    }
+}
+
+/// An entry in the fd table of a process
+#[derive(Clone)]
+pub struct FileDescriptor {
+    pub file: Arc<dyn File + Send + Sync>,
+    pub flags: FdFlags,
+}
+
+impl FileDescriptor {
+    pub fn new(file: Arc<dyn File + Send + Sync>, flags: FdFlags) -> Self {
+        Self { file, flags }
+    }
+
+    /// Read into `buf`, return -2 instead of waiting if the fd is non-blocking
+    /// and -1 if the file was not opened for reading
+    pub fn read(&self, buf: UserBuffer) -> isize {
+        if !self.file.readable() {
+            return -1;
+        }
+        if self.flags.contains(FdFlags::NONBLOCK) {
+            self.file.read_nonblock(buf).map_or(-2, |size| size as isize)
+        } else {
+            self.file.read(buf) as isize
+        }
+    }
+
+    /// Write `buf`, return -2 instead of waiting if the fd is non-blocking
+    /// and -1 if the file was not opened for writing
+    ///
+    /// Return -1 as well if nothing is written because the file is broken,
+    /// like a pipe whose read ends are all closed.
+    pub fn write(&self, buf: UserBuffer) -> isize {
+        if !self.file.writable() {
+            return -1;
+        }
+        let len = buf.len();
+        let size = if self.flags.contains(FdFlags::NONBLOCK) {
+            match self.file.write_nonblock(buf) {
+                Some(size) => size,
+                None => return -2,
+            }
+        } else {
+            self.file.write(buf)
+        };
+        if size == 0 && len > 0 && self.file.poll().contains(PollEvents::ERR) {
+            -1
+        } else {
+            size as isize
+        }
+    }
 }
 
 /// The stat of a inode
@@ -28,6 +179,18 @@ pub struct Stat {
     pad: [u64; 7],
 }
 
+impl Stat {
+    pub fn new(dev: u64, ino: u64, mode: StatMode, nlink: u32) -> Self {
+        Self {
+            dev,
+            ino,
+            mode,
+            nlink,
+            pad: [0; 7],
+        }
+    }
+}
+
 bitflags! {
     /// The mode of a inode
     /// whether a directory or a file
@@ -37,9 +200,21 @@ bitflags! {
         const DIR   = 0o040000;
         /// ordinary regular file
         const FILE  = 0o100000;
+        /// named pipe
+        const FIFO  = 0o010000;
+        /// character device
+        const CHR   = 0o020000;
+        /// symbolic link
+        const LNK   = 0o120000;
     }
 }    
 
 pub use stdio::{Stdin, Stdout};
-pub use inode::{OSInode, open_file, OpenFlags, list_apps};
-pub use pipe::{Pipe, make_pipe};
+pub use inode::{OSInode, open_file, open_inode, make_dir, make_fifo, make_symlink, read_link, rename, stat, unlink, OpenFlags, list_apps};
+pub use mount::{init, mount, umount, UmountError};
+pub use path::absolute_path;
+pub use fifo::remove_fifo_waiter;
+pub use pipe::{Pipe, PipeRingBuffer, make_pipe};
+pub use poll::{PollEvents, WaitQueue};
+pub use socket::{Socket, SocketKind};
+pub use vfs::InodeType;
@@ -0,0 +1,154 @@
+//! The mount table
+//!
+//! Each mount point is the normalized path of a directory, whose place in
+//! the tree the root directory of the mounted filesystem takes. easy-fs on
+//! the root disk is mounted at `/` from the start and stays there, and a
+//! tmpfs at `/tmp`, a devfs at `/dev` and a procfs at `/proc` once [`init`]
+//! runs.
+
+use super::devfs::DevFs;
+use super::easyfs::EasyFs;
+use super::inode::make_dir;
+use super::path::find_inode;
+use super::procfs::ProcFs;
+use super::tmpfs::TmpFs;
+use super::vfs::{FileSystem, VfsInode};
+use crate::drivers::{block_device, BLOCK_DEVICE};
+use crate::sync::UPSafeCell;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// A filesystem mounted somewhere in the tree
+struct Mount {
+    /// Normalized path of the mount point
+    path: String,
+    /// Where the filesystem came from, a device path or `none`
+    source: String,
+    fs: Arc<dyn FileSystem>,
+}
+
+lazy_static! {
+    static ref MOUNTS: UPSafeCell<Vec<Mount>> = unsafe {
+        UPSafeCell::new(vec![Mount {
+            path: String::from("/"),
+            source: String::from("/dev/vda"),
+            fs: EasyFs::open(BLOCK_DEVICE.clone()).expect("Error loading EFS!"),
+        }])
+    };
+}
+
+/// Why a filesystem could not be unmounted
+pub enum UmountError {
+    /// Nothing is mounted there
+    NotMounted,
+    /// Files in it are open, or another filesystem is mounted inside it
+    Busy,
+}
+
+/// Mount a tmpfs at `/tmp` for scratch files, a devfs at `/dev` and a
+/// procfs at `/proc`, making the directories if needed
+pub fn init() {
+    for (target, fs_type) in [("/tmp", "tmpfs"), ("/dev", "devfs"), ("/proc", "procfs")] {
+        if find_inode(target).is_none() {
+            make_dir(target);
+        }
+        assert!(mount("none", target, fs_type));
+    }
+}
+
+/// The root directory of the filesystem mounted at the normalized `path`
+pub fn mounted_root(path: &str) -> Option<Arc<dyn VfsInode>> {
+    let mounts = MOUNTS.exclusive_access();
+    let fs = mounts
+        .iter()
+        .find(|mount| mount.path == path)
+        .map(|mount| Arc::clone(&mount.fs))?;
+    drop(mounts);
+    Some(fs.root_inode())
+}
+
+/// List the mount point, source and type of each mounted filesystem
+pub fn mounts() -> Vec<(String, String, &'static str)> {
+    MOUNTS
+        .exclusive_access()
+        .iter()
+        .map(|mount| (mount.path.clone(), mount.source.clone(), mount.fs.fs_type()))
+        .collect()
+}
+
+/// Create a filesystem of type `fs_type` from `source`
+fn new_fs(source: &str, fs_type: &str) -> Option<Arc<dyn FileSystem>> {
+    match fs_type {
+        "easyfs" => {
+            // a disk is only mounted once, so its blocks have one owner
+            let in_use = MOUNTS
+                .exclusive_access()
+                .iter()
+                .any(|mount| mount.source == source);
+            if in_use {
+                return None;
+            }
+            let device = block_device(source.strip_prefix("/dev/")?)?;
+            EasyFs::open(device).map(|fs| fs as Arc<dyn FileSystem>)
+        }
+        "tmpfs" => Some(TmpFs::new()),
+        "devfs" => Some(DevFs::new()),
+        "procfs" => Some(ProcFs::new()),
+        _ => None,
+    }
+}
+
+/// Mount a filesystem of type `fs_type` from `source` on the directory at
+/// the normalized path `target`
+///
+/// Return false if `target` is not a directory or a mount point already, or
+/// the filesystem cannot be created.
+pub fn mount(source: &str, target: &str, fs_type: &str) -> bool {
+    if !find_inode(target).map_or(false, |inode| inode.is_dir()) {
+        return false;
+    }
+    if MOUNTS
+        .exclusive_access()
+        .iter()
+        .any(|mount| mount.path == target)
+    {
+        return false;
+    }
+    match new_fs(source, fs_type) {
+        Some(fs) => {
+            MOUNTS.exclusive_access().push(Mount {
+                path: String::from(target),
+                source: String::from(source),
+                fs,
+            });
+            true
+        }
+        None => false,
+    }
+}
+
+/// Unmount the filesystem mounted at the normalized path `target`
+pub fn umount(target: &str) -> Result<(), UmountError> {
+    let mut mounts = MOUNTS.exclusive_access();
+    if target == "/" {
+        return Err(UmountError::Busy);
+    }
+    let idx = mounts
+        .iter()
+        .position(|mount| mount.path == target)
+        .ok_or(UmountError::NotMounted)?;
+    let nested = mounts
+        .iter()
+        .any(|mount| mount.path.starts_with(target) && mount.path[target.len()..].starts_with('/'));
+    // only the mount table holds the filesystem once no inode of it is open
+    if nested || Arc::strong_count(&mounts[idx].fs) > 1 {
+        return Err(UmountError::Busy);
+    }
+    let mount = mounts.remove(idx);
+    drop(mounts);
+    drop(mount);
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! Paths in the directory tree
+//!
+//! Paths are made absolute against the working directory of a process and
+//! normalized, so `.` and `..` never reach the filesystem, which has no
+//! such entries. This happens before symbolic links are followed, so `..`
+//! after a link leaves the directory holding the link, not its target.
+
+use super::mount::mounted_root;
+use super::vfs::VfsInode;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Join `path` to the absolute directory `cwd` unless it is absolute itself,
+/// and resolve `.` and `..`
+pub fn absolute_path(cwd: &str, path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    let base = if path.starts_with('/') { "" } else { cwd };
+    for component in base.split('/').chain(path.split('/')) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+    let mut absolute = String::new();
+    for name in components.iter() {
+        absolute.push('/');
+        absolute.push_str(name);
+    }
+    if absolute.is_empty() {
+        absolute.push('/');
+    }
+    absolute
+}
+
+/// Most symbolic links followed while finding one path, so links pointing
+/// at each other cannot loop forever
+const MAX_SYMLINKS: usize = 8;
+
+/// Find the inode at a normalized path, relative paths start at the root
+///
+/// A mount point leads to the root of the filesystem mounted there, and a
+/// symbolic link to what it points to.
+pub fn find_inode(path: &str) -> Option<Arc<dyn VfsInode>> {
+    lookup(path, true)
+}
+
+/// Find the inode at a normalized path like [`find_inode`], but give a
+/// symbolic link in the last component itself
+pub fn find_inode_nofollow(path: &str) -> Option<Arc<dyn VfsInode>> {
+    lookup(path, false)
+}
+
+/// Walk a normalized path from the root, following symbolic links before
+/// the last component, and in it with `follow`
+fn lookup(path: &str, follow: bool) -> Option<Arc<dyn VfsInode>> {
+    lookup_trail(path, follow)?.pop()
+}
+
+/// Walk a normalized path like [`lookup`], return the inodes on the way,
+/// the root first and the inode at the path last
+fn lookup_trail(path: &str, follow: bool) -> Option<Vec<Arc<dyn VfsInode>>> {
+    let mut path = String::from(path);
+    let mut trail = Vec::new();
+    for _ in 0..=MAX_SYMLINKS {
+        match walk(&path, follow, &mut trail)? {
+            Walk::Found => return Some(trail),
+            Walk::Link(next) => path = next,
+        }
+    }
+    None
+}
+
+/// Where walking a path ended
+enum Walk {
+    /// The inode at the path is the last of the trail
+    Found,
+    /// A symbolic link was met, walk this path instead
+    Link(String),
+}
+
+fn walk(path: &str, follow: bool, trail: &mut Vec<Arc<dyn VfsInode>>) -> Option<Walk> {
+    trail.clear();
+    let mut inode = mounted_root("/")?;
+    trail.push(Arc::clone(&inode));
+    let mut prefix = String::new();
+    let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+    for (idx, name) in names.iter().enumerate() {
+        let dir_len = prefix.len();
+        prefix.push('/');
+        prefix.push_str(name);
+        inode = match mounted_root(&prefix) {
+            Some(root) => root,
+            None => inode.find(name)?,
+        };
+        trail.push(Arc::clone(&inode));
+        if let Some(target) = inode.read_link() {
+            if follow || idx + 1 < names.len() {
+                // a relative target starts at the directory holding the link
+                let mut rest = target;
+                for name in names[idx + 1..].iter() {
+                    rest.push('/');
+                    rest.push_str(name);
+                }
+                let dir = if dir_len == 0 { "/" } else { &prefix[..dir_len] };
+                return Some(Walk::Link(absolute_path(dir, &rest)));
+            }
+        }
+    }
+    Some(Walk::Found)
+}
+
+/// Find the directory holding the last component of a normalized path,
+/// return it with that component, `None` for the root
+pub fn find_parent(path: &str) -> Option<(Arc<dyn VfsInode>, &str)> {
+    let (dir, name) = split_parent(path)?;
+    Some((find_inode(dir)?, name))
+}
+
+/// Find the inodes from the root to the directory holding the last
+/// component of a normalized path, following all symbolic links, `None`
+/// for the root
+pub fn find_parent_trail(path: &str) -> Option<Vec<Arc<dyn VfsInode>>> {
+    let (dir, _) = split_parent(path)?;
+    lookup_trail(dir, true)
+}
+
+/// Split a normalized path into the directory and the last component,
+/// `None` for the root
+fn split_parent(path: &str) -> Option<(&str, &str)> {
+    let path = path.trim_end_matches('/');
+    let (dir, name) = match path.rfind('/') {
+        Some(idx) => (&path[..idx], &path[idx + 1..]),
+        None => ("", path),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some((dir, name))
+}
@@ -1,9 +1,17 @@
-use super::File;
+use super::{File, PollEvents, WaitQueue};
 use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::config::PAGE_SIZE;
 use crate::sync::UPSafeCell;
 use crate::mm::UserBuffer;
 
-use crate::task::suspend_current_and_run_next;
+use crate::task::{block_current_and_run_next, current_task, current_task_killed, TaskControlBlock};
+
+/// Capacity of a new pipe
+const PIPE_BUFFER_SIZE: usize = PAGE_SIZE;
+/// Largest capacity a pipe can be given
+pub const PIPE_MAX_SIZE: usize = 16 * PAGE_SIZE;
 
 /// One end of a pipe
 pub struct Pipe {
@@ -29,82 +37,215 @@ impl Pipe {
             buffer,
         }
     }
-}
-
-const RING_BUFFER_SIZE: usize = 32;
+    /// Get the capacity of the pipe
+    pub fn capacity(&self) -> usize {
+        self.buffer.exclusive_access().capacity()
+    }
+    /// Change the capacity of the pipe
+    ///
+    /// Return the new capacity, or `None` if it is 0, more than
+    /// [`PIPE_MAX_SIZE`] or too small for the data in the pipe.
+    pub fn set_capacity(&self, capacity: usize) -> Option<usize> {
+        if capacity == 0 || capacity > PIPE_MAX_SIZE {
+            return None;
+        }
+        let mut ring_buffer = self.buffer.exclusive_access();
+        if ring_buffer.resize(capacity) {
+            Some(capacity)
+        } else {
+            None
+        }
+    }
 
-#[derive(Copy, Clone, PartialEq)]
-enum RingBufferStatus {
-    FULL,
-    EMPTY,
-    NORMAL,
+    /// Fill `buf`, or with `nonblock` take only what is there already and
+    /// return `None` if there is nothing
+    fn read_inner(&self, buf: UserBuffer, nonblock: bool) -> Option<usize> {
+        assert_eq!(self.readable(), true);
+        let mut read_size = 0usize;
+        for mut segment in buf.buffers.into_iter() {
+            while !segment.is_empty() {
+                let mut ring_buffer = self.buffer.exclusive_access();
+                if ring_buffer.available_read() == 0 {
+                    if ring_buffer.all_write_ends_closed() || current_task_killed() {
+                        return Some(read_size);
+                    }
+                    if nonblock {
+                        return if read_size == 0 { None } else { Some(read_size) };
+                    }
+                    ring_buffer.waiters.add(current_task().unwrap());
+                    drop(ring_buffer);
+                    block_current_and_run_next();
+                    continue;
+                }
+                let size = ring_buffer.read_slice(segment);
+                read_size += size;
+                segment = &mut core::mem::take(&mut segment)[size..];
+            }
+        }
+        Some(read_size)
+    }
+    /// Write all of `buf`, or with `nonblock` only what fits and return
+    /// `None` if nothing does
+    ///
+    /// Writing stops early once all read ends are closed.
+    fn write_inner(&self, buf: UserBuffer, nonblock: bool) -> Option<usize> {
+        assert_eq!(self.writable(), true);
+        let mut write_size = 0usize;
+        for mut segment in buf.buffers.into_iter() {
+            while !segment.is_empty() {
+                let mut ring_buffer = self.buffer.exclusive_access();
+                if ring_buffer.all_read_ends_closed() || current_task_killed() {
+                    return Some(write_size);
+                }
+                if ring_buffer.available_write() == 0 {
+                    if nonblock {
+                        return if write_size == 0 { None } else { Some(write_size) };
+                    }
+                    ring_buffer.waiters.add(current_task().unwrap());
+                    drop(ring_buffer);
+                    block_current_and_run_next();
+                    continue;
+                }
+                let size = ring_buffer.write_slice(segment);
+                write_size += size;
+                segment = &mut core::mem::take(&mut segment)[size..];
+            }
+        }
+        Some(write_size)
+    }
 }
 
 /// The underlying ring buffer of a pipe
 pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
+    arr: Vec<u8>,
     head: usize,
-    tail: usize,
-    status: RingBufferStatus,
+    /// Length of the data in the buffer
+    len: usize,
+    read_end: Option<Weak<Pipe>>,
     write_end: Option<Weak<Pipe>>,
+    /// Times the read end of a FIFO was opened, so a writer waiting for a
+    /// reader sees one came even if it is gone again
+    read_opens: usize,
+    /// Times the write end of a FIFO was opened
+    write_opens: usize,
+    /// Tasks waiting on or polling either end
+    waiters: WaitQueue,
 }
 
 impl PipeRingBuffer {
     pub fn new() -> Self {
         Self {
-            arr: [0; RING_BUFFER_SIZE],
+            arr: vec![0; PIPE_BUFFER_SIZE],
             head: 0,
-            tail: 0,
-            status: RingBufferStatus::EMPTY,
+            len: 0,
+            read_end: None,
             write_end: None,
+            read_opens: 0,
+            write_opens: 0,
+            waiters: WaitQueue::new(),
         }
     }
+    /// Set the read end bound to this buffer
+    pub fn set_read_end(&mut self, read_end: &Arc<Pipe>) {
+        self.read_end = Some(Arc::downgrade(read_end));
+    }
     /// Set the write end bound to this buffer
     pub fn set_write_end(&mut self, write_end: &Arc<Pipe>) {
         self.write_end = Some(Arc::downgrade(write_end));
     }
-    /// Write into the buffer
-    pub fn write_byte(&mut self, byte: u8) {
-        self.status = RingBufferStatus::NORMAL;
-        self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::FULL;
+    /// Count an open of the write end, or else the read end, and wake the
+    /// tasks waiting for it
+    pub fn count_open(&mut self, writable: bool) {
+        if writable {
+            self.write_opens += 1;
+        } else {
+            self.read_opens += 1;
+        }
+        self.waiters.wake_all();
+    }
+    /// Get the times the write end, or else the read end, was opened
+    pub fn opens(&self, writable: bool) -> usize {
+        if writable {
+            self.write_opens
+        } else {
+            self.read_opens
+        }
+    }
+    /// Wait for a change to either end, until `task` is woken
+    pub fn add_waiter(&mut self, task: Arc<TaskControlBlock>) {
+        self.waiters.add(task);
+    }
+    /// Stop `task` waiting, return false if it was not
+    pub fn remove_waiter(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        self.waiters.remove(task)
+    }
+    /// Get the capacity of the buffer
+    pub fn capacity(&self) -> usize {
+        self.arr.len()
+    }
+    /// Copy as much of `src` into the buffer as fits, return the length copied
+    pub fn write_slice(&mut self, src: &[u8]) -> usize {
+        let size = src.len().min(self.available_write());
+        let tail = (self.head + self.len) % self.capacity();
+        // the part up to the end of the array, then the wrapped part
+        let first = size.min(self.capacity() - tail);
+        self.arr[tail..tail + first].copy_from_slice(&src[..first]);
+        self.arr[..size - first].copy_from_slice(&src[first..size]);
+        self.len += size;
+        if size > 0 {
+            self.waiters.wake_all();
         }
+        size
     }
-    /// Read from the buffer
-    pub fn read_byte(&mut self) -> u8 {
-        self.status = RingBufferStatus::NORMAL;
-        let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
-        if self.head == self.tail {
-            self.status = RingBufferStatus::EMPTY;
+    /// Copy as much of the buffer into `dst` as fits, return the length copied
+    pub fn read_slice(&mut self, dst: &mut [u8]) -> usize {
+        let size = dst.len().min(self.len);
+        let first = size.min(self.capacity() - self.head);
+        dst[..first].copy_from_slice(&self.arr[self.head..self.head + first]);
+        dst[first..size].copy_from_slice(&self.arr[..size - first]);
+        self.head = (self.head + size) % self.capacity();
+        self.len -= size;
+        if size > 0 {
+            self.waiters.wake_all();
         }
-        c
+        size
+    }
+    /// Move the data into a buffer of `capacity` bytes, return false if it
+    /// does not fit
+    pub fn resize(&mut self, capacity: usize) -> bool {
+        if capacity < self.len {
+            return false;
+        }
+        let mut arr = vec![0; capacity];
+        let len = self.read_slice(&mut arr);
+        self.arr = arr;
+        self.head = 0;
+        self.len = len;
+        true
     }
     /// Get the length of remaining data in the buffer
     pub fn available_read(&self) -> usize {
-        if self.status == RingBufferStatus::EMPTY {
-            0
-        } else {
-            if self.tail > self.head {
-                self.tail - self.head
-            } else {
-                self.tail + RING_BUFFER_SIZE - self.head
-            }
-        }
+        self.len
     }
     /// Get the length of remaining space in the buffer
     pub fn available_write(&self) -> usize {
-        if self.status == RingBufferStatus::FULL {
-            0
-        } else {
-            RING_BUFFER_SIZE - self.available_read()
-        }
+        self.capacity() - self.len
+    }
+    /// Get the read end bound to this buffer, if it is still open
+    pub fn read_end(&self) -> Option<Arc<Pipe>> {
+        self.read_end.as_ref().and_then(|read_end| read_end.upgrade())
+    }
+    /// Get the write end bound to this buffer, if it is still open
+    pub fn write_end(&self) -> Option<Arc<Pipe>> {
+        self.write_end.as_ref().and_then(|write_end| write_end.upgrade())
+    }
+    /// Check if all read ends bounded to this buffer are closed
+    pub fn all_read_ends_closed(&self) -> bool {
+        self.read_end().is_none()
     }
     /// Check if all write ends bounded to this buffer are closed
     pub fn all_write_ends_closed(&self) -> bool {
-        self.write_end.as_ref().unwrap().upgrade().is_none()
+        self.write_end().is_none()
     }
 }
 
@@ -120,60 +261,61 @@ pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
     let write_end = Arc::new(
         Pipe::write_end_with_buffer(buffer.clone())
     );
+    buffer.exclusive_access().set_read_end(&read_end);
     buffer.exclusive_access().set_write_end(&write_end);
     (read_end, write_end)
 }
 
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // tasks waiting for the other end
+        self.buffer.exclusive_access().waiters.wake_all();
+    }
+}
+
 impl File for Pipe {
     fn readable(&self) -> bool { self.readable }
     fn writable(&self) -> bool { self.writable }
     fn read(&self, buf: UserBuffer) -> usize {
-        assert_eq!(self.readable(), true);
-        let mut buf_iter = buf.into_iter();
-        let mut read_size = 0usize;
-        loop {
-            let mut ring_buffer = self.buffer.exclusive_access();
-            let loop_read = ring_buffer.available_read();
-            if loop_read == 0 {
-                if ring_buffer.all_write_ends_closed() {
-                    return read_size;
-                }
-                drop(ring_buffer);
-                suspend_current_and_run_next();
-                continue;
-            }
-            // read at most loop_read bytes
-            for _ in 0..loop_read {
-                if let Some(byte_ref) = buf_iter.next() {
-                    unsafe { *byte_ref = ring_buffer.read_byte(); }
-                    read_size += 1;
-                } else {
-                    return read_size;
-                }
-            }
-        }
+        self.read_inner(buf, false).unwrap()
     }
     fn write(&self, buf: UserBuffer) -> usize {
-        assert_eq!(self.writable(), true);
-        let mut buf_iter = buf.into_iter();
-        let mut write_size = 0usize;
-        loop {
-            let mut ring_buffer = self.buffer.exclusive_access();
-            let loop_write = ring_buffer.available_write();
-            if loop_write == 0 {
-                drop(ring_buffer);
-                suspend_current_and_run_next();
-                continue;
+        self.write_inner(buf, false).unwrap()
+    }
+    fn read_nonblock(&self, buf: UserBuffer) -> Option<usize> {
+        self.read_inner(buf, true)
+    }
+    fn write_nonblock(&self, buf: UserBuffer) -> Option<usize> {
+        self.write_inner(buf, true)
+    }
+    fn poll(&self) -> PollEvents {
+        let ring_buffer = self.buffer.exclusive_access();
+        let mut events = PollEvents::empty();
+        if self.readable {
+            if ring_buffer.available_read() > 0 {
+                events |= PollEvents::IN;
             }
-            // write at most loop_write bytes
-            for _ in 0..loop_write {
-                if let Some(byte_ref) = buf_iter.next() {
-                    ring_buffer.write_byte(unsafe { *byte_ref });
-                    write_size += 1;
-                } else {
-                    return write_size;
-                }
+            if ring_buffer.all_write_ends_closed() {
+                events |= PollEvents::HUP;
+            }
+        }
+        if self.writable {
+            if ring_buffer.all_read_ends_closed() {
+                events |= PollEvents::ERR;
+            } else if ring_buffer.available_write() > 0 {
+                events |= PollEvents::OUT;
             }
         }
+        events
+    }
+    fn register_waiter(&self, task: Arc<TaskControlBlock>) -> bool {
+        self.buffer.exclusive_access().waiters.add(task);
+        true
+    }
+    fn unregister_waiter(&self, task: &Arc<TaskControlBlock>) -> bool {
+        self.buffer.exclusive_access().waiters.remove(task)
+    }
+    fn as_pipe(&self) -> Option<&Pipe> {
+        Some(self)
     }
 }
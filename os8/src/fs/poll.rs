@@ -0,0 +1,58 @@
+//! Readiness of files for poll and select
+
+use crate::task::{wakeup_task, TaskControlBlock};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+bitflags! {
+    /// Events a file is ready for, with the bits of `struct pollfd`
+    pub struct PollEvents: u16 {
+        /// there is data to read
+        const IN    = 0x001;
+        /// writing would not wait
+        const OUT   = 0x004;
+        /// the other end of a pipe or socket can no longer read
+        const ERR   = 0x008;
+        /// the other end of a pipe or socket has been closed
+        const HUP   = 0x010;
+        /// the fd is not open
+        const NVAL  = 0x020;
+    }
+}
+
+/// Tasks polling a file, woken whenever its readiness may have changed
+pub struct WaitQueue {
+    tasks: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self {
+            tasks: VecDeque::new(),
+        }
+    }
+
+    pub fn add(&mut self, task: Arc<TaskControlBlock>) {
+        if !self.tasks.iter().any(|t| Arc::ptr_eq(t, &task)) {
+            self.tasks.push_back(task);
+        }
+    }
+
+    /// Remove a task, return whether it was waiting
+    pub fn remove(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        match self.tasks.iter().position(|t| Arc::ptr_eq(t, task)) {
+            Some(idx) => {
+                self.tasks.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wake up and remove all waiting tasks
+    pub fn wake_all(&mut self) {
+        while let Some(task) = self.tasks.pop_front() {
+            wakeup_task(task);
+        }
+    }
+}
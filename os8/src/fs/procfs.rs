@@ -0,0 +1,338 @@
+//! Kernel state as read-only files, mounted at `/proc`
+//!
+//! The root holds files about the whole system and a directory for each
+//! process, named by its pid, with `self` for the process looking. The
+//! content of a file is made again on each read, so it is as fresh as the
+//! read is.
+
+use super::mount::mounts;
+use super::vfs::{alloc_dev, DirEntry, FileSystem, InodeType, VfsInode};
+use super::FdFlags;
+use crate::config::PAGE_SIZE;
+use crate::mm::{frame_stats, MapPermission};
+use crate::task::{current_process, pid2process, pids};
+use crate::timer::get_time_ms;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::Write;
+use easy_fs::block_cache_stats;
+
+/// The files about the whole system, whose inode numbers count from 2
+const GLOBAL_FILES: [(&str, fn() -> String); 4] = [
+    ("meminfo", meminfo),
+    ("uptime", uptime),
+    ("blockcache", blockcache),
+    ("mounts", mounts_list),
+];
+
+/// The files in the directory of a process, made from its pid, `None` once
+/// the process has exited
+const PROCESS_FILES: [(&str, fn(usize) -> Option<String>); 5] = [
+    ("status", status),
+    ("threads", threads),
+    ("fds", fds),
+    ("maps", maps),
+    ("syscalls", syscalls),
+];
+
+/// A procfs filesystem
+pub struct ProcFs {
+    dev: usize,
+}
+
+impl ProcFs {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { dev: alloc_dev() })
+    }
+}
+
+impl FileSystem for ProcFs {
+    fn fs_type(&self) -> &'static str {
+        "procfs"
+    }
+    fn root_inode(self: Arc<Self>) -> Arc<dyn VfsInode> {
+        Arc::new(ProcFsInode {
+            fs: self,
+            node: Node::Root,
+        })
+    }
+}
+
+/// What an inode of a procfs shows
+#[derive(Clone, Copy)]
+enum Node {
+    Root,
+    /// One of [`GLOBAL_FILES`]
+    Global(usize),
+    /// The directory of the process with the pid
+    Process(usize),
+    /// One of [`PROCESS_FILES`] of the process with the pid
+    ProcessFile(usize, usize),
+}
+
+impl Node {
+    fn ino(&self) -> usize {
+        match *self {
+            Node::Root => 1,
+            Node::Global(idx) => 2 + idx,
+            Node::Process(pid) => (pid + 1) << 4,
+            Node::ProcessFile(pid, idx) => ((pid + 1) << 4) + 1 + idx,
+        }
+    }
+    fn inode_type(&self) -> InodeType {
+        match self {
+            Node::Root | Node::Process(_) => InodeType::Directory,
+            Node::Global(_) | Node::ProcessFile(..) => InodeType::File,
+        }
+    }
+    /// Make the content of a file, `None` for a directory or a process that
+    /// has exited
+    fn content(&self) -> Option<String> {
+        match *self {
+            Node::Global(idx) => Some((GLOBAL_FILES[idx].1)()),
+            Node::ProcessFile(pid, idx) => (PROCESS_FILES[idx].1)(pid),
+            _ => None,
+        }
+    }
+}
+
+/// The root directory of a procfs, or a directory or file in it
+struct ProcFsInode {
+    fs: Arc<ProcFs>,
+    node: Node,
+}
+
+impl VfsInode for ProcFsInode {
+    fn dev(&self) -> usize {
+        self.fs.dev
+    }
+    fn ino(&self) -> usize {
+        self.node.ino()
+    }
+    fn inode_type(&self) -> InodeType {
+        self.node.inode_type()
+    }
+    fn size(&self) -> usize {
+        self.node.content().map_or(0, |content| content.len())
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let content = match self.node.content() {
+            Some(content) => content,
+            None => return 0,
+        };
+        let bytes = content.as_bytes();
+        if offset >= bytes.len() {
+            return 0;
+        }
+        let len = buf.len().min(bytes.len() - offset);
+        buf[..len].copy_from_slice(&bytes[offset..offset + len]);
+        len
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> usize {
+        0
+    }
+    fn clear(&self) {}
+    fn find(&self, name: &str) -> Option<Arc<dyn VfsInode>> {
+        let node = match self.node {
+            Node::Root => {
+                if let Some(idx) = GLOBAL_FILES.iter().position(|(file, _)| *file == name) {
+                    Node::Global(idx)
+                } else if name == "self" {
+                    Node::Process(current_process().getpid())
+                } else {
+                    let pid = name.parse().ok()?;
+                    pid2process(pid)?;
+                    Node::Process(pid)
+                }
+            }
+            Node::Process(pid) => {
+                pid2process(pid)?;
+                let idx = PROCESS_FILES.iter().position(|(file, _)| *file == name)?;
+                Node::ProcessFile(pid, idx)
+            }
+            _ => return None,
+        };
+        Some(Arc::new(Self {
+            fs: Arc::clone(&self.fs),
+            node,
+        }))
+    }
+    fn create(&self, _name: &str, _type_: InodeType) -> Option<Arc<dyn VfsInode>> {
+        None
+    }
+    fn read_dir(&self) -> Vec<DirEntry> {
+        let nodes: Vec<(String, Node)> = match self.node {
+            Node::Root => {
+                let mut nodes: Vec<(String, Node)> = GLOBAL_FILES
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, (name, _))| (String::from(*name), Node::Global(idx)))
+                    .collect();
+                nodes.push((
+                    String::from("self"),
+                    Node::Process(current_process().getpid()),
+                ));
+                for pid in pids() {
+                    nodes.push((format!("{}", pid), Node::Process(pid)));
+                }
+                nodes
+            }
+            Node::Process(pid) => PROCESS_FILES
+                .iter()
+                .enumerate()
+                .map(|(idx, (name, _))| (String::from(*name), Node::ProcessFile(pid, idx)))
+                .collect(),
+            _ => Vec::new(),
+        };
+        nodes
+            .into_iter()
+            .map(|(name, node)| DirEntry {
+                name,
+                ino: node.ino(),
+                type_: node.inode_type(),
+            })
+            .collect()
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn read_only(&self) -> bool {
+        true
+    }
+}
+
+/// Frames in all and free ones
+fn meminfo() -> String {
+    let (total, free) = frame_stats();
+    format!(
+        "page_size: {}\ntotal_frames: {}\nfree_frames: {}\n",
+        PAGE_SIZE, total, free
+    )
+}
+
+/// Time since boot in seconds
+fn uptime() -> String {
+    let ms = get_time_ms();
+    format!("{}.{:03}\n", ms / 1000, ms % 1000)
+}
+
+/// Blocks the block cache holds and how often it had them
+fn blockcache() -> String {
+    let stats = block_cache_stats();
+    format!(
+        "capacity: {}\ncached: {}\nhits: {}\nmisses: {}\n",
+        stats.capacity, stats.cached, stats.hits, stats.misses
+    )
+}
+
+/// The mount table, a line of source, mount point and type per filesystem
+fn mounts_list() -> String {
+    let mut content = String::new();
+    for (path, source, fs_type) in mounts() {
+        writeln!(content, "{} {} {}", source, path, fs_type).unwrap();
+    }
+    content
+}
+
+/// Pid, parent pid, number of threads and working directory
+fn status(pid: usize) -> Option<String> {
+    let process = pid2process(pid)?;
+    let inner = process.inner_exclusive_access();
+    let ppid = inner
+        .parent
+        .as_ref()
+        .and_then(Weak::upgrade)
+        .map_or(0, |parent| parent.getpid());
+    let threads = inner.tasks.iter().filter(|task| task.is_some()).count();
+    Some(format!(
+        "pid: {}\nppid: {}\nthreads: {}\ncwd: {}\n",
+        pid, ppid, threads, inner.cwd
+    ))
+}
+
+/// A line of tid and status per thread
+fn threads(pid: usize) -> Option<String> {
+    let process = pid2process(pid)?;
+    let inner = process.inner_exclusive_access();
+    let mut content = String::new();
+    for (tid, task) in inner.tasks.iter().enumerate() {
+        if let Some(task) = task {
+            let status = task.inner_exclusive_access().task_status;
+            writeln!(content, "{} {:?}", tid, status).unwrap();
+        }
+    }
+    Some(content)
+}
+
+/// A line of fd, access, kind and flags per open file
+fn fds(pid: usize) -> Option<String> {
+    let process = pid2process(pid)?;
+    let inner = process.inner_exclusive_access();
+    let mut content = String::new();
+    for (fd, file) in inner.fd_table.iter().enumerate() {
+        if let Some(file) = file {
+            let kind = if file.file.as_inode().is_some() {
+                "inode"
+            } else if file.file.as_pipe().is_some() {
+                "pipe"
+            } else if file.file.as_socket().is_some() {
+                "socket"
+            } else {
+                "other"
+            };
+            write!(
+                content,
+                "{} {}{} {}",
+                fd,
+                if file.file.readable() { 'r' } else { '-' },
+                if file.file.writable() { 'w' } else { '-' },
+                kind
+            )
+            .unwrap();
+            if file.flags.contains(FdFlags::NONBLOCK) {
+                content.push_str(" nonblock");
+            }
+            if file.flags.contains(FdFlags::CLOEXEC) {
+                content.push_str(" cloexec");
+            }
+            content.push('\n');
+        }
+    }
+    Some(content)
+}
+
+/// A line of start, end and permissions per area of the address space
+fn maps(pid: usize) -> Option<String> {
+    let process = pid2process(pid)?;
+    let inner = process.inner_exclusive_access();
+    let mut content = String::new();
+    for (start, end, perm) in inner.memory_set.area_ranges() {
+        let flag = |bit: MapPermission, c: char| if perm.contains(bit) { c } else { '-' };
+        writeln!(
+            content,
+            "{:#x}-{:#x} {}{}{}{}",
+            usize::from(start),
+            usize::from(end),
+            flag(MapPermission::R, 'r'),
+            flag(MapPermission::W, 'w'),
+            flag(MapPermission::X, 'x'),
+            flag(MapPermission::U, 'u'),
+        )
+        .unwrap();
+    }
+    Some(content)
+}
+
+/// A line of syscall id and number of calls per syscall made
+fn syscalls(pid: usize) -> Option<String> {
+    let process = pid2process(pid)?;
+    let inner = process.inner_exclusive_access();
+    let mut content = String::new();
+    for (id, count) in inner.syscall_counts.iter() {
+        writeln!(content, "{} {}", id, count).unwrap();
+    }
+    Some(content)
+}
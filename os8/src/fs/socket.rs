@@ -0,0 +1,484 @@
+//! Local sockets in an abstract namespace
+//!
+//! A bound socket is found by its name, which is released again once the
+//! socket is closed. Each socket receives into a channel of its own, and a
+//! connected socket sends into the channel of its peer. Besides data, a
+//! channel carries files to pass open file descriptors on.
+
+use super::{File, PollEvents, WaitQueue};
+use crate::mm::UserBuffer;
+use crate::sync::UPSafeCell;
+use crate::task::{
+    block_current_and_run_next, current_task, current_task_killed, TaskControlBlock,
+};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use lazy_static::*;
+
+/// Bytes a stream channel holds before senders have to wait
+const STREAM_BUFFER_SIZE: usize = 4096;
+/// Datagrams a channel holds before senders have to wait
+const DGRAM_QUEUE_LEN: usize = 16;
+/// Longer datagrams are cut off at this length
+const DGRAM_MAX_LEN: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SocketKind {
+    Stream,
+    Datagram,
+}
+
+/// Data and files on their way to one socket
+struct Channel {
+    bytes: VecDeque<u8>,
+    datagrams: VecDeque<Vec<u8>>,
+    files: VecDeque<Arc<dyn File + Send + Sync>>,
+    /// The peer sending into a stream channel has been closed
+    writer_closed: bool,
+    /// The socket receiving from the channel has been closed
+    reader_closed: bool,
+    /// Tasks waiting on or polling the sockets at either end
+    waiters: WaitQueue,
+}
+
+impl Channel {
+    fn new() -> Arc<UPSafeCell<Self>> {
+        Arc::new(unsafe {
+            UPSafeCell::new(Self {
+                bytes: VecDeque::new(),
+                datagrams: VecDeque::new(),
+                files: VecDeque::new(),
+                writer_closed: false,
+                reader_closed: false,
+                waiters: WaitQueue::new(),
+            })
+        })
+    }
+}
+
+enum SocketState {
+    Unconnected,
+    Listening {
+        backlog: usize,
+        /// Connected sockets waiting to be accepted
+        pending: VecDeque<Arc<Socket>>,
+    },
+    Connected {
+        /// Channel of the peer
+        tx: Arc<UPSafeCell<Channel>>,
+    },
+}
+
+struct SocketInner {
+    kind: SocketKind,
+    name: Option<String>,
+    state: SocketState,
+    rx: Arc<UPSafeCell<Channel>>,
+}
+
+/// A local stream or datagram socket
+pub struct Socket {
+    inner: Arc<UPSafeCell<SocketInner>>,
+}
+
+lazy_static! {
+    /// Bound sockets by name
+    static ref SOCKET_NAMES: UPSafeCell<BTreeMap<String, Weak<UPSafeCell<SocketInner>>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Find the socket bound to `name`
+fn lookup(name: &str) -> Option<Arc<UPSafeCell<SocketInner>>> {
+    SOCKET_NAMES
+        .exclusive_access()
+        .get(name)
+        .and_then(|socket| socket.upgrade())
+}
+
+impl Socket {
+    pub fn new(kind: SocketKind) -> Self {
+        Self::with_state(kind, SocketState::Unconnected, Channel::new())
+    }
+
+    fn with_state(kind: SocketKind, state: SocketState, rx: Arc<UPSafeCell<Channel>>) -> Self {
+        Self {
+            inner: Arc::new(unsafe {
+                UPSafeCell::new(SocketInner {
+                    kind,
+                    name: None,
+                    state,
+                    rx,
+                })
+            }),
+        }
+    }
+
+    /// Give the socket a name, return false if it has one already or the
+    /// name is taken
+    pub fn bind(&self, name: String) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if inner.name.is_some() || lookup(&name).is_some() {
+            return false;
+        }
+        SOCKET_NAMES
+            .exclusive_access()
+            .insert(name.clone(), Arc::downgrade(&self.inner));
+        inner.name = Some(name);
+        true
+    }
+
+    /// Accept connections on a bound stream socket, at most `backlog` of
+    /// them waiting at once
+    pub fn listen(&self, backlog: usize) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if inner.kind != SocketKind::Stream || inner.name.is_none() {
+            return false;
+        }
+        match &mut inner.state {
+            SocketState::Unconnected => {
+                inner.state = SocketState::Listening {
+                    backlog: backlog.max(1),
+                    pending: VecDeque::new(),
+                };
+                true
+            }
+            SocketState::Listening { backlog: old, .. } => {
+                *old = backlog.max(1);
+                true
+            }
+            SocketState::Connected { .. } => false,
+        }
+    }
+
+    /// Wait for a connection on a listening socket, `None` if the socket is
+    /// not listening or the task is killed meanwhile
+    pub fn accept(&self) -> Option<Arc<Socket>> {
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            match &mut inner.state {
+                SocketState::Listening { pending, .. } => {
+                    if let Some(socket) = pending.pop_front() {
+                        return Some(socket);
+                    }
+                }
+                _ => return None,
+            }
+            if current_task_killed() {
+                return None;
+            }
+            let rx = Arc::clone(&inner.rx);
+            drop(inner);
+            // connect wakes the channel of the listener
+            rx.exclusive_access().waiters.add(current_task().unwrap());
+            block_current_and_run_next();
+        }
+    }
+
+    /// Connect to the socket bound to `name`, return false if there is none
+    /// of the same kind, it is not listening or its backlog is full
+    ///
+    /// A stream connection is set up at once and waits in the backlog of the
+    /// listener, a datagram socket just remembers where to send to.
+    pub fn connect(&self, name: &str) -> bool {
+        let target = match lookup(name) {
+            Some(target) => target,
+            None => return false,
+        };
+        if Arc::ptr_eq(&target, &self.inner) {
+            return false;
+        }
+        let mut inner = self.inner.exclusive_access();
+        if matches!(inner.state, SocketState::Listening { .. }) {
+            return false;
+        }
+        let mut target_inner = target.exclusive_access();
+        if target_inner.kind != inner.kind {
+            return false;
+        }
+        match inner.kind {
+            SocketKind::Datagram => {
+                inner.state = SocketState::Connected {
+                    tx: Arc::clone(&target_inner.rx),
+                };
+            }
+            SocketKind::Stream => {
+                if matches!(inner.state, SocketState::Connected { .. }) {
+                    return false;
+                }
+                let (backlog, pending) = match &mut target_inner.state {
+                    SocketState::Listening { backlog, pending } => (*backlog, pending),
+                    _ => return false,
+                };
+                if pending.len() >= backlog {
+                    return false;
+                }
+                let server = Socket::with_state(
+                    SocketKind::Stream,
+                    SocketState::Connected {
+                        tx: Arc::clone(&inner.rx),
+                    },
+                    Channel::new(),
+                );
+                inner.state = SocketState::Connected {
+                    tx: Arc::clone(&server.inner.exclusive_access().rx),
+                };
+                pending.push_back(Arc::new(server));
+                target_inner.rx.exclusive_access().waiters.wake_all();
+            }
+        }
+        true
+    }
+
+    /// Channel of the peer
+    fn tx(&self) -> Option<Arc<UPSafeCell<Channel>>> {
+        match &self.inner.exclusive_access().state {
+            SocketState::Connected { tx } => Some(Arc::clone(tx)),
+            _ => None,
+        }
+    }
+
+    /// Send the contents of `buf` to the peer, waiting for room
+    ///
+    /// Return the number of bytes sent, or -1 if the socket is not connected
+    /// or the peer has been closed before anything was sent.
+    pub fn send(&self, buf: UserBuffer) -> isize {
+        let tx = match self.tx() {
+            Some(tx) => tx,
+            None => return -1,
+        };
+        let kind = self.inner.exclusive_access().kind;
+        let len = buf.len();
+        let mut buf_iter = buf.into_iter();
+        let mut sent = 0;
+        loop {
+            let mut channel = tx.exclusive_access();
+            if channel.reader_closed {
+                return if sent == 0 { -1 } else { sent as isize };
+            }
+            match kind {
+                SocketKind::Stream => {
+                    while channel.bytes.len() < STREAM_BUFFER_SIZE {
+                        match buf_iter.next() {
+                            Some(byte_ref) => channel.bytes.push_back(unsafe { *byte_ref }),
+                            None => return sent as isize,
+                        }
+                        channel.waiters.wake_all();
+                        sent += 1;
+                    }
+                }
+                SocketKind::Datagram => {
+                    if channel.datagrams.len() < DGRAM_QUEUE_LEN {
+                        let datagram: Vec<u8> = buf_iter
+                            .by_ref()
+                            .take(DGRAM_MAX_LEN)
+                            .map(|byte_ref| unsafe { *byte_ref })
+                            .collect();
+                        channel.datagrams.push_back(datagram);
+                        channel.waiters.wake_all();
+                        return len.min(DGRAM_MAX_LEN) as isize;
+                    }
+                }
+            }
+            if current_task_killed() {
+                return sent as isize;
+            }
+            channel.waiters.add(current_task().unwrap());
+            drop(channel);
+            block_current_and_run_next();
+        }
+    }
+
+    /// Receive into `buf`, waiting for data
+    ///
+    /// A datagram is taken as a whole and what does not fit is dropped.
+    /// Return the number of bytes received, 0 once the peer of a stream has
+    /// been closed and everything is read, or -1 for a listening socket.
+    pub fn recv(&self, buf: UserBuffer) -> isize {
+        let inner = self.inner.exclusive_access();
+        if matches!(inner.state, SocketState::Listening { .. }) {
+            return -1;
+        }
+        let (kind, rx) = (inner.kind, Arc::clone(&inner.rx));
+        drop(inner);
+        let mut buf_iter = buf.into_iter();
+        loop {
+            let mut channel = rx.exclusive_access();
+            match kind {
+                SocketKind::Stream if !channel.bytes.is_empty() => {
+                    channel.waiters.wake_all();
+                    let mut received = 0;
+                    while let Some(byte_ref) = buf_iter.next() {
+                        match channel.bytes.pop_front() {
+                            Some(byte) => unsafe { *byte_ref = byte },
+                            None => break,
+                        }
+                        received += 1;
+                    }
+                    return received;
+                }
+                SocketKind::Datagram if !channel.datagrams.is_empty() => {
+                    let datagram = channel.datagrams.pop_front().unwrap();
+                    channel.waiters.wake_all();
+                    let mut received = 0;
+                    for (byte_ref, byte) in buf_iter.zip(datagram) {
+                        unsafe { *byte_ref = byte };
+                        received += 1;
+                    }
+                    return received;
+                }
+                _ => {}
+            }
+            if channel.writer_closed || current_task_killed() {
+                return 0;
+            }
+            channel.waiters.add(current_task().unwrap());
+            drop(channel);
+            block_current_and_run_next();
+        }
+    }
+
+    /// Whether the socket receives from or sends into `channel`
+    fn uses_channel(&self, channel: &Arc<UPSafeCell<Channel>>) -> bool {
+        let inner = self.inner.exclusive_access();
+        Arc::ptr_eq(&inner.rx, channel)
+            || matches!(&inner.state, SocketState::Connected { tx } if Arc::ptr_eq(tx, channel))
+    }
+
+    /// Pass an open file to the peer, return false if not connected, the
+    /// peer has been closed or the file is this socket or its peer
+    ///
+    /// A socket queued in a channel it uses would keep itself alive.
+    pub fn send_file(&self, file: Arc<dyn File + Send + Sync>) -> bool {
+        match self.tx() {
+            Some(tx) => {
+                if file
+                    .as_socket()
+                    .map_or(false, |socket| socket.uses_channel(&tx))
+                {
+                    return false;
+                }
+                let mut channel = tx.exclusive_access();
+                if channel.reader_closed {
+                    return false;
+                }
+                channel.files.push_back(file);
+                channel.waiters.wake_all();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wait for a file passed by the peer, `None` once the peer of a stream
+    /// has been closed without passing one
+    pub fn recv_file(&self) -> Option<Arc<dyn File + Send + Sync>> {
+        let rx = Arc::clone(&self.inner.exclusive_access().rx);
+        loop {
+            let mut channel = rx.exclusive_access();
+            if let Some(file) = channel.files.pop_front() {
+                return Some(file);
+            }
+            if channel.writer_closed || current_task_killed() {
+                return None;
+            }
+            channel.waiters.add(current_task().unwrap());
+            drop(channel);
+            block_current_and_run_next();
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let inner = self.inner.exclusive_access();
+        let mut rx = inner.rx.exclusive_access();
+        rx.reader_closed = true;
+        rx.waiters.wake_all();
+        drop(rx);
+        if let SocketState::Connected { tx } = &inner.state {
+            // datagram channels have any number of senders
+            if inner.kind == SocketKind::Stream {
+                let mut tx = tx.exclusive_access();
+                tx.writer_closed = true;
+                tx.waiters.wake_all();
+            }
+        }
+        if let Some(name) = inner.name.as_ref() {
+            let mut names = SOCKET_NAMES.exclusive_access();
+            let ours = names
+                .get(name)
+                .map_or(false, |socket| socket.ptr_eq(&Arc::downgrade(&self.inner)));
+            if ours {
+                names.remove(name);
+            }
+        }
+    }
+}
+
+impl File for Socket {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> usize {
+        self.recv(buf).max(0) as usize
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        self.send(buf).max(0) as usize
+    }
+    fn as_socket(&self) -> Option<&Socket> {
+        Some(self)
+    }
+    fn poll(&self) -> PollEvents {
+        let inner = self.inner.exclusive_access();
+        let mut events = PollEvents::empty();
+        let rx = inner.rx.exclusive_access();
+        match &inner.state {
+            SocketState::Listening { pending, .. } => {
+                if !pending.is_empty() {
+                    events |= PollEvents::IN;
+                }
+                return events;
+            }
+            SocketState::Connected { tx } => {
+                let tx = tx.exclusive_access();
+                if tx.reader_closed {
+                    events |= PollEvents::ERR;
+                } else if match inner.kind {
+                    SocketKind::Stream => tx.bytes.len() < STREAM_BUFFER_SIZE,
+                    SocketKind::Datagram => tx.datagrams.len() < DGRAM_QUEUE_LEN,
+                } {
+                    events |= PollEvents::OUT;
+                }
+            }
+            SocketState::Unconnected => {}
+        }
+        if !rx.bytes.is_empty() || !rx.datagrams.is_empty() || !rx.files.is_empty() {
+            events |= PollEvents::IN;
+        }
+        if rx.writer_closed {
+            events |= PollEvents::IN | PollEvents::HUP;
+        }
+        events
+    }
+    fn register_waiter(&self, task: Arc<TaskControlBlock>) -> bool {
+        let inner = self.inner.exclusive_access();
+        inner.rx.exclusive_access().waiters.add(Arc::clone(&task));
+        if let SocketState::Connected { tx } = &inner.state {
+            tx.exclusive_access().waiters.add(task);
+        }
+        true
+    }
+    fn unregister_waiter(&self, task: &Arc<TaskControlBlock>) -> bool {
+        let inner = self.inner.exclusive_access();
+        let mut registered = inner.rx.exclusive_access().waiters.remove(task);
+        if let SocketState::Connected { tx } = &inner.state {
+            registered |= tx.exclusive_access().waiters.remove(task);
+        }
+        registered
+    }
+}
@@ -1,23 +1,44 @@
-use super::File;
+use super::{File, PollEvents};
 use crate::mm::{UserBuffer};
 use crate::sbi::console_getchar;
-use crate::task::suspend_current_and_run_next;
+use crate::sync::UPSafeCell;
+use crate::task::{current_task_killed, suspend_current_and_run_next};
+use lazy_static::*;
 
 /// The standard input
 pub struct Stdin;
 /// The standard output
 pub struct Stdout;
 
+lazy_static! {
+    /// A character taken from the console by poll but not read yet
+    static ref STDIN_PENDING: UPSafeCell<Option<u8>> = unsafe { UPSafeCell::new(None) };
+}
+
+/// Take a character from the console, 0 if there is none
+fn stdin_getchar() -> usize {
+    match STDIN_PENDING.exclusive_access().take() {
+        Some(ch) => ch as usize,
+        None => console_getchar(),
+    }
+}
+
 impl File for Stdin {
     fn readable(&self) -> bool { true }
     fn writable(&self) -> bool { false }
+    /// Read a single character, waiting for it
     fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
+        if user_buf.len() == 0 {
+            return 0;
+        }
         // busy loop
         let mut c: usize;
         loop {
-            c = console_getchar();
+            c = stdin_getchar();
             if c == 0 {
+                if current_task_killed() {
+                    return 0;
+                }
                 suspend_current_and_run_next();
                 continue;
             } else {
@@ -25,12 +46,28 @@ impl File for Stdin {
             }
         }
         let ch = c as u8;
-        unsafe { user_buf.buffers[0].as_mut_ptr().write_volatile(ch); }
+        let first = user_buf.buffers.iter_mut().find(|buffer| !buffer.is_empty()).unwrap();
+        unsafe { first.as_mut_ptr().write_volatile(ch); }
         1
     }
     fn write(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot write to stdin!");
     }
+    // the console raises no interrupts, so pollers cannot be woken up
+    fn poll(&self) -> PollEvents {
+        let mut pending = STDIN_PENDING.exclusive_access();
+        if pending.is_none() {
+            let c = console_getchar();
+            if c != 0 {
+                *pending = Some(c as u8);
+            }
+        }
+        if pending.is_some() {
+            PollEvents::IN
+        } else {
+            PollEvents::empty()
+        }
+    }
 }
 
 impl File for Stdout {
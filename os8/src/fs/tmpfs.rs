@@ -0,0 +1,323 @@
+//! A filesystem kept in memory
+//!
+//! The data of a file lives in whole frames, which are freed once the file
+//! is unlinked and closed. Nothing reaches the disk, and everything is gone
+//! once the filesystem is unmounted.
+
+use super::vfs::{alloc_dev, DirEntry, FileSystem, InodeType, VfsInode};
+use crate::config::PAGE_SIZE;
+use crate::mm::{frame_alloc, frame_stats, FrameTracker};
+use crate::sync::UPSafeCell;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The largest size of a file
+const MAX_FILE_SIZE: usize = 16 * 1024 * 1024;
+/// Frames a file never takes, so the kernel keeps some to run on
+const RESERVED_FRAMES: usize = 256;
+
+/// A tmpfs filesystem
+pub struct TmpFs {
+    dev: usize,
+    root: Arc<TmpNode>,
+    /// Inode number of the next node
+    next_ino: AtomicUsize,
+}
+
+impl TmpFs {
+    /// Create an empty filesystem
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            dev: alloc_dev(),
+            root: Arc::new(TmpNode::new(0, InodeType::Directory)),
+            next_ino: AtomicUsize::new(1),
+        })
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn fs_type(&self) -> &'static str {
+        "tmpfs"
+    }
+    fn root_inode(self: Arc<Self>) -> Arc<dyn VfsInode> {
+        let node = Arc::clone(&self.root);
+        Arc::new(TmpFsInode { fs: self, node })
+    }
+}
+
+/// A file, directory or named pipe in a tmpfs
+struct TmpNode {
+    ino: usize,
+    type_: InodeType,
+    inner: UPSafeCell<TmpNodeInner>,
+}
+
+struct TmpNodeInner {
+    /// Data of a file, a frame for each page
+    frames: Vec<FrameTracker>,
+    /// Size of the data of a file in bytes
+    size: usize,
+    /// Entries of a directory in the order they were created
+    entries: Vec<(String, Arc<TmpNode>)>,
+}
+
+impl TmpNode {
+    fn new(ino: usize, type_: InodeType) -> Self {
+        Self {
+            ino,
+            type_,
+            inner: unsafe {
+                UPSafeCell::new(TmpNodeInner {
+                    frames: Vec::new(),
+                    size: 0,
+                    entries: Vec::new(),
+                })
+            },
+        }
+    }
+}
+
+impl TmpNodeInner {
+    /// Add zeroed frames until they hold `size` bytes, return false and add
+    /// none if that is past [`MAX_FILE_SIZE`] or the frames to spare
+    fn grow(&mut self, size: usize) -> bool {
+        if size > MAX_FILE_SIZE {
+            return false;
+        }
+        let old_len = self.frames.len();
+        let needed = ((size + PAGE_SIZE - 1) / PAGE_SIZE).saturating_sub(old_len);
+        if needed + RESERVED_FRAMES > frame_stats().1 {
+            return false;
+        }
+        for _ in 0..needed {
+            match frame_alloc() {
+                Some(frame) => self.frames.push(frame),
+                None => {
+                    self.frames.truncate(old_len);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// A node of a tmpfs behind the virtual filesystem layer
+struct TmpFsInode {
+    fs: Arc<TmpFs>,
+    node: Arc<TmpNode>,
+}
+
+impl TmpFsInode {
+    fn wrap(&self, node: Arc<TmpNode>) -> Arc<dyn VfsInode> {
+        Arc::new(Self {
+            fs: Arc::clone(&self.fs),
+            node,
+        })
+    }
+}
+
+impl VfsInode for TmpFsInode {
+    fn dev(&self) -> usize {
+        self.fs.dev
+    }
+    fn ino(&self) -> usize {
+        self.node.ino
+    }
+    fn inode_type(&self) -> InodeType {
+        self.node.type_
+    }
+    fn size(&self) -> usize {
+        self.node.inner.exclusive_access().size
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let inner = self.node.inner.exclusive_access();
+        let end = inner.size.min(offset.saturating_add(buf.len()));
+        let mut pos = offset;
+        while pos < end {
+            let page_offset = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - page_offset).min(end - pos);
+            let page = inner.frames[pos / PAGE_SIZE].ppn.get_bytes_array();
+            buf[pos - offset..pos - offset + len]
+                .copy_from_slice(&page[page_offset..page_offset + len]);
+            pos += len;
+        }
+        end.saturating_sub(offset)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        if !matches!(self.node.type_, InodeType::File | InodeType::Symlink) {
+            return 0;
+        }
+        let mut inner = self.node.inner.exclusive_access();
+        let mut end = offset.saturating_add(buf.len());
+        // new frames are zeroed, which fills any gap before `offset`; if
+        // there are not enough, only the frames already there are written
+        if !inner.grow(end) {
+            end = end.min(inner.frames.len() * PAGE_SIZE);
+            if end <= offset {
+                return 0;
+            }
+        }
+        let mut pos = offset;
+        while pos < end {
+            let page_offset = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - page_offset).min(end - pos);
+            let page = inner.frames[pos / PAGE_SIZE].ppn.get_bytes_array();
+            page[page_offset..page_offset + len]
+                .copy_from_slice(&buf[pos - offset..pos - offset + len]);
+            pos += len;
+        }
+        inner.size = inner.size.max(end);
+        end.saturating_sub(offset)
+    }
+    fn clear(&self) {
+        let mut inner = self.node.inner.exclusive_access();
+        inner.frames.clear();
+        inner.size = 0;
+    }
+    fn truncate(&self, size: usize) -> bool {
+        if self.node.type_ != InodeType::File {
+            return false;
+        }
+        let mut inner = self.node.inner.exclusive_access();
+        if size < inner.size {
+            inner.frames.truncate((size + PAGE_SIZE - 1) / PAGE_SIZE);
+            // zero the rest of the last page kept, or growing again shows it
+            if size % PAGE_SIZE != 0 {
+                let page = inner.frames[size / PAGE_SIZE].ppn.get_bytes_array();
+                page[size % PAGE_SIZE..].fill(0);
+            }
+        }
+        // new frames are zeroed
+        if !inner.grow(size) {
+            return false;
+        }
+        inner.size = size;
+        true
+    }
+    fn max_size(&self) -> usize {
+        MAX_FILE_SIZE
+    }
+    fn find(&self, name: &str) -> Option<Arc<dyn VfsInode>> {
+        let inner = self.node.inner.exclusive_access();
+        let node = inner
+            .entries
+            .iter()
+            .find(|(entry_name, _)| entry_name == name)
+            .map(|(_, node)| Arc::clone(node))?;
+        drop(inner);
+        Some(self.wrap(node))
+    }
+    fn create(&self, name: &str, type_: InodeType) -> Option<Arc<dyn VfsInode>> {
+        if self.node.type_ != InodeType::Directory || type_ == InodeType::CharDevice {
+            return None;
+        }
+        let mut inner = self.node.inner.exclusive_access();
+        if inner
+            .entries
+            .iter()
+            .any(|(entry_name, _)| entry_name == name)
+        {
+            return None;
+        }
+        let ino = self.fs.next_ino.fetch_add(1, Ordering::Relaxed);
+        let node = Arc::new(TmpNode::new(ino, type_));
+        inner.entries.push((String::from(name), Arc::clone(&node)));
+        drop(inner);
+        Some(self.wrap(node))
+    }
+    fn symlink(&self, name: &str, target: &str) -> bool {
+        match self.create(name, InodeType::Symlink) {
+            Some(link) => {
+                link.write_at(0, target.as_bytes());
+                true
+            }
+            None => false,
+        }
+    }
+    fn rename(&self, old_name: &str, new_dir: &dyn VfsInode, new_name: &str) -> bool {
+        let new_dir = match new_dir.as_any().downcast_ref::<Self>() {
+            Some(new_dir) if Arc::ptr_eq(&new_dir.fs, &self.fs) => new_dir,
+            _ => return false,
+        };
+        if new_dir.node.type_ != InodeType::Directory {
+            return false;
+        }
+        let node = match self
+            .node
+            .inner
+            .exclusive_access()
+            .entries
+            .iter()
+            .find(|(entry_name, _)| entry_name == old_name)
+        {
+            Some((_, node)) => Arc::clone(node),
+            None => return false,
+        };
+        let mut new_inner = new_dir.node.inner.exclusive_access();
+        match new_inner
+            .entries
+            .iter()
+            .position(|(entry_name, _)| entry_name == new_name)
+        {
+            Some(idx) => {
+                let target = &new_inner.entries[idx].1;
+                if Arc::ptr_eq(target, &node) {
+                    return true;
+                }
+                if target.type_ == InodeType::Directory || node.type_ == InodeType::Directory {
+                    return false;
+                }
+                new_inner.entries[idx].1 = Arc::clone(&node);
+            }
+            None => new_inner
+                .entries
+                .push((String::from(new_name), Arc::clone(&node))),
+        }
+        drop(new_inner);
+        // the new entry, if in this directory, has another name
+        self.node
+            .inner
+            .exclusive_access()
+            .entries
+            .retain(|(entry_name, _)| entry_name != old_name);
+        true
+    }
+    fn read_dir(&self) -> Vec<DirEntry> {
+        self.node
+            .inner
+            .exclusive_access()
+            .entries
+            .iter()
+            .map(|(name, node)| DirEntry {
+                name: name.clone(),
+                ino: node.ino,
+                type_: node.type_,
+            })
+            .collect()
+    }
+    fn unlink(&self, name: &str) -> bool {
+        let mut inner = self.node.inner.exclusive_access();
+        let idx = match inner
+            .entries
+            .iter()
+            .position(|(entry_name, _)| entry_name == name)
+        {
+            Some(idx) => idx,
+            None => return false,
+        };
+        // a directory goes only once it is empty
+        let node = &inner.entries[idx].1;
+        if node.type_ == InodeType::Directory && !node.inner.exclusive_access().entries.is_empty() {
+            return false;
+        }
+        inner.entries.remove(idx);
+        true
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
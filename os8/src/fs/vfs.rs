@@ -0,0 +1,126 @@
+//! The virtual filesystem layer
+//!
+//! Every filesystem implements [`FileSystem`] for the filesystem as a whole
+//! and [`VfsInode`] for the files and directories in it. Open files are
+//! [`super::OSInode`]s over a [`VfsInode`], and the mount table decides
+//! which filesystem a path leads into.
+
+use super::File;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Type of an inode
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InodeType {
+    File,
+    Directory,
+    Fifo,
+    CharDevice,
+    /// A symbolic link, whose data is the path it points to
+    Symlink,
+}
+
+/// An entry of a directory
+pub struct DirEntry {
+    pub name: String,
+    pub ino: usize,
+    pub type_: InodeType,
+}
+
+/// A mounted filesystem
+pub trait FileSystem: Send + Sync {
+    /// Name of the filesystem type, as given to mount
+    fn fs_type(&self) -> &'static str;
+    /// Get the root directory
+    fn root_inode(self: Arc<Self>) -> Arc<dyn VfsInode>;
+}
+
+/// A file, directory or named pipe in a filesystem
+///
+/// Inodes hold their filesystem, so the filesystem is in use as long as one
+/// of its inodes is.
+pub trait VfsInode: Send + Sync {
+    /// Device id of the filesystem holding the inode
+    fn dev(&self) -> usize;
+    /// Inode number, unique in the filesystem
+    fn ino(&self) -> usize;
+    fn inode_type(&self) -> InodeType;
+    /// Size of the data in bytes
+    fn size(&self) -> usize;
+    /// Read from `offset` into `buf`, return the length read
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize;
+    /// Write `buf` at `offset`, growing the file as needed, return the
+    /// length written
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize;
+    /// Drop all data of the file
+    fn clear(&self);
+    /// Change the size of the file to `size`, filling the space added with
+    /// zeros, return false if this is not a file or the filesystem cannot
+    fn truncate(&self, _size: usize) -> bool {
+        false
+    }
+    /// The largest size the file can have, so no offset goes past it
+    fn max_size(&self) -> usize {
+        usize::MAX
+    }
+    /// Find the inode named `name` in this directory, `None` if this is not
+    /// a directory
+    fn find(&self, name: &str) -> Option<Arc<dyn VfsInode>>;
+    /// Create an inode of type `type_` named `name` in this directory,
+    /// `None` if the name is taken or this is not a directory
+    fn create(&self, name: &str, type_: InodeType) -> Option<Arc<dyn VfsInode>>;
+    /// List the entries of this directory, empty if this is not one
+    fn read_dir(&self) -> Vec<DirEntry>;
+    /// Remove the entry `name` from this directory, a directory only if it
+    /// is empty, return false if that fails or the filesystem cannot
+    fn unlink(&self, _name: &str) -> bool {
+        false
+    }
+    /// Move the entry `old_name` of this directory to `new_name` in
+    /// `new_dir`, which may be this directory, replacing a file there
+    ///
+    /// Return false if there is no `old_name`, `new_dir` is in another
+    /// filesystem, a directory would be replaced or replace a file, or the
+    /// filesystem cannot.
+    fn rename(&self, _old_name: &str, _new_dir: &dyn VfsInode, _new_name: &str) -> bool {
+        false
+    }
+    /// Create a symbolic link to `target` named `name` in this directory,
+    /// return false if the name is taken or the filesystem cannot
+    fn symlink(&self, _name: &str, _target: &str) -> bool {
+        false
+    }
+    /// The path a symbolic link points to, `None` if this is not one
+    fn read_link(&self) -> Option<String> {
+        if self.inode_type() != InodeType::Symlink {
+            return None;
+        }
+        let mut target = vec![0u8; self.size()];
+        let len = self.read_at(0, &mut target);
+        target.truncate(len);
+        String::from_utf8(target).ok()
+    }
+    /// Open a device with the given access, `None` if this is not a device
+    fn open_device(&self, _readable: bool, _writable: bool) -> Option<Arc<dyn File + Send + Sync>> {
+        None
+    }
+    /// Whether the inode can only be opened for reading
+    fn read_only(&self) -> bool {
+        false
+    }
+    /// The inode as [`Any`], for a filesystem to tell its own inodes
+    fn as_any(&self) -> &dyn Any;
+    fn is_dir(&self) -> bool {
+        self.inode_type() == InodeType::Directory
+    }
+}
+
+/// Get a device id for a new filesystem
+pub fn alloc_dev() -> usize {
+    static NEXT_DEV: AtomicUsize = AtomicUsize::new(1);
+    NEXT_DEV.fetch_add(1, Ordering::Relaxed)
+}
@@ -67,6 +67,7 @@ pub fn rust_main() -> ! {
     trap::init();
     trap::enable_timer_interrupt();
     timer::set_next_trigger();
+    fs::init();
     fs::list_apps();
     task::add_initproc();
     task::run_tasks();
@@ -0,0 +1,73 @@
+use crate::sync::UPSafeCell;
+use crate::task::{block_current_and_run_next, current_task, wakeup_task, TaskControlBlock};
+use alloc::vec::Vec;
+use alloc::{collections::VecDeque, sync::Arc};
+
+/// Lets `parties` threads wait until all of them have arrived, then
+/// starts over for the next round
+pub struct Barrier {
+    pub inner: UPSafeCell<BarrierInner>,
+}
+
+pub struct BarrierInner {
+    pub parties: usize,
+    pub wait_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl Barrier {
+    pub fn new(parties: usize) -> Self {
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(BarrierInner {
+                    parties,
+                    wait_queue: VecDeque::new(),
+                })
+            },
+        }
+    }
+
+    /// Wait for the other parties, return true in the thread arriving last
+    pub fn wait(&self) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if inner.wait_queue.len() + 1 >= inner.parties {
+            let woken: Vec<_> = inner.wait_queue.drain(..).collect();
+            drop(inner);
+            for task in woken {
+                wakeup_task(task);
+            }
+            true
+        } else {
+            inner.wait_queue.push_back(current_task().unwrap());
+            drop(inner);
+            block_current_and_run_next();
+            false
+        }
+    }
+
+    /// Remove a task from the wait queue, return whether it was waiting
+    pub fn remove_waiter(&self, task: &Arc<TaskControlBlock>) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if let Some(idx) = inner.wait_queue.iter().position(|t| Arc::ptr_eq(t, task)) {
+            inner.wait_queue.remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether some task is blocked on the barrier
+    pub fn has_waiters(&self) -> bool {
+        !self.inner.exclusive_access().wait_queue.is_empty()
+    }
+
+    /// Number of parties and tids of the tasks that have arrived
+    pub fn get_waiting(&self) -> (usize, Vec<usize>) {
+        let inner = self.inner.exclusive_access();
+        let tids = inner
+            .wait_queue
+            .iter()
+            .map(|task| task.inner_exclusive_access().res.as_ref().unwrap().tid)
+            .collect();
+        (inner.parties, tids)
+    }
+}
@@ -1,5 +1,9 @@
 use crate::sync::{Mutex, UPSafeCell};
-use crate::task::{add_task, block_current_and_run_next, current_task, TaskControlBlock};
+use crate::task::{
+    block_current_and_run_next, current_task, wakeup_task, TaskControlBlock, TaskStatus,
+};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
+use alloc::vec::Vec;
 use alloc::{collections::VecDeque, sync::Arc};
 
 pub struct Condvar {
@@ -7,7 +11,8 @@ pub struct Condvar {
 }
 
 pub struct CondvarInner {
-    pub wait_queue: VecDeque<Arc<TaskControlBlock>>,
+    /// Waiting tasks and the mutexes they will take again once signaled
+    pub wait_queue: VecDeque<(Arc<TaskControlBlock>, Arc<dyn Mutex>)>,
 }
 
 impl Condvar {
@@ -23,17 +28,108 @@ impl Condvar {
 
     pub fn signal(&self) {
         let mut inner = self.inner.exclusive_access();
-        if let Some(task) = inner.wait_queue.pop_front() {
-            add_task(task);
+        if let Some((task, mutex)) = inner.wait_queue.pop_front() {
+            drop(inner);
+            Self::wake(task, mutex);
+        }
+    }
+
+    /// Wake up all waiting tasks, return how many there were
+    pub fn broadcast(&self) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        let waiting: Vec<_> = inner.wait_queue.drain(..).collect();
+        drop(inner);
+        let count = waiting.len();
+        for (task, mutex) in waiting {
+            Self::wake(task, mutex);
+        }
+        count
+    }
+
+    /// Move a signaled task over to the wait queue of its mutex, so that it is
+    /// woken up holding the lock rather than all of them racing for it
+    fn wake(task: Arc<TaskControlBlock>, mutex: Arc<dyn Mutex>) {
+        // a task whose timer has gone off is running already and locks by itself
+        if task.inner_exclusive_access().task_status == TaskStatus::Blocking {
+            remove_timer(&task);
+            if mutex.requeue(Arc::clone(&task)) {
+                return;
+            }
+        }
+        wakeup_task(task);
+    }
+
+    /// Whether some task is waiting for a signal
+    pub fn has_waiters(&self) -> bool {
+        !self.inner.exclusive_access().wait_queue.is_empty()
+    }
+
+    /// Tids of the waiting tasks together with the mutexes they wait to take again
+    pub fn get_waiting(&self) -> Vec<(usize, Arc<dyn Mutex>)> {
+        let inner = self.inner.exclusive_access();
+        inner
+            .wait_queue
+            .iter()
+            .filter_map(|(task, mutex)| {
+                let task_inner = task.inner_exclusive_access();
+                task_inner
+                    .res
+                    .as_ref()
+                    .map(|res| (res.tid, Arc::clone(mutex)))
+            })
+            .collect()
+    }
+
+    /// Remove a task from the wait queue, return whether it was waiting
+    pub fn remove_waiter(&self, task: &Arc<TaskControlBlock>) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if let Some(idx) = inner.wait_queue.iter().position(|(t, _)| Arc::ptr_eq(t, task)) {
+            inner.wait_queue.remove(idx);
+            true
+        } else {
+            false
         }
     }
 
     pub fn wait(&self, mutex: Arc<dyn Mutex>) {
         mutex.unlock();
         let mut inner = self.inner.exclusive_access();
-        inner.wait_queue.push_back(current_task().unwrap());
+        inner
+            .wait_queue
+            .push_back((current_task().unwrap(), Arc::clone(&mutex)));
         drop(inner);
         block_current_and_run_next();
+        relock(&mutex);
+    }
+
+    /// Like `wait` but give up after `timeout_ms`, return whether it was signaled
+    ///
+    /// The mutex is locked again in both cases.
+    pub fn wait_timeout(&self, mutex: Arc<dyn Mutex>, timeout_ms: usize) -> bool {
+        mutex.unlock();
+        let task = current_task().unwrap();
+        let mut inner = self.inner.exclusive_access();
+        inner
+            .wait_queue
+            .push_back((Arc::clone(&task), Arc::clone(&mutex)));
+        drop(inner);
+        add_timer(get_time_ms() + timeout_ms, Arc::clone(&task));
+        block_current_and_run_next();
+        // signal dequeues us, only the timer leaves us queued
+        let signaled = !self.remove_waiter(&task);
+        if signaled {
+            remove_timer(&task);
+        }
+        relock(&mutex);
+        signaled
+    }
+}
+
+/// Take the mutex back after a wait unless it has been handed over already
+fn relock(mutex: &Arc<dyn Mutex>) {
+    let task = current_task().unwrap();
+    let tid = task.inner_exclusive_access().res.as_ref().unwrap().tid;
+    if mutex.get_allocate_tid() != Some(tid) {
         mutex.lock();
     }
 }
@@ -0,0 +1,80 @@
+//! Futex wait queues keyed by user virtual address
+
+use crate::task::{wakeup_task, TaskControlBlock};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+
+/// Wait queues of all futexes of a process
+///
+/// A futex is nothing but a word in user memory, so queues are created on
+/// the first wait and dropped once they become empty.
+pub struct FutexTable {
+    queues: BTreeMap<usize, VecDeque<Arc<TaskControlBlock>>>,
+}
+
+impl FutexTable {
+    pub fn new() -> Self {
+        Self {
+            queues: BTreeMap::new(),
+        }
+    }
+
+    /// Append a task to the wait queue of the futex at `uaddr`
+    pub fn push(&mut self, uaddr: usize, task: Arc<TaskControlBlock>) {
+        self.queues
+            .entry(uaddr)
+            .or_insert_with(VecDeque::new)
+            .push_back(task);
+    }
+
+    /// Remove a task from whichever queue it waits in, return whether it was found
+    pub fn remove(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        let found = self.queues.iter().find_map(|(uaddr, queue)| {
+            queue
+                .iter()
+                .position(|waiting| Arc::ptr_eq(waiting, task))
+                .map(|idx| (*uaddr, idx))
+        });
+        if let Some((uaddr, idx)) = found {
+            let queue = self.queues.get_mut(&uaddr).unwrap();
+            queue.remove(idx);
+            if queue.is_empty() {
+                self.queues.remove(&uaddr);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Wake up at most `count` tasks waiting on `uaddr`, return how many were woken
+    pub fn wake(&mut self, uaddr: usize, count: usize) -> usize {
+        let mut woken = 0;
+        if let Some(queue) = self.queues.get_mut(&uaddr) {
+            while woken < count {
+                match queue.pop_front() {
+                    Some(task) => wakeup_task(task),
+                    None => break,
+                }
+                woken += 1;
+            }
+            if queue.is_empty() {
+                self.queues.remove(&uaddr);
+            }
+        }
+        woken
+    }
+
+    /// Wake up at most `count` tasks waiting on `uaddr` and move the others
+    /// to the queue of `uaddr2`, return how many were woken
+    pub fn requeue(&mut self, uaddr: usize, count: usize, uaddr2: usize) -> usize {
+        let woken = self.wake(uaddr, count);
+        if let Some(mut queue) = self.queues.remove(&uaddr) {
+            self.queues
+                .entry(uaddr2)
+                .or_insert_with(VecDeque::new)
+                .append(&mut queue);
+        }
+        woken
+    }
+}
@@ -0,0 +1,73 @@
+use crate::task::{wakeup_task, TaskControlBlock};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Most messages a mailbox holds
+pub const MAILBOX_CAPACITY: usize = 16;
+/// Longer messages are cut off at this length
+pub const MAIL_MAX_LEN: usize = 256;
+
+/// Bounded queue of messages sent to a process
+///
+/// It lives in the process control block, which already serializes access.
+pub struct Mailbox {
+    messages: VecDeque<Vec<u8>>,
+    /// Tasks blocked until a message arrives
+    wait_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl Mailbox {
+    pub fn new() -> Self {
+        Self {
+            messages: VecDeque::new(),
+            wait_queue: VecDeque::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.messages.len() >= MAILBOX_CAPACITY
+    }
+
+    /// Queue a message and wake up a receiver, return false if the mailbox is full
+    pub fn push(&mut self, mut message: Vec<u8>) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        message.truncate(MAIL_MAX_LEN);
+        self.messages.push_back(message);
+        if let Some(task) = self.wait_queue.pop_front() {
+            wakeup_task(task);
+        }
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        self.messages.pop_front()
+    }
+
+    /// Let a task wait for the next message, it has to block afterwards
+    pub fn add_waiter(&mut self, task: Arc<TaskControlBlock>) {
+        self.wait_queue.push_back(task);
+    }
+
+    /// Remove a task from the wait queue, return whether it was waiting
+    pub fn remove_waiter(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        if let Some(idx) = self.wait_queue.iter().position(|t| Arc::ptr_eq(t, task)) {
+            self.wait_queue.remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drop all messages of an exited process
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.wait_queue.clear();
+    }
+}
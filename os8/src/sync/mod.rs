@@ -1,11 +1,19 @@
 //! Synchronization and interior mutability primitives
 
+mod barrier;
 mod condvar;
+mod futex;
+mod mailbox;
 mod mutex;
+mod rwlock;
 mod semaphore;
 mod up;
 
+pub use barrier::Barrier;
 pub use condvar::Condvar;
+pub use futex::FutexTable;
+pub use mailbox::{Mailbox, MAILBOX_CAPACITY, MAIL_MAX_LEN};
 pub use mutex::{Mutex, MutexBlocking, MutexSpin};
+pub use rwlock::RwLock;
 pub use semaphore::Semaphore;
 pub use up::UPSafeCell;
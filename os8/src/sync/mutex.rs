@@ -1,21 +1,33 @@
 use super::UPSafeCell;
 use crate::task::TaskControlBlock;
-use crate::task::{add_task, current_task};
+use crate::task::{current_task, current_task_killed, update_current_priorities, wakeup_task};
 use crate::task::{block_current_and_run_next, suspend_current_and_run_next};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
 use alloc::vec::Vec;
 use alloc::{collections::VecDeque, sync::Arc};
 
 pub trait Mutex: Sync + Send {
     fn lock(&self);
+    /// Take the lock only if it is free, return whether it was taken
+    fn try_lock(&self) -> bool;
+    /// Wait at most `timeout_ms` for the lock, return whether it was taken
+    fn lock_timeout(&self, timeout_ms: usize) -> bool;
     fn unlock(&self);
     fn get_allocate_tid(&self)-> Option<usize>;
     fn get_waiting_tids(&self)-> Option<Vec<usize>>;
     fn get_count(&self) -> isize;
+    /// Remove a task from the wait queue, return whether it was waiting
+    fn remove_waiter(&self, task: &Arc<TaskControlBlock>) -> bool;
+    /// Make a blocked task wait for the lock as if it had called `lock`,
+    /// return false if the mutex cannot hold tasks in a queue
+    fn requeue(&self, task: Arc<TaskControlBlock>) -> bool;
 }
 
 pub struct MutexSpin {
     locked: UPSafeCell<bool>,
     allocate_tid: UPSafeCell<usize>,
+    /// Tids of the tasks spinning on the lock
+    spinning_tids: UPSafeCell<Vec<usize>>,
 }
 
 impl MutexSpin {
@@ -25,39 +37,76 @@ impl MutexSpin {
                 UPSafeCell::new(0)
             },
             locked: unsafe { UPSafeCell::new(false) },
+            spinning_tids: unsafe { UPSafeCell::new(Vec::new()) },
         }
     }
+
+    /// Spin until the lock is taken, the task is killed or `expire_ms` has
+    /// passed, return whether the lock was taken
+    fn spin_lock(&self, expire_ms: Option<usize>) -> bool {
+        if self.try_lock() {
+            return true;
+        }
+        let tid = current_tid();
+        self.spinning_tids.exclusive_access().push(tid);
+        update_current_priorities();
+        let taken = loop {
+            if current_task_killed() || expire_ms.map_or(false, |ms| get_time_ms() >= ms) {
+                break false;
+            }
+            suspend_current_and_run_next();
+            if self.try_lock() {
+                break true;
+            }
+        };
+        let mut spinning_tids = self.spinning_tids.exclusive_access();
+        let idx = spinning_tids.iter().position(|t| *t == tid).unwrap();
+        spinning_tids.swap_remove(idx);
+        drop(spinning_tids);
+        update_current_priorities();
+        taken
+    }
 }
 
 impl Mutex for MutexSpin {
     fn lock(&self) {
-        loop {
-            let mut locked = self.locked.exclusive_access();
-            if *locked {
-                drop(locked);
-                suspend_current_and_run_next();
-                continue;
-            } else {
-                *locked = true;
-                let mut tid = self.allocate_tid.exclusive_access();
-
-                let current_task = current_task().unwrap();
-                let current_task_inner = current_task.inner_exclusive_access();
-                let current_task_res = current_task_inner.res.as_ref().unwrap();
-                let td = current_task_res.tid;
-
-                *tid = td;
-                return;
-            }
+        self.spin_lock(None);
+    }
+
+    fn try_lock(&self) -> bool {
+        let mut locked = self.locked.exclusive_access();
+        if *locked {
+            return false;
         }
+        *locked = true;
+        *self.allocate_tid.exclusive_access() = current_tid();
+        true
+    }
+
+    fn lock_timeout(&self, timeout_ms: usize) -> bool {
+        self.spin_lock(Some(get_time_ms() + timeout_ms))
     }
 
     fn unlock(&self) {
         let mut locked = self.locked.exclusive_access();
         *locked = false;
+        drop(locked);
+        update_current_priorities();
     }
     fn get_waiting_tids(&self)-> Option<Vec<usize>> {
-        return None;
+        let spinning_tids = self.spinning_tids.exclusive_access();
+        let mut res = Vec::new();
+        for &tid in spinning_tids.iter() {
+            if res.len() < tid + 1 {
+                res.resize(tid + 1, 0);
+            }
+            res[tid] += 1;
+        }
+        if res.is_empty() {
+            None
+        } else {
+            Some(res)
+        }
     }
     fn get_allocate_tid(&self)-> Option<usize> {
         let mut locked = self.locked.exclusive_access();
@@ -76,6 +125,13 @@ impl Mutex for MutexSpin {
             return 1;
         }
     }
+    fn remove_waiter(&self, _task: &Arc<TaskControlBlock>) -> bool {
+        // spinning tasks stay in the ready queue
+        false
+    }
+    fn requeue(&self, _task: Arc<TaskControlBlock>) -> bool {
+        false
+    }
 }
 
 pub struct MutexBlocking {
@@ -108,6 +164,7 @@ impl Mutex for MutexBlocking {
         if mutex_inner.locked {
             mutex_inner.wait_queue.push_back(current_task().unwrap());
             drop(mutex_inner);
+            update_current_priorities();
             block_current_and_run_next();
         } else {
             mutex_inner.locked = true;
@@ -121,18 +178,60 @@ impl Mutex for MutexBlocking {
         }
     }
 
+    fn try_lock(&self) -> bool {
+        let mut mutex_inner = self.inner.exclusive_access();
+        if mutex_inner.locked {
+            return false;
+        }
+        mutex_inner.locked = true;
+        mutex_inner.allocate_tid = current_tid();
+        true
+    }
+
+    fn lock_timeout(&self, timeout_ms: usize) -> bool {
+        if self.try_lock() {
+            return true;
+        }
+        let task = current_task().unwrap();
+        self.inner
+            .exclusive_access()
+            .wait_queue
+            .push_back(Arc::clone(&task));
+        update_current_priorities();
+        add_timer(get_time_ms() + timeout_ms, Arc::clone(&task));
+        block_current_and_run_next();
+        // unlock hands the lock over and dequeues us, only the timer leaves us queued
+        if self.remove_waiter(&task) {
+            update_current_priorities();
+            return false;
+        }
+        remove_timer(&task);
+        true
+    }
+
     fn unlock(&self) {
         let mut mutex_inner = self.inner.exclusive_access();
         assert!(mutex_inner.locked);
-        if let Some(waking_task) = mutex_inner.wait_queue.pop_front() {
+        // hand the lock to the waiter of the highest priority, the first one on ties
+        let next = mutex_inner
+            .wait_queue
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, task)| task.inner_exclusive_access().effective_priority)
+            .map(|(idx, _)| idx);
+        if let Some(waking_task) = next.and_then(|idx| mutex_inner.wait_queue.remove(idx)) {
 
             let current_task_inner = waking_task.inner_exclusive_access();
             let current_task_res = current_task_inner.res.as_ref().unwrap();
             let td = current_task_res.tid;
             mutex_inner.allocate_tid = td;
             drop(current_task_inner);
+            drop(mutex_inner);
 
-            add_task(waking_task);
+            wakeup_task(waking_task);
+            // drop our boost and pass it on to the new holder
+            update_current_priorities();
         } else {
             mutex_inner.locked = false;
         }
@@ -180,4 +279,34 @@ impl Mutex for MutexBlocking {
             return 1;
         }
     }
+    fn remove_waiter(&self, task: &Arc<TaskControlBlock>) -> bool {
+        let mut mutex_inner = self.inner.exclusive_access();
+        if let Some(idx) = mutex_inner.wait_queue.iter().position(|t| Arc::ptr_eq(t, task)) {
+            mutex_inner.wait_queue.remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+    fn requeue(&self, task: Arc<TaskControlBlock>) -> bool {
+        let mut mutex_inner = self.inner.exclusive_access();
+        if mutex_inner.locked {
+            mutex_inner.wait_queue.push_back(task);
+            drop(mutex_inner);
+            update_current_priorities();
+        } else {
+            mutex_inner.locked = true;
+            mutex_inner.allocate_tid = task.inner_exclusive_access().res.as_ref().unwrap().tid;
+            drop(mutex_inner);
+            wakeup_task(task);
+        }
+        true
+    }
+}
+
+/// Thread id of the current task
+fn current_tid() -> usize {
+    let task = current_task().unwrap();
+    let task_inner = task.inner_exclusive_access();
+    task_inner.res.as_ref().unwrap().tid
 }
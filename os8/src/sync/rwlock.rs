@@ -0,0 +1,177 @@
+use crate::sync::UPSafeCell;
+use crate::task::{block_current_and_run_next, current_task, wakeup_task, TaskControlBlock};
+use alloc::vec;
+use alloc::vec::Vec;
+use alloc::{collections::VecDeque, sync::Arc};
+
+/// Reader-writer lock preferring readers, or writers if asked to
+///
+/// Readers keep coming in while a writer waits unless writers are
+/// preferred, in which case a waiting writer holds back new readers.
+pub struct RwLock {
+    pub inner: UPSafeCell<RwLockInner>,
+}
+
+pub struct RwLockInner {
+    pub writer_preferred: bool,
+    /// Tids holding the lock for reading, once per read lock taken
+    pub readers: Vec<usize>,
+    pub writer: Option<usize>,
+    /// Waiting tasks and whether they want to write
+    pub wait_queue: VecDeque<(Arc<TaskControlBlock>, bool)>,
+}
+
+impl RwLock {
+    pub fn new(writer_preferred: bool) -> Self {
+        Self {
+            inner: unsafe {
+                UPSafeCell::new(RwLockInner {
+                    writer_preferred,
+                    readers: Vec::new(),
+                    writer: None,
+                    wait_queue: VecDeque::new(),
+                })
+            },
+        }
+    }
+
+    pub fn read(&self) {
+        let mut inner = self.inner.exclusive_access();
+        let writer_waiting = inner.wait_queue.iter().any(|(_, write)| *write);
+        if inner.writer.is_none() && !(inner.writer_preferred && writer_waiting) {
+            inner.readers.push(current_tid());
+        } else {
+            inner.wait_queue.push_back((current_task().unwrap(), false));
+            drop(inner);
+            // the lock has been taken for us once we are woken up
+            block_current_and_run_next();
+        }
+    }
+
+    pub fn write(&self) {
+        let mut inner = self.inner.exclusive_access();
+        if inner.writer.is_none() && inner.readers.is_empty() {
+            inner.writer = Some(current_tid());
+        } else {
+            inner.wait_queue.push_back((current_task().unwrap(), true));
+            drop(inner);
+            block_current_and_run_next();
+        }
+    }
+
+    /// Release the read or write lock the current thread holds, return
+    /// false if it holds none
+    pub fn unlock(&self) -> bool {
+        let tid = current_tid();
+        let mut inner = self.inner.exclusive_access();
+        if inner.writer == Some(tid) {
+            inner.writer = None;
+        } else if let Some(idx) = inner.readers.iter().position(|reader| *reader == tid) {
+            inner.readers.swap_remove(idx);
+        } else {
+            return false;
+        }
+        let woken = inner.grant();
+        drop(inner);
+        for task in woken {
+            wakeup_task(task);
+        }
+        true
+    }
+
+    /// Remove a task from the wait queue, return whether it was waiting
+    pub fn remove_waiter(&self, task: &Arc<TaskControlBlock>) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if let Some(idx) = inner
+            .wait_queue
+            .iter()
+            .position(|(t, _)| Arc::ptr_eq(t, task))
+        {
+            inner.wait_queue.remove(idx);
+            // readers may have been held back by a writer that is gone now
+            let woken = inner.grant();
+            drop(inner);
+            for task in woken {
+                wakeup_task(task);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether the lock is held or some task is blocked on it
+    pub fn is_busy(&self) -> bool {
+        let inner = self.inner.exclusive_access();
+        inner.writer.is_some() || !inner.readers.is_empty() || !inner.wait_queue.is_empty()
+    }
+
+    /// Tids holding the lock and whether it is held for writing
+    pub fn get_holders(&self) -> (Vec<usize>, bool) {
+        let inner = self.inner.exclusive_access();
+        match inner.writer {
+            Some(tid) => (vec![tid], true),
+            None => (inner.readers.clone(), false),
+        }
+    }
+
+    /// Tids of the waiting tasks and whether they want to write
+    pub fn get_waiting(&self) -> Vec<(usize, bool)> {
+        let inner = self.inner.exclusive_access();
+        inner
+            .wait_queue
+            .iter()
+            .map(|(task, write)| {
+                (
+                    task.inner_exclusive_access().res.as_ref().unwrap().tid,
+                    *write,
+                )
+            })
+            .collect()
+    }
+}
+
+impl RwLockInner {
+    /// Hand the lock over to waiting tasks as far as it is free, return the
+    /// tasks to wake up
+    fn grant(&mut self) -> Vec<Arc<TaskControlBlock>> {
+        let mut woken = Vec::new();
+        if self.writer.is_some() {
+            return woken;
+        }
+        let writer_waiting = self.wait_queue.iter().any(|(_, write)| *write);
+        let reader_waiting = self.wait_queue.iter().any(|(_, write)| !*write);
+        if writer_waiting && (self.writer_preferred || !reader_waiting) {
+            if self.readers.is_empty() {
+                let idx = self
+                    .wait_queue
+                    .iter()
+                    .position(|(_, write)| *write)
+                    .unwrap();
+                let (task, _) = self.wait_queue.remove(idx).unwrap();
+                self.writer = Some(task.inner_exclusive_access().res.as_ref().unwrap().tid);
+                woken.push(task);
+            }
+            return woken;
+        }
+        let mut idx = 0;
+        while idx < self.wait_queue.len() {
+            if self.wait_queue[idx].1 {
+                idx += 1;
+                continue;
+            }
+            let (task, _) = self.wait_queue.remove(idx).unwrap();
+            self.readers
+                .push(task.inner_exclusive_access().res.as_ref().unwrap().tid);
+            woken.push(task);
+        }
+        woken
+    }
+}
+
+/// Thread id of the current task
+fn current_tid() -> usize {
+    let task = current_task().unwrap();
+    let task_inner = task.inner_exclusive_access();
+    task_inner.res.as_ref().unwrap().tid
+}
@@ -1,5 +1,6 @@
 use crate::sync::UPSafeCell;
-use crate::task::{add_task, block_current_and_run_next, current_task, TaskControlBlock};
+use crate::task::{block_current_and_run_next, current_task, wakeup_task, TaskControlBlock};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
 use alloc::vec::Vec;
 use alloc::{collections::VecDeque, sync::Arc};
 
@@ -52,7 +53,7 @@ impl Semaphore {
                 inner.allocated_queue[task_tid] += 1;
 
 
-                add_task(task);
+                wakeup_task(task);
             }
         }
     }
@@ -78,6 +79,60 @@ impl Semaphore {
             inner.allocated_queue[tid] += 1;
         }
     }
+    /// Take a resource only if one is available, return whether it was taken
+    pub fn try_down(&self) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if inner.count <= 0 {
+            return false;
+        }
+        inner.count -= 1;
+        let current_task = current_task().unwrap();
+        let current_task_inner = current_task.inner_exclusive_access();
+        let tid = current_task_inner.res.as_ref().unwrap().tid;
+        drop(current_task_inner);
+        while inner.allocated_queue.len() < tid + 1 {
+            inner.allocated_queue.push(0);
+        }
+        inner.allocated_queue[tid] += 1;
+        true
+    }
+
+    /// Wait at most `timeout_ms` for a resource, return whether it was taken
+    pub fn down_timeout(&self, timeout_ms: usize) -> bool {
+        if self.try_down() {
+            return true;
+        }
+        let task = current_task().unwrap();
+        let mut inner = self.inner.exclusive_access();
+        inner.count -= 1;
+        inner.wait_queue.push_back(Arc::clone(&task));
+        drop(inner);
+        add_timer(get_time_ms() + timeout_ms, Arc::clone(&task));
+        block_current_and_run_next();
+        // up hands a resource over and dequeues us, only the timer leaves us queued
+        if self.remove_waiter(&task) {
+            return false;
+        }
+        remove_timer(&task);
+        true
+    }
+
+    /// Remove a task from the wait queue and give back the resource it asked for,
+    /// return whether it was waiting
+    pub fn remove_waiter(&self, task: &Arc<TaskControlBlock>) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if let Some(idx) = inner.wait_queue.iter().position(|t| Arc::ptr_eq(t, task)) {
+            inner.wait_queue.remove(idx);
+            inner.count += 1;
+            true
+        } else {
+            false
+        }
+    }
+    /// Whether some task is blocked on the semaphore
+    pub fn has_waiters(&self) -> bool {
+        !self.inner.exclusive_access().wait_queue.is_empty()
+    }
     pub fn get_count(&self)-> isize{
         let mut inner = self.inner.exclusive_access();
         if inner.count < 0{
@@ -1,16 +1,36 @@
 //! File and filesystem-related syscalls
 
+use crate::config::MAX_FD;
+use crate::fs::absolute_path;
+use crate::fs::make_dir;
+use crate::fs::make_fifo;
 use crate::fs::make_pipe;
+use crate::fs::make_symlink;
+use crate::fs::mount;
 use crate::fs::open_file;
+use crate::fs::open_inode;
+use crate::fs::read_link;
+use crate::fs::rename;
+use crate::fs::stat;
+use crate::fs::FdFlags;
+use crate::fs::File;
+use crate::fs::FileDescriptor;
+use crate::fs::InodeType;
 use crate::fs::OpenFlags;
+use crate::fs::SeekFrom;
 use crate::fs::Stat;
+use crate::fs::umount;
+use crate::fs::unlink;
+use crate::fs::UmountError;
 use crate::mm::translated_byte_buffer;
 use crate::mm::translated_refmut;
 use crate::mm::translated_str;
 use crate::mm::UserBuffer;
 use crate::task::current_process;
 use crate::task::current_user_token;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     let token = current_user_token();
@@ -23,7 +43,7 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
         let file = file.clone();
         // release current process TCB manually to avoid multi-borrow
         drop(inner);
-        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        file.write(UserBuffer::new(translated_byte_buffer(token, buf, len)))
     } else {
         -1
     }
@@ -40,26 +60,240 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
         let file = file.clone();
         // release current process TCB manually to avoid multi-borrow
         drop(inner);
-        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len))) as isize
+        file.read(UserBuffer::new(translated_byte_buffer(token, buf, len)))
     } else {
         -1
     }
 }
 
-pub fn sys_open(path: *const u8, flags: u32) -> isize {
+/// The file open at `fd`
+fn get_file(fd: usize) -> Option<Arc<dyn File + Send + Sync>> {
     let process = current_process();
+    let inner = process.inner_exclusive_access();
+    inner.fd_table.get(fd).cloned().flatten().map(|fd| fd.file)
+}
+
+/// Read from `offset` without moving the offset of the file, return -1 for
+/// a file that is not seekable
+pub fn sys_pread(fd: usize, buf: *const u8, len: usize, offset: usize) -> isize {
+    let token = current_user_token();
+    match get_file(fd) {
+        Some(file) if file.readable() => file
+            .read_at(offset, UserBuffer::new(translated_byte_buffer(token, buf, len)))
+            .map_or(-1, |size| size as isize),
+        _ => -1,
+    }
+}
+
+/// Write at `offset` without moving the offset of the file, return -1 for
+/// a file that is not seekable or cannot grow past `offset`
+pub fn sys_pwrite(fd: usize, buf: *const u8, len: usize, offset: usize) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
+    match get_file(fd) {
+        Some(file) if file.writable() => file
+            .write_at(offset, UserBuffer::new(translated_byte_buffer(token, buf, len)))
+            .map_or(-1, |size| size as isize),
+        _ => -1,
+    }
+}
+
+const SEEK_SET: usize = 0;
+const SEEK_CUR: usize = 1;
+const SEEK_END: usize = 2;
+
+/// Move the offset of `fd` relative to `whence`, return the new offset, or
+/// -1 if it would be negative or past the largest size of the file, or the
+/// file is not seekable
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    let pos = match whence {
+        SEEK_SET if offset >= 0 => SeekFrom::Start(offset as usize),
+        SEEK_CUR => SeekFrom::Current(offset),
+        SEEK_END => SeekFrom::End(offset),
+        _ => return -1,
+    };
+    get_file(fd)
+        .and_then(|file| file.seek(pos))
+        .map_or(-1, |offset| offset as isize)
+}
+
+/// The user path at `path` made absolute against the working directory
+pub fn resolve_path(path: *const u8) -> String {
+    let path = translated_str(current_user_token(), path);
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    absolute_path(&inner.cwd, &path)
+}
+
+/// Copy `data` to the user buffer at `buf`
+fn copy_to_user(buf: *mut u8, data: &[u8]) {
+    let mut copied = 0;
+    for buffer in translated_byte_buffer(current_user_token(), buf, data.len()) {
+        buffer.copy_from_slice(&data[copied..copied + buffer.len()]);
+        copied += buffer.len();
+    }
+}
+
+/// Return value when a process already has [`MAX_FD`] fds open
+pub const EMFILE: isize = -4;
+
+pub fn sys_open(path: *const u8, flags: u32) -> isize {
+    let process = current_process();
+    let path = resolve_path(path);
+    let flags = match OpenFlags::from_bits(flags) {
+        Some(flags) => flags,
+        None => return -1,
+    };
+    if let Some(inode) = open_file(path.as_str(), flags) {
         let mut inner = process.inner_exclusive_access();
-        let fd = inner.alloc_fd();
-        inner.fd_table[fd] = Some(inode);
+        let fd = match inner.alloc_fd() {
+            Some(fd) => fd,
+            None => return EMFILE,
+        };
+        let fd_flags = FdFlags::from_bits_truncate(flags.bits());
+        inner.fd_table[fd] = Some(FileDescriptor::new(inode, fd_flags));
         fd as isize
     } else {
         -1
     }
 }
 
+/// File type bits of a mode
+const S_IFMT: u32 = 0o170000;
+/// File type of a named pipe
+const S_IFIFO: u32 = 0o010000;
+
+/// Create a named pipe at `path`, the only node type supported
+///
+/// Return -1 if `mode` is not `S_IFIFO` or the path is taken.
+pub fn sys_mknodat(path: *const u8, mode: u32) -> isize {
+    if mode & S_IFMT != S_IFIFO {
+        return -1;
+    }
+    if make_fifo(resolve_path(path).as_str()) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Create a directory at `path`, return -1 if the path is taken or its
+/// parent is not a directory
+pub fn sys_mkdirat(path: *const u8) -> isize {
+    if make_dir(resolve_path(path).as_str()) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Change the working directory to `path`, return -1 if it is not a
+/// directory
+pub fn sys_chdir(path: *const u8) -> isize {
+    let path = resolve_path(path);
+    match open_inode(path.as_str(), OpenFlags::RDONLY) {
+        Some(inode) if inode.is_dir() => {
+            current_process().inner_exclusive_access().cwd = path;
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Copy the working directory with a terminating NUL to `buf`, return its
+/// length with the NUL or -1 if it does not fit in `len` bytes
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> isize {
+    let process = current_process();
+    let mut cwd = process.inner_exclusive_access().cwd.clone().into_bytes();
+    cwd.push(0);
+    if cwd.len() > len {
+        return -1;
+    }
+    copy_to_user(buf, &cwd);
+    cwd.len() as isize
+}
+
+/// Entry types of `getdents64`
+const DT_FIFO: u8 = 1;
+const DT_CHR: u8 = 2;
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+const DT_LNK: u8 = 10;
+
+/// Fill `buf` with as many entries of the directory at `fd` as fit, in the
+/// `linux_dirent64` layout, and move its offset past them
+///
+/// Return the number of bytes filled, 0 at the end of the directory, or -1
+/// if `fd` is not a directory or the next entry does not fit.
+pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
+    let file = match get_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    let inode = match file.as_inode() {
+        Some(inode) => inode,
+        None => return -1,
+    };
+    let mut dirents: Vec<u8> = Vec::new();
+    let mut full = false;
+    let is_dir = inode.read_dir(|index, name, inode_id, type_| {
+        // d_ino, d_off, d_reclen, d_type, then the name and NUL, 8 aligned
+        let reclen = (8 + 8 + 2 + 1 + name.len() + 1 + 7) & !7;
+        if dirents.len() + reclen > len {
+            full = true;
+            return false;
+        }
+        let d_type = match type_ {
+            InodeType::File => DT_REG,
+            InodeType::Directory => DT_DIR,
+            InodeType::Fifo => DT_FIFO,
+            InodeType::CharDevice => DT_CHR,
+            InodeType::Symlink => DT_LNK,
+        };
+        let start = dirents.len();
+        dirents.extend_from_slice(&(inode_id as u64).to_le_bytes());
+        // the offset of the next entry
+        dirents.extend_from_slice(&(index as i64 + 1).to_le_bytes());
+        dirents.extend_from_slice(&(reclen as u16).to_le_bytes());
+        dirents.push(d_type);
+        dirents.extend_from_slice(name.as_bytes());
+        dirents.resize(start + reclen, 0);
+        true
+    });
+    if !is_dir || (full && dirents.is_empty()) {
+        return -1;
+    }
+    copy_to_user(buf, &dirents);
+    dirents.len() as isize
+}
+
+/// Mount a filesystem of type `fs_type` from `source` on the directory
+/// `target`, only `easyfs` on `/dev/vdb` so far
+///
+/// Return -1 if `target` is not a directory or already a mount point, or
+/// the filesystem cannot be created from `source`.
+pub fn sys_mount(source: *const u8, target: *const u8, fs_type: *const u8, _flags: usize) -> isize {
+    let token = current_user_token();
+    let source = translated_str(token, source);
+    let fs_type = translated_str(token, fs_type);
+    if mount(&source, &resolve_path(target), &fs_type) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Unmount the filesystem mounted at `target`
+///
+/// Return -1 if nothing is mounted there, and -2 while files in it are
+/// open or another filesystem is mounted inside it.
+pub fn sys_umount2(target: *const u8, _flags: usize) -> isize {
+    match umount(&resolve_path(target)) {
+        Ok(()) => 0,
+        Err(UmountError::NotMounted) => -1,
+        Err(UmountError::Busy) => -2,
+    }
+}
+
 pub fn sys_close(fd: usize) -> isize {
     let process = current_process();
     let mut inner = process.inner_exclusive_access();
@@ -73,15 +307,29 @@ pub fn sys_close(fd: usize) -> isize {
     0
 }
 
-pub fn sys_pipe(pipe: *mut usize) -> isize {
+/// Create a pipe, both ends taking the fd flags in `flags`
+pub fn sys_pipe(pipe: *mut usize, flags: u32) -> isize {
+    let flags = match FdFlags::from_bits(flags) {
+        Some(flags) => flags,
+        None => return -1,
+    };
     let process = current_process();
     let token = current_user_token();
     let mut inner = process.inner_exclusive_access();
     let (pipe_read, pipe_write) = make_pipe();
-    let read_fd = inner.alloc_fd();
-    inner.fd_table[read_fd] = Some(pipe_read);
-    let write_fd = inner.alloc_fd();
-    inner.fd_table[write_fd] = Some(pipe_write);
+    let read_fd = match inner.alloc_fd() {
+        Some(fd) => fd,
+        None => return EMFILE,
+    };
+    inner.fd_table[read_fd] = Some(FileDescriptor::new(pipe_read, flags));
+    let write_fd = match inner.alloc_fd() {
+        Some(fd) => fd,
+        None => {
+            inner.fd_table[read_fd].take();
+            return EMFILE;
+        }
+    };
+    inner.fd_table[write_fd] = Some(FileDescriptor::new(pipe_write, flags));
     *translated_refmut(token, pipe) = read_fd;
     *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd;
     0
@@ -96,11 +344,104 @@ pub fn sys_dup(fd: usize) -> isize {
     if inner.fd_table[fd].is_none() {
         return -1;
     }
-    let new_fd = inner.alloc_fd();
-    inner.fd_table[new_fd] = Some(Arc::clone(inner.fd_table[fd].as_ref().unwrap()));
+    let new_fd = match inner.alloc_fd() {
+        Some(fd) => fd,
+        None => return EMFILE,
+    };
+    // the copy is kept open across exec
+    let mut copy = inner.fd_table[fd].clone().unwrap();
+    copy.flags.remove(FdFlags::CLOEXEC);
+    inner.fd_table[new_fd] = Some(copy);
+    new_fd as isize
+}
+
+/// Duplicate `old_fd` into `new_fd`, closing the file open there first
+///
+/// Only `O_CLOEXEC` is accepted in `flags`, and the copy is kept open
+/// across exec without it. Return -1 if `old_fd` is not open, equals
+/// `new_fd` or `flags` is invalid, and [`EMFILE`] if `new_fd` is beyond
+/// [`MAX_FD`].
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: u32) -> isize {
+    let flags = match FdFlags::from_bits(flags) {
+        Some(flags) if !flags.contains(FdFlags::NONBLOCK) => flags,
+        _ => return -1,
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let mut copy = match inner.fd_table.get(old_fd).cloned().flatten() {
+        Some(fd) if old_fd != new_fd => fd,
+        _ => return -1,
+    };
+    if new_fd >= MAX_FD {
+        return EMFILE;
+    }
+    copy.flags.set(FdFlags::CLOEXEC, flags.contains(FdFlags::CLOEXEC));
+    if new_fd >= inner.fd_table.len() {
+        inner.fd_table.resize(new_fd + 1, None);
+    }
+    let old = inner.fd_table[new_fd].replace(copy);
+    // the replaced file may wake waiters when it is dropped
+    drop(inner);
+    drop(old);
     new_fd as isize
 }
 
+const F_GETFD: usize = 1;
+const F_SETFD: usize = 2;
+const F_GETFL: usize = 3;
+const F_SETFL: usize = 4;
+const F_SETPIPE_SZ: usize = 1031;
+const F_GETPIPE_SZ: usize = 1032;
+/// The close-on-exec bit for `F_GETFD` and `F_SETFD`
+const FD_CLOEXEC: usize = 1;
+
+/// Get or set the flags of `fd`
+///
+/// `F_GETFD` and `F_SETFD` deal with `FD_CLOEXEC`, `F_GETFL` and `F_SETFL`
+/// with the `O_NONBLOCK` bit of [`OpenFlags`]. Return the flags for a get, 0
+/// for a set and -1 for a bad fd or command.
+///
+/// `F_GETPIPE_SZ` returns the capacity of a pipe, `F_SETPIPE_SZ` sets it to
+/// `arg` and returns it, or -1 if that is too small for the data in the pipe.
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let fd = match inner.fd_table.get_mut(fd) {
+        Some(Some(fd)) => fd,
+        _ => return -1,
+    };
+    if let F_GETPIPE_SZ | F_SETPIPE_SZ = cmd {
+        let file = Arc::clone(&fd.file);
+        // release current process TCB manually to avoid multi-borrow
+        drop(inner);
+        return match (file.as_pipe(), cmd) {
+            (Some(pipe), F_GETPIPE_SZ) => pipe.capacity() as isize,
+            (Some(pipe), _) => pipe.set_capacity(arg).map_or(-1, |size| size as isize),
+            (None, _) => -1,
+        };
+    }
+    let flags = &mut fd.flags;
+    match cmd {
+        F_GETFD => {
+            if flags.contains(FdFlags::CLOEXEC) {
+                FD_CLOEXEC as isize
+            } else {
+                0
+            }
+        }
+        F_SETFD => {
+            flags.set(FdFlags::CLOEXEC, arg & FD_CLOEXEC != 0);
+            0
+        }
+        F_GETFL => (*flags & FdFlags::NONBLOCK).bits() as isize,
+        F_SETFL => {
+            flags.set(FdFlags::NONBLOCK, arg & FdFlags::NONBLOCK.bits() as usize != 0);
+            0
+        }
+        _ => -1,
+    }
+}
+
 pub fn sys_fstat(_fd: usize, _st: *mut Stat) -> isize {
     -1
 }
@@ -109,6 +450,97 @@ pub fn sys_linkat(_old_name: *const u8, _new_name: *const u8) -> isize {
     -1
 }
 
-pub fn sys_unlinkat(_name: *const u8) -> isize {
-    -1
+/// Create a symbolic link at `path` pointing to `target`, which is kept as
+/// given, return -1 if the path is taken or its filesystem cannot
+pub fn sys_symlinkat(target: *const u8, path: *const u8) -> isize {
+    let target = translated_str(current_user_token(), target);
+    if !target.is_empty() && make_symlink(&target, &resolve_path(path)) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Copy the target of the symbolic link at `path` to `buf`, cut to `len`
+/// bytes and without a NUL, return its length or -1 if `path` is not a
+/// symbolic link
+pub fn sys_readlinkat(path: *const u8, buf: *mut u8, len: usize) -> isize {
+    match read_link(&resolve_path(path)) {
+        Some(target) => {
+            let target = &target.as_bytes()[..target.len().min(len)];
+            copy_to_user(buf, target);
+            target.len() as isize
+        }
+        None => -1,
+    }
+}
+
+/// Give a symbolic link itself instead of what it points to
+const AT_SYMLINK_NOFOLLOW: usize = 0x100;
+
+/// Fill `st` with the stat of the inode at `path`, following a symbolic
+/// link there unless `AT_SYMLINK_NOFOLLOW` is given
+pub fn sys_fstatat(path: *const u8, st: *mut Stat, flags: usize) -> isize {
+    if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+        return -1;
+    }
+    match stat(&resolve_path(path), flags & AT_SYMLINK_NOFOLLOW == 0) {
+        Some(stat) => {
+            let bytes = unsafe {
+                core::slice::from_raw_parts(
+                    &stat as *const Stat as *const u8,
+                    core::mem::size_of::<Stat>(),
+                )
+            };
+            copy_to_user(st as *mut u8, bytes);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Move the file or directory at `old` to `new`, replacing a file there,
+/// return -1 if the paths are in different filesystems or it cannot be
+/// moved
+pub fn sys_renameat(old: *const u8, new: *const u8) -> isize {
+    if rename(&resolve_path(old), &resolve_path(new)) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Change the size of the file open at `fd` to `len`, filling the space
+/// added with zeros, return -1 if it is not a regular file open for
+/// writing
+pub fn sys_ftruncate(fd: usize, len: usize) -> isize {
+    let file = match get_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    if !file.writable() || len > isize::MAX as usize {
+        return -1;
+    }
+    match file.as_inode() {
+        Some(inode) if inode.truncate(len) => 0,
+        _ => -1,
+    }
+}
+
+/// Remove a directory instead of a file
+const AT_REMOVEDIR: usize = 0x200;
+
+/// Remove the file at `path`, or the empty directory with `AT_REMOVEDIR`
+///
+/// Return -1 if `path` is of the other kind, or its filesystem cannot
+/// remove it, which easy-fs never does.
+pub fn sys_unlinkat(path: *const u8, flags: usize) -> isize {
+    if flags & !AT_REMOVEDIR != 0 {
+        return -1;
+    }
+    if unlink(&resolve_path(path), flags & AT_REMOVEDIR != 0) {
+        0
+    } else {
+        -1
+    }
 }
@@ -0,0 +1,74 @@
+//! Mailbox IPC syscalls
+//!
+//! Every process has a mailbox of at most [`crate::sync::MAILBOX_CAPACITY`]
+//! messages, each cut off at [`MAIL_MAX_LEN`] bytes. Messages are addressed by pid.
+
+use crate::mm::translated_byte_buffer;
+use crate::sync::MAIL_MAX_LEN;
+use crate::task::{
+    block_current_and_run_next, current_process, current_task, current_task_killed,
+    current_user_token, pid2process,
+};
+use alloc::vec::Vec;
+
+/// Block until a message arrives instead of failing
+const MAIL_BLOCK: usize = 1;
+
+/// Take the oldest message out of the mailbox of the current process
+///
+/// At most `len` bytes are copied to `buf` and the rest of the message is
+/// dropped. A `len` of 0 only checks for mail. Return the number of bytes
+/// copied, or -1 if there is no mail and `MAIL_BLOCK` is not set in `flags`.
+pub fn sys_mail_read(buf: *mut u8, len: usize, flags: usize) -> isize {
+    let process = current_process();
+    let message = loop {
+        let mut inner = process.inner_exclusive_access();
+        if len == 0 {
+            return if inner.mailbox.is_empty() { -1 } else { 0 };
+        }
+        if let Some(message) = inner.mailbox.pop() {
+            break message;
+        }
+        if flags & MAIL_BLOCK == 0 || current_task_killed() {
+            return -1;
+        }
+        inner.mailbox.add_waiter(current_task().unwrap());
+        drop(inner);
+        block_current_and_run_next();
+    };
+    let len = len.min(message.len());
+    let buffers = translated_byte_buffer(current_user_token(), buf, len);
+    let mut copied = 0;
+    for buffer in buffers {
+        buffer.copy_from_slice(&message[copied..copied + buffer.len()]);
+        copied += buffer.len();
+    }
+    len as isize
+}
+
+/// Send the first `len` bytes of `buf` to the process `pid`, at most
+/// `MAIL_MAX_LEN` of them
+///
+/// A `len` of 0 only checks whether the mailbox has room. Return the number
+/// of bytes sent, or -1 if there is no such process or its mailbox is full.
+pub fn sys_mail_write(pid: usize, buf: *const u8, len: usize) -> isize {
+    let target = match pid2process(pid) {
+        Some(target) => target,
+        None => return -1,
+    };
+    let len = len.min(MAIL_MAX_LEN);
+    // copy the message before taking the target, which may be the current process
+    let mut message = Vec::with_capacity(len);
+    for buffer in translated_byte_buffer(current_user_token(), buf, len) {
+        message.extend_from_slice(buffer);
+    }
+    let mut target_inner = target.inner_exclusive_access();
+    if target_inner.mailbox.is_full() {
+        return -1;
+    }
+    if len == 0 {
+        return 0;
+    }
+    target_inner.mailbox.push(message);
+    len as isize
+}
@@ -10,14 +10,32 @@
 //! `sys_` then the name of the syscall. You can find functions like this in
 //! submodules, and you should also implement syscalls this way.
 
+const SYSCALL_GETCWD: usize = 17;
 const SYSCALL_DUP: usize = 24;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_MKNODAT: usize = 33;
+const SYSCALL_MKDIRAT: usize = 34;
 const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_SYMLINKAT: usize = 36;
 const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_RENAMEAT: usize = 38;
+const SYSCALL_UMOUNT2: usize = 39;
+const SYSCALL_MOUNT: usize = 40;
+const SYSCALL_FTRUNCATE: usize = 46;
+const SYSCALL_CHDIR: usize = 49;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
+const SYSCALL_GETDENTS64: usize = 61;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_PREAD: usize = 67;
+const SYSCALL_PWRITE: usize = 68;
+const SYSCALL_PSELECT: usize = 72;
+const SYSCALL_PPOLL: usize = 73;
+const SYSCALL_READLINKAT: usize = 78;
+const SYSCALL_FSTATAT: usize = 79;
 const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
 const SYSCALL_SLEEP: usize = 101;
@@ -25,10 +43,19 @@ const SYSCALL_YIELD: usize = 124;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_GETTID: usize = 178;
+const SYSCALL_SOCKET: usize = 198;
+const SYSCALL_BIND: usize = 200;
+const SYSCALL_LISTEN: usize = 201;
+const SYSCALL_ACCEPT: usize = 202;
+const SYSCALL_CONNECT: usize = 203;
+const SYSCALL_SENDTO: usize = 206;
+const SYSCALL_RECVFROM: usize = 207;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
 const SYSCALL_WAITPID: usize = 260;
 const SYSCALL_SPAWN: usize = 400;
+const SYSCALL_MAIL_READ: usize = 401;
+const SYSCALL_MAIL_WRITE: usize = 402;
 const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_MMAP: usize = 222;
 const SYSCALL_SET_PRIORITY: usize = 140;
@@ -45,35 +72,108 @@ const SYSCALL_SEMAPHORE_DOWN: usize = 470;
 const SYSCALL_CONDVAR_CREATE: usize = 471;
 const SYSCALL_CONDVAR_SIGNAL: usize = 472;
 const SYSCALL_CONDVAR_WAIT: usize = 473;
+const SYSCALL_FUTEX_WAIT: usize = 474;
+const SYSCALL_FUTEX_WAKE: usize = 475;
+const SYSCALL_FUTEX_REQUEUE: usize = 476;
+const SYSCALL_MUTEX_TRYLOCK: usize = 477;
+const SYSCALL_MUTEX_TIMEDLOCK: usize = 478;
+const SYSCALL_SEMAPHORE_TRYDOWN: usize = 479;
+const SYSCALL_SEMAPHORE_TIMEDDOWN: usize = 480;
+const SYSCALL_CONDVAR_TIMEDWAIT: usize = 481;
+const SYSCALL_MUTEX_DESTROY: usize = 482;
+const SYSCALL_SEMAPHORE_DESTROY: usize = 483;
+const SYSCALL_CONDVAR_DESTROY: usize = 484;
+const SYSCALL_DEADLOCK_REPORT: usize = 485;
+const SYSCALL_DEADLOCK_CLAIM: usize = 486;
+const SYSCALL_RWLOCK_CREATE: usize = 487;
+const SYSCALL_RWLOCK_READ: usize = 488;
+const SYSCALL_RWLOCK_WRITE: usize = 489;
+const SYSCALL_RWLOCK_UNLOCK: usize = 490;
+const SYSCALL_RWLOCK_DESTROY: usize = 491;
+const SYSCALL_BARRIER_CREATE: usize = 492;
+const SYSCALL_BARRIER_WAIT: usize = 493;
+const SYSCALL_BARRIER_DESTROY: usize = 494;
+const SYSCALL_CONDVAR_BROADCAST: usize = 495;
+const SYSCALL_SEND_FD: usize = 496;
+const SYSCALL_RECV_FD: usize = 497;
+const SYSCALL_DUP3: usize = 498;
 
 mod fs;
+mod mail;
+mod poll;
 pub mod process;
+mod socket;
 mod sync;
 mod thread;
 
 use crate::fs::Stat;
+use crate::task::{current_process, DeadlockReport};
 use fs::*;
+use mail::*;
+use poll::*;
 use process::*;
+use socket::*;
 use sync::*;
 use thread::*;
 
 /// handle syscall exception with `syscall_id` and other arguments
 pub fn syscall(syscall_id: usize, args: [usize; 4]) -> isize {
+    *current_process()
+        .inner_exclusive_access()
+        .syscall_counts
+        .entry(syscall_id)
+        .or_insert(0) += 1;
     match syscall_id {
+        SYSCALL_GETCWD => sys_getcwd(args[0] as *mut u8, args[1]),
         SYSCALL_DUP => sys_dup(args[0]),
+        SYSCALL_DUP3 => sys_dup3(args[0], args[1], args[2] as u32),
+        SYSCALL_FCNTL => sys_fcntl(args[0], args[1], args[2]),
+        SYSCALL_MKNODAT => sys_mknodat(args[1] as *const u8, args[2] as u32),
+        SYSCALL_MKDIRAT => sys_mkdirat(args[1] as *const u8),
         SYSCALL_LINKAT => sys_linkat(args[1] as *const u8, args[3] as *const u8),
-        SYSCALL_UNLINKAT => sys_unlinkat(args[1] as *const u8),
+        SYSCALL_UNLINKAT => sys_unlinkat(args[1] as *const u8, args[2]),
+        SYSCALL_SYMLINKAT => sys_symlinkat(args[0] as *const u8, args[2] as *const u8),
+        SYSCALL_RENAMEAT => sys_renameat(args[1] as *const u8, args[3] as *const u8),
+        SYSCALL_UMOUNT2 => sys_umount2(args[0] as *const u8, args[1]),
+        SYSCALL_MOUNT => sys_mount(
+            args[0] as *const u8,
+            args[1] as *const u8,
+            args[2] as *const u8,
+            args[3],
+        ),
+        SYSCALL_FTRUNCATE => sys_ftruncate(args[0], args[1]),
+        SYSCALL_CHDIR => sys_chdir(args[0] as *const u8),
         SYSCALL_OPEN => sys_open(args[1] as *const u8, args[2] as u32),
         SYSCALL_CLOSE => sys_close(args[0]),
-        SYSCALL_PIPE => sys_pipe(args[0] as *mut usize),
+        SYSCALL_PIPE => sys_pipe(args[0] as *mut usize, args[1] as u32),
+        SYSCALL_GETDENTS64 => sys_getdents64(args[0], args[1] as *mut u8, args[2]),
+        SYSCALL_LSEEK => sys_lseek(args[0], args[1] as isize, args[2]),
         SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_PREAD => sys_pread(args[0], args[1] as *const u8, args[2], args[3]),
+        SYSCALL_PWRITE => sys_pwrite(args[0], args[1] as *const u8, args[2], args[3]),
+        SYSCALL_PSELECT => sys_pselect(
+            args[0],
+            args[1] as *mut u64,
+            args[2] as *mut u64,
+            args[3] as isize,
+        ),
+        SYSCALL_PPOLL => sys_ppoll(args[0] as *mut PollFd, args[1], args[2] as isize),
+        SYSCALL_READLINKAT => sys_readlinkat(args[1] as *const u8, args[2] as *mut u8, args[3]),
+        SYSCALL_FSTATAT => sys_fstatat(args[1] as *const u8, args[2] as *mut Stat, args[3]),
         SYSCALL_FSTAT => sys_fstat(args[0], args[1] as *mut Stat),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
         SYSCALL_SLEEP => sys_sleep(args[0]),
         SYSCALL_YIELD => sys_yield(),
         SYSCALL_GETPID => sys_getpid(),
         SYSCALL_GETTID => sys_gettid(),
+        SYSCALL_SOCKET => sys_socket(args[0], args[1], args[2]),
+        SYSCALL_BIND => sys_bind(args[0], args[1] as *const u8),
+        SYSCALL_LISTEN => sys_listen(args[0], args[1]),
+        SYSCALL_ACCEPT => sys_accept(args[0]),
+        SYSCALL_CONNECT => sys_connect(args[0], args[1] as *const u8),
+        SYSCALL_SENDTO => sys_send(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_RECVFROM => sys_recv(args[0], args[1] as *mut u8, args[2]),
         SYSCALL_FORK => sys_fork(),
         SYSCALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const usize),
         SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
@@ -83,6 +183,8 @@ pub fn syscall(syscall_id: usize, args: [usize; 4]) -> isize {
         SYSCALL_SET_PRIORITY => sys_set_priority(args[0] as isize),
         SYSCALL_TASK_INFO => sys_task_info(args[0] as *mut TaskInfo),
         SYSCALL_SPAWN => sys_spawn(args[0] as *const u8),
+        SYSCALL_MAIL_READ => sys_mail_read(args[0] as *mut u8, args[1], args[2]),
+        SYSCALL_MAIL_WRITE => sys_mail_write(args[0], args[1] as *const u8, args[2]),
         SYSCALL_THREAD_CREATE => sys_thread_create(args[0], args[1]),
         SYSCALL_WAITTID => sys_waittid(args[0]) as isize,
         SYSCALL_MUTEX_CREATE => sys_mutex_create(args[0] == 1),
@@ -95,6 +197,30 @@ pub fn syscall(syscall_id: usize, args: [usize; 4]) -> isize {
         SYSCALL_CONDVAR_CREATE => sys_condvar_create(args[0]),
         SYSCALL_CONDVAR_SIGNAL => sys_condvar_signal(args[0]),
         SYSCALL_CONDVAR_WAIT => sys_condvar_wait(args[0], args[1]),
+        SYSCALL_FUTEX_WAIT => sys_futex_wait(args[0], args[1] as u32, args[2]),
+        SYSCALL_FUTEX_WAKE => sys_futex_wake(args[0], args[1]),
+        SYSCALL_FUTEX_REQUEUE => sys_futex_requeue(args[0], args[1], args[2], args[3] as u32),
+        SYSCALL_MUTEX_TRYLOCK => sys_mutex_trylock(args[0]),
+        SYSCALL_MUTEX_TIMEDLOCK => sys_mutex_timedlock(args[0], args[1]),
+        SYSCALL_SEMAPHORE_TRYDOWN => sys_semaphore_trydown(args[0]),
+        SYSCALL_SEMAPHORE_TIMEDDOWN => sys_semaphore_timeddown(args[0], args[1]),
+        SYSCALL_CONDVAR_TIMEDWAIT => sys_condvar_timedwait(args[0], args[1], args[2]),
+        SYSCALL_MUTEX_DESTROY => sys_mutex_destroy(args[0]),
+        SYSCALL_SEMAPHORE_DESTROY => sys_semaphore_destroy(args[0]),
+        SYSCALL_CONDVAR_DESTROY => sys_condvar_destroy(args[0]),
+        SYSCALL_DEADLOCK_REPORT => sys_deadlock_report(args[0] as *mut DeadlockReport),
+        SYSCALL_DEADLOCK_CLAIM => sys_deadlock_claim(args[0], args[1], args[2]),
+        SYSCALL_RWLOCK_CREATE => sys_rwlock_create(args[0] == 1),
+        SYSCALL_RWLOCK_READ => sys_rwlock_read(args[0]),
+        SYSCALL_RWLOCK_WRITE => sys_rwlock_write(args[0]),
+        SYSCALL_RWLOCK_UNLOCK => sys_rwlock_unlock(args[0]),
+        SYSCALL_RWLOCK_DESTROY => sys_rwlock_destroy(args[0]),
+        SYSCALL_BARRIER_CREATE => sys_barrier_create(args[0]),
+        SYSCALL_BARRIER_WAIT => sys_barrier_wait(args[0]),
+        SYSCALL_BARRIER_DESTROY => sys_barrier_destroy(args[0]),
+        SYSCALL_CONDVAR_BROADCAST => sys_condvar_broadcast(args[0]),
+        SYSCALL_SEND_FD => sys_send_fd(args[0], args[1]),
+        SYSCALL_RECV_FD => sys_recv_fd(args[0]),
         _ => panic!("Unsupported syscall_id: {}", syscall_id),
     }
 }
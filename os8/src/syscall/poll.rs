@@ -0,0 +1,184 @@
+//! Waiting on several files at once
+
+use crate::fs::{File, PollEvents};
+use crate::mm::translated_refmut;
+use crate::task::{
+    block_current_and_run_next, current_process, current_task, current_task_killed,
+    current_user_token, suspend_current_and_run_next,
+};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// An fd to poll, as `struct pollfd`
+#[repr(C)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: u16,
+    pub revents: u16,
+}
+
+/// The file open at `fd`
+fn get_file(fd: usize) -> Option<Arc<dyn File + Send + Sync>> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    inner.fd_table.get(fd).cloned().flatten().map(|fd| fd.file)
+}
+
+/// Wait until `ready` counts a ready fd or `timeout_ms` has passed
+///
+/// A negative `timeout_ms` waits forever and 0 only checks once. The
+/// current task sleeps on the wait queues of `files` meanwhile, or yields if
+/// one of them cannot tell when it becomes ready. Return the last count, or
+/// -1 if the task is killed while waiting.
+fn wait_ready(
+    files: &[Arc<dyn File + Send + Sync>],
+    timeout_ms: isize,
+    mut ready: impl FnMut() -> usize,
+) -> isize {
+    let expire_ms = get_time_ms() + timeout_ms.max(0) as usize;
+    loop {
+        let count = ready();
+        if count > 0 || timeout_ms == 0 || (timeout_ms > 0 && get_time_ms() >= expire_ms) {
+            return count as isize;
+        }
+        if current_task_killed() {
+            return -1;
+        }
+        let task = current_task().unwrap();
+        let mut notified = true;
+        for file in files.iter() {
+            if !file.register_waiter(Arc::clone(&task)) {
+                notified = false;
+            }
+        }
+        if notified {
+            if timeout_ms > 0 {
+                add_timer(expire_ms, Arc::clone(&task));
+            }
+            block_current_and_run_next();
+            if timeout_ms > 0 {
+                remove_timer(&task);
+            }
+        } else {
+            suspend_current_and_run_next();
+        }
+        for file in files.iter() {
+            file.unregister_waiter(&task);
+        }
+    }
+}
+
+/// Wait for events on `nfds` fds described by the `PollFd`s at `fds`
+///
+/// Negative fds are skipped and fds that are not open report `NVAL`. Return
+/// the number of fds with events, 0 on timeout.
+pub fn sys_ppoll(fds: *mut PollFd, nfds: usize, timeout_ms: isize) -> isize {
+    let token = current_user_token();
+    let mut entries: Vec<&'static mut PollFd> = (0..nfds)
+        .map(|i| translated_refmut(token, unsafe { fds.add(i) }))
+        .collect();
+    let targets: Vec<Option<Arc<dyn File + Send + Sync>>> = entries
+        .iter()
+        .map(|entry| {
+            if entry.fd < 0 {
+                None
+            } else {
+                get_file(entry.fd as usize)
+            }
+        })
+        .collect();
+    let files: Vec<Arc<dyn File + Send + Sync>> = targets.iter().flatten().cloned().collect();
+    wait_ready(&files, timeout_ms, || {
+        let mut count = 0;
+        for (entry, target) in entries.iter_mut().zip(targets.iter()) {
+            let revents = match target {
+                _ if entry.fd < 0 => PollEvents::empty(),
+                // errors are reported whether asked for or not
+                Some(file) => {
+                    let always = PollEvents::ERR | PollEvents::HUP;
+                    file.poll() & (PollEvents::from_bits_truncate(entry.events) | always)
+                }
+                None => PollEvents::NVAL,
+            };
+            entry.revents = revents.bits();
+            if !revents.is_empty() {
+                count += 1;
+            }
+        }
+        count
+    })
+}
+
+/// Copy the fd set at `set` covering `nfds` fds, empty for a null pointer
+fn read_fd_set(token: usize, set: *mut u64, nfds: usize) -> Vec<u64> {
+    (0..(nfds + 63) / 64)
+        .map(|i| {
+            if set.is_null() {
+                0
+            } else {
+                *translated_refmut(token, unsafe { set.add(i) })
+            }
+        })
+        .collect()
+}
+
+/// Wait until one of the fds in `readfds` can be read or one in `writefds`
+/// can be written, both being bitmaps over the first `nfds` fds
+///
+/// Either set may be null. The sets are replaced by the fds that are ready.
+/// Return how many there are, 0 on timeout and -1 if a set holds an fd that
+/// is not open.
+pub fn sys_pselect(nfds: usize, readfds: *mut u64, writefds: *mut u64, timeout_ms: isize) -> isize {
+    let token = current_user_token();
+    let read_set = read_fd_set(token, readfds, nfds);
+    let write_set = read_fd_set(token, writefds, nfds);
+    let mut targets = Vec::new();
+    for fd in 0..nfds {
+        let (word, bit) = (fd / 64, 1u64 << (fd % 64));
+        let (read, write) = (read_set[word] & bit != 0, write_set[word] & bit != 0);
+        if read || write {
+            match get_file(fd) {
+                Some(file) => targets.push((fd, file, read, write)),
+                None => return -1,
+            }
+        }
+    }
+    let files: Vec<Arc<dyn File + Send + Sync>> = targets
+        .iter()
+        .map(|(_, file, _, _)| Arc::clone(file))
+        .collect();
+    let mut read_ready = read_set.clone();
+    let mut write_ready = write_set.clone();
+    let count = wait_ready(&files, timeout_ms, || {
+        read_ready.iter_mut().for_each(|word| *word = 0);
+        write_ready.iter_mut().for_each(|word| *word = 0);
+        let mut count = 0;
+        for (fd, file, read, write) in targets.iter() {
+            let (word, bit) = (fd / 64, 1u64 << (fd % 64));
+            let events = file.poll();
+            // a closed peer or error does not block a read or write either
+            let readable = PollEvents::IN | PollEvents::HUP | PollEvents::ERR;
+            if *read && events.intersects(readable) {
+                read_ready[word] |= bit;
+                count += 1;
+            }
+            if *write && events.intersects(PollEvents::OUT | PollEvents::ERR) {
+                write_ready[word] |= bit;
+                count += 1;
+            }
+        }
+        count
+    });
+    if count < 0 {
+        return count;
+    }
+    for (set, ready) in [(readfds, read_ready), (writefds, write_ready)] {
+        if !set.is_null() {
+            for (i, word) in ready.into_iter().enumerate() {
+                *translated_refmut(token, unsafe { set.add(i) }) = word;
+            }
+        }
+    }
+    count
+}
@@ -1,11 +1,12 @@
 //! Process management syscalls
 
+use super::fs::resolve_path;
 use crate::config::MAX_SYSCALL_NUM;
-use crate::fs::{open_file, OpenFlags};
+use crate::fs::{open_inode, OpenFlags};
 use crate::mm::{translated_ref, translated_refmut, translated_str, PageTable, VirtAddr};
 use crate::task::{
     current_process, current_task, current_user_token, exit_current_and_run_next,
-    suspend_current_and_run_next, TaskStatus,
+    suspend_current_and_run_next, update_current_priorities, TaskStatus,
 };
 use crate::timer::get_time_us;
 use alloc::string::String;
@@ -60,7 +61,7 @@ pub fn sys_fork() -> isize {
 /// Syscall Exec which accepts the elf path
 pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
     let token = current_user_token();
-    let path = translated_str(token, path);
+    let path = resolve_path(path);
     let mut args_vec: Vec<String> = Vec::new();
     loop {
         let arg_str_ptr = *translated_ref(token, args);
@@ -72,7 +73,7 @@ pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
             args = args.add(1);
         }
     }
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
+    if let Some(app_inode) = open_inode(path.as_str(), OpenFlags::RDONLY).filter(|inode| !inode.is_dir()) {
         let all_data = app_inode.read_all();
         let process = current_process();
         let argc = args_vec.len();
@@ -139,8 +140,14 @@ pub fn sys_task_info(_ti: *mut TaskInfo) -> isize {
     -1
 }
 
-pub fn sys_set_priority(_prio: isize) -> isize {
-    -1
+/// Set the priority of the calling thread, it has to be at least 2
+pub fn sys_set_priority(prio: isize) -> isize {
+    if prio < 2 {
+        return -1;
+    }
+    current_task().unwrap().inner_exclusive_access().priority = prio as usize;
+    update_current_priorities();
+    prio
 }
 
 pub fn sys_mmap(_start: usize, _len: usize, _port: usize) -> isize {
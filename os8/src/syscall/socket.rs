@@ -0,0 +1,111 @@
+//! Local socket syscalls
+
+use super::fs::EMFILE;
+use crate::fs::{FdFlags, File, FileDescriptor, Socket, SocketKind};
+use crate::mm::{translated_byte_buffer, translated_str, UserBuffer};
+use crate::task::{current_process, current_user_token};
+use alloc::sync::Arc;
+
+const AF_UNIX: usize = 1;
+const SOCK_STREAM: usize = 1;
+const SOCK_DGRAM: usize = 2;
+
+/// The open file at `fd`, `None` if there is none
+fn get_file(fd: usize) -> Option<Arc<dyn File + Send + Sync>> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    inner.fd_table.get(fd).cloned().flatten().map(|fd| fd.file)
+}
+
+/// Run `f` on the socket at `fd`, return -1 if `fd` is not a socket
+fn with_socket(fd: usize, f: impl FnOnce(&Socket) -> isize) -> isize {
+    match get_file(fd) {
+        Some(file) => match file.as_socket() {
+            Some(socket) => f(socket),
+            None => -1,
+        },
+        None => -1,
+    }
+}
+
+/// Put `file` into a new fd of the current process
+fn install_file(file: Arc<dyn File + Send + Sync>) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    match inner.alloc_fd() {
+        Some(fd) => {
+            inner.fd_table[fd] = Some(FileDescriptor::new(file, FdFlags::empty()));
+            fd as isize
+        }
+        None => EMFILE,
+    }
+}
+
+/// Create a socket, only `AF_UNIX` with `SOCK_STREAM` or `SOCK_DGRAM` is supported
+pub fn sys_socket(domain: usize, kind: usize, _protocol: usize) -> isize {
+    let kind = match (domain, kind) {
+        (AF_UNIX, SOCK_STREAM) => SocketKind::Stream,
+        (AF_UNIX, SOCK_DGRAM) => SocketKind::Datagram,
+        _ => return -1,
+    };
+    install_file(Arc::new(Socket::new(kind)))
+}
+
+/// Bind a socket to `name`, return -1 if it is bound already or the name is taken
+pub fn sys_bind(fd: usize, name: *const u8) -> isize {
+    let name = translated_str(current_user_token(), name);
+    with_socket(fd, |socket| if socket.bind(name) { 0 } else { -1 })
+}
+
+/// Let a bound stream socket accept connections
+pub fn sys_listen(fd: usize, backlog: usize) -> isize {
+    with_socket(fd, |socket| if socket.listen(backlog) { 0 } else { -1 })
+}
+
+/// Wait for a connection, return the fd of the connected socket
+pub fn sys_accept(fd: usize) -> isize {
+    let socket = match get_file(fd).and_then(|file| file.as_socket().and_then(|s| s.accept())) {
+        Some(socket) => socket,
+        None => return -1,
+    };
+    install_file(socket)
+}
+
+/// Connect to the socket bound to `name`
+pub fn sys_connect(fd: usize, name: *const u8) -> isize {
+    let name = translated_str(current_user_token(), name);
+    with_socket(fd, |socket| if socket.connect(&name) { 0 } else { -1 })
+}
+
+pub fn sys_send(fd: usize, buf: *const u8, len: usize) -> isize {
+    let token = current_user_token();
+    with_socket(fd, |socket| {
+        socket.send(UserBuffer::new(translated_byte_buffer(token, buf, len)))
+    })
+}
+
+pub fn sys_recv(fd: usize, buf: *mut u8, len: usize) -> isize {
+    let token = current_user_token();
+    with_socket(fd, |socket| {
+        socket.recv(UserBuffer::new(translated_byte_buffer(token, buf, len)))
+    })
+}
+
+/// Pass the file at `fd` to the peer of the socket at `sockfd`
+pub fn sys_send_fd(sockfd: usize, fd: usize) -> isize {
+    let file = match get_file(fd) {
+        Some(file) => file,
+        None => return -1,
+    };
+    with_socket(sockfd, |socket| if socket.send_file(file) { 0 } else { -1 })
+}
+
+/// Wait for a file passed by the peer, return the fd it got in this process
+pub fn sys_recv_fd(sockfd: usize) -> isize {
+    let file = match get_file(sockfd).and_then(|file| file.as_socket().and_then(|s| s.recv_file()))
+    {
+        Some(file) => file,
+        None => return -1,
+    };
+    install_file(file)
+}
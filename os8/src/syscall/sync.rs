@@ -1,6 +1,10 @@
-use crate::sync::{Condvar, Mutex, MutexBlocking, MutexSpin, Semaphore};
-use crate::task::{block_current_and_run_next, current_process, current_task};
-use crate::timer::{add_timer, get_time_ms};
+use crate::mm::{translated_refmut, PageTable, VirtAddr};
+use crate::sync::{Barrier, Condvar, Mutex, MutexBlocking, MutexSpin, RwLock, Semaphore};
+use crate::task::{
+    block_current_and_run_next, current_process, current_task, current_user_token, Avoidance,
+    DeadlockMode, DeadlockReport, ResourceId,
+};
+use crate::timer::{add_timer, get_time_ms, remove_timer};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 
@@ -40,31 +44,94 @@ pub fn sys_mutex_create(blocking: bool) -> isize {
 // LAB5 HINT: Return -0xDEAD if deadlock is detected
 pub fn sys_mutex_lock(mutex_id: usize) -> isize {
     let process = current_process();
-
-    let is_detection = process.inner_exclusive_access().is_enable_deadlock_detection;
-    if is_detection{
-        let process_inner = process.inner_exclusive_access();
-        let res = process_inner.deadlock_detection(mutex_id);
-        if !res{
-            return -0xDEAD;
-        }
+    let mutex = match process.inner_exclusive_access().get_mutex(mutex_id) {
+        Some(mutex) => mutex,
+        None => return -1,
+    };
+    drop(process);
+    let ret = deadlock_policy(ResourceId::Mutex(mutex_id), None);
+    if ret != 0 {
+        return ret;
     }
-    
+    mutex.lock();
+    0
+}
+
+/// Take the mutex only if it is free, return -2 if it is held
+pub fn sys_mutex_trylock(mutex_id: usize) -> isize {
+    let process = current_process();
     let process_inner = process.inner_exclusive_access();
-    let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
+    let mutex = match process_inner.get_mutex(mutex_id) {
+        Some(mutex) => mutex,
+        None => return -1,
+    };
+    let avoid = process_inner.deadlock_mode == DeadlockMode::Avoid;
     drop(process_inner);
     drop(process);
-    mutex.lock();
-    0
+    // under avoidance an unsafe grant counts as busy as well
+    if avoid {
+        match deadlock_policy(ResourceId::Mutex(mutex_id), Some(0)) {
+            0 => {}
+            -3 => return -2,
+            ret => return ret,
+        }
+    }
+    if mutex.try_lock() {
+        0
+    } else {
+        -2
+    }
+}
+
+/// Wait at most `timeout_ms` for the mutex, return -3 on timeout
+pub fn sys_mutex_timedlock(mutex_id: usize, timeout_ms: usize) -> isize {
+    let process = current_process();
+    let mutex = match process.inner_exclusive_access().get_mutex(mutex_id) {
+        Some(mutex) => mutex,
+        None => return -1,
+    };
+    drop(process);
+    let start_ms = get_time_ms();
+    let ret = deadlock_policy(ResourceId::Mutex(mutex_id), Some(timeout_ms));
+    if ret != 0 {
+        return ret;
+    }
+    if mutex.lock_timeout(timeout_ms.saturating_sub(get_time_ms() - start_ms)) {
+        0
+    } else {
+        -3
+    }
 }
 
 pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
-    let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
+    let mutex = match process_inner.get_mutex(mutex_id) {
+        Some(mutex) => mutex,
+        None => return -1,
+    };
     drop(process_inner);
-    drop(process);
     mutex.unlock();
+    process.inner_exclusive_access().wake_avoidance_waiters();
+    0
+}
+
+/// Free the id of a mutex for reuse
+///
+/// A held mutex cannot be destroyed, since its holder or waiters still
+/// refer to it. Return -1 for an unknown id and -2 if the mutex is held.
+pub fn sys_mutex_destroy(mutex_id: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let mutex = match process_inner.get_mutex(mutex_id) {
+        Some(mutex) => mutex,
+        None => return -1,
+    };
+    if mutex.get_count() == 0 {
+        return -2;
+    }
+    process_inner.mutex_list[mutex_id] = None;
+    process_inner.clear_claims(None, Some(ResourceId::Mutex(mutex_id)));
     0
 }
 
@@ -86,74 +153,100 @@ pub fn sys_semaphore_create(res_count: usize) -> isize {
             .push(Some(Arc::new(Semaphore::new(res_count))));
         process_inner.semaphore_list.len() - 1
     };
-    // while process_inner.sem_work.len() < id + 1{
-    //     process_inner.sem_work.push(0);
-    // }
-    // process_inner.sem_work[id] = res_count;
     id as isize
 }
 
 pub fn sys_semaphore_up(sem_id: usize) -> isize {
     let process = current_process();
-    let mut process_inner = process.inner_exclusive_access();
-
-    // let current_task = current_task().unwrap();
-    // let current_task_inner = current_task.inner_exclusive_access();
-    // let current_task_res = current_task_inner.res.as_ref().unwrap();
-    // let tid = current_task_res.tid;
-    // while process_inner.sem_alloc.len() < sem_id + 1{
-    //     process_inner.sem_alloc.push(Vec::new());
-    // }
-    // while process_inner.sem_alloc[sem_id].len()<tid +1 {
-    //     process_inner.sem_alloc[sem_id].push(0);
-    // }
-
-    // if process_inner.sem_alloc[sem_id][tid] > 0{
-    //     process_inner.sem_alloc[sem_id][tid] -= 1;
-    // }
-
-    let sem = Arc::clone(process_inner.semaphore_list[sem_id].as_ref().unwrap());
+    let process_inner = process.inner_exclusive_access();
+    let sem = match process_inner.get_semaphore(sem_id) {
+        Some(sem) => sem,
+        None => return -1,
+    };
     drop(process_inner);
-
-    
     sem.up();
+    process.inner_exclusive_access().wake_avoidance_waiters();
     0
 }
 
 // LAB5 HINT: Return -0xDEAD if deadlock is detected
 pub fn sys_semaphore_down(sem_id: usize) -> isize {
     let process = current_process();
+    let sem = match process.inner_exclusive_access().get_semaphore(sem_id) {
+        Some(sem) => sem,
+        None => return -1,
+    };
+    drop(process);
+    let ret = deadlock_policy(ResourceId::Semaphore(sem_id), None);
+    if ret != 0 {
+        return ret;
+    }
+    sem.down();
+    0
+}
 
-    let is_detection = process.inner_exclusive_access().is_enable_deadlock_detection;
-    if is_detection{
-        let process_inner = process.inner_exclusive_access();
-        let res = process_inner.deadlock_detection(sem_id);
-        if !res{
-            drop(process_inner);
-            drop(process);
-            return -0xDEAD;
+/// Take a resource only if one is available, return -2 otherwise
+pub fn sys_semaphore_trydown(sem_id: usize) -> isize {
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let sem = match process_inner.get_semaphore(sem_id) {
+        Some(sem) => sem,
+        None => return -1,
+    };
+    let avoid = process_inner.deadlock_mode == DeadlockMode::Avoid;
+    drop(process_inner);
+    drop(process);
+    // under avoidance an unsafe grant counts as busy as well
+    if avoid {
+        match deadlock_policy(ResourceId::Semaphore(sem_id), Some(0)) {
+            0 => {}
+            -3 => return -2,
+            ret => return ret,
         }
     }
+    if sem.try_down() {
+        0
+    } else {
+        -2
+    }
+}
 
-    let mut process_inner = process.inner_exclusive_access();
-
-    // let current_task = current_task().unwrap();
-    // let current_task_inner = current_task.inner_exclusive_access();
-    // let current_task_res = current_task_inner.res.as_ref().unwrap();
-    // let tid = current_task_res.tid;
-    // while process_inner.sem_need.len() < sem_id + 1{
-    //     process_inner.sem_need.push(Vec::new());
-    // }
-    // while process_inner.sem_need[sem_id].len()<tid +1 {
-    //     process_inner.sem_need[sem_id].push(0);
-    // }
-
-    // process_inner.sem_need[sem_id][tid] += 1;
-    
+/// Wait at most `timeout_ms` for a resource, return -3 on timeout
+pub fn sys_semaphore_timeddown(sem_id: usize, timeout_ms: usize) -> isize {
+    let process = current_process();
+    let sem = match process.inner_exclusive_access().get_semaphore(sem_id) {
+        Some(sem) => sem,
+        None => return -1,
+    };
+    drop(process);
+    let start_ms = get_time_ms();
+    let ret = deadlock_policy(ResourceId::Semaphore(sem_id), Some(timeout_ms));
+    if ret != 0 {
+        return ret;
+    }
+    if sem.down_timeout(timeout_ms.saturating_sub(get_time_ms() - start_ms)) {
+        0
+    } else {
+        -3
+    }
+}
 
-    let sem = Arc::clone(process_inner.semaphore_list[sem_id].as_ref().unwrap());
-    drop(process_inner);
-    sem.down();
+/// Free the id of a semaphore for reuse
+///
+/// Return -1 for an unknown id and -2 while tasks are blocked on it, whose
+/// `up` would otherwise never come.
+pub fn sys_semaphore_destroy(sem_id: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let sem = match process_inner.get_semaphore(sem_id) {
+        Some(sem) => sem,
+        None => return -1,
+    };
+    if sem.has_waiters() {
+        return -2;
+    }
+    process_inner.semaphore_list[sem_id] = None;
+    process_inner.clear_claims(None, Some(ResourceId::Semaphore(sem_id)));
     0
 }
 
@@ -181,35 +274,399 @@ pub fn sys_condvar_create(_arg: usize) -> isize {
 pub fn sys_condvar_signal(condvar_id: usize) -> isize {
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
-    let condvar = Arc::clone(process_inner.condvar_list[condvar_id].as_ref().unwrap());
+    let condvar = match process_inner.get_condvar(condvar_id) {
+        Some(condvar) => condvar,
+        None => return -1,
+    };
     drop(process_inner);
     condvar.signal();
     0
 }
 
-pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+/// Wake up every task waiting on the condvar, return how many were woken
+pub fn sys_condvar_broadcast(condvar_id: usize) -> isize {
     let process = current_process();
     let process_inner = process.inner_exclusive_access();
-    let condvar = Arc::clone(process_inner.condvar_list[condvar_id].as_ref().unwrap());
-    let mutex = Arc::clone(process_inner.mutex_list[mutex_id].as_ref().unwrap());
+    let condvar = match process_inner.get_condvar(condvar_id) {
+        Some(condvar) => condvar,
+        None => return -1,
+    };
+    drop(process_inner);
+    condvar.broadcast() as isize
+}
+
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let condvar = match process_inner.get_condvar(condvar_id) {
+        Some(condvar) => condvar,
+        None => return -1,
+    };
+    let mutex = match process_inner.get_mutex(mutex_id) {
+        Some(mutex) => mutex,
+        None => return -1,
+    };
+    // the mutex is released while waiting
+    process_inner.wake_avoidance_waiters();
     drop(process_inner);
     condvar.wait(mutex);
     0
 }
 
-// LAB5 YOUR JOB: Implement deadlock detection, but might not all in this syscall
-pub fn sys_enable_deadlock_detect(_enabled: usize) -> isize {
-    if  _enabled < 0 || _enabled > 1 {
+/// Free the id of a condvar for reuse
+///
+/// Return -1 for an unknown id and -2 while tasks are waiting on it.
+pub fn sys_condvar_destroy(condvar_id: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let condvar = match process_inner.get_condvar(condvar_id) {
+        Some(condvar) => condvar,
+        None => return -1,
+    };
+    if condvar.has_waiters() {
+        return -2;
+    }
+    process_inner.condvar_list[condvar_id] = None;
+    0
+}
+
+/// Wait at most `timeout_ms` for a signal, return -3 on timeout
+///
+/// The mutex is locked again before returning in both cases.
+pub fn sys_condvar_timedwait(condvar_id: usize, mutex_id: usize, timeout_ms: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let condvar = match process_inner.get_condvar(condvar_id) {
+        Some(condvar) => condvar,
+        None => return -1,
+    };
+    let mutex = match process_inner.get_mutex(mutex_id) {
+        Some(mutex) => mutex,
+        None => return -1,
+    };
+    // the mutex is released while waiting
+    process_inner.wake_avoidance_waiters();
+    drop(process_inner);
+    drop(process);
+    if condvar.wait_timeout(mutex, timeout_ms) {
+        0
+    } else {
+        -3
+    }
+}
+
+/// Create a reader-writer lock, `writer_preferred` holds back new readers
+/// while a writer waits
+pub fn sys_rwlock_create(writer_preferred: bool) -> isize {
+    let process = current_process();
+    let rwlock = Some(Arc::new(RwLock::new(writer_preferred)));
+    let mut process_inner = process.inner_exclusive_access();
+    if let Some(id) = process_inner
+        .rwlock_list
+        .iter()
+        .position(|item| item.is_none())
+    {
+        process_inner.rwlock_list[id] = rwlock;
+        id as isize
+    } else {
+        process_inner.rwlock_list.push(rwlock);
+        process_inner.rwlock_list.len() as isize - 1
+    }
+}
+
+pub fn sys_rwlock_read(rwlock_id: usize) -> isize {
+    let process = current_process();
+    let rwlock = match process.inner_exclusive_access().get_rwlock(rwlock_id) {
+        Some(rwlock) => rwlock,
+        None => return -1,
+    };
+    let ret = deadlock_check(ResourceId::RwLock(rwlock_id), false);
+    if ret != 0 {
+        return ret;
+    }
+    rwlock.read();
+    0
+}
+
+pub fn sys_rwlock_write(rwlock_id: usize) -> isize {
+    let process = current_process();
+    let rwlock = match process.inner_exclusive_access().get_rwlock(rwlock_id) {
+        Some(rwlock) => rwlock,
+        None => return -1,
+    };
+    let ret = deadlock_check(ResourceId::RwLock(rwlock_id), true);
+    if ret != 0 {
+        return ret;
+    }
+    rwlock.write();
+    0
+}
+
+/// Release the read or write lock held by the current thread, return -1
+/// for an unknown id or if the thread holds none
+pub fn sys_rwlock_unlock(rwlock_id: usize) -> isize {
+    let process = current_process();
+    let rwlock = match process.inner_exclusive_access().get_rwlock(rwlock_id) {
+        Some(rwlock) => rwlock,
+        None => return -1,
+    };
+    if rwlock.unlock() {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Free the id of a rwlock for reuse
+///
+/// Return -1 for an unknown id and -2 while it is held or waited for.
+pub fn sys_rwlock_destroy(rwlock_id: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let rwlock = match process_inner.get_rwlock(rwlock_id) {
+        Some(rwlock) => rwlock,
+        None => return -1,
+    };
+    if rwlock.is_busy() {
+        return -2;
+    }
+    process_inner.rwlock_list[rwlock_id] = None;
+    0
+}
+
+/// Create a barrier for `parties` threads, return -1 if `parties` is 0
+pub fn sys_barrier_create(parties: usize) -> isize {
+    if parties == 0 {
         return -1;
-    }else if _enabled==0 {
-        let process = current_process();
-        let mut process_inner = process.inner_exclusive_access();
-        process_inner.is_enable_deadlock_detection = false;
-        return 0;
-    }else {
+    }
+    let process = current_process();
+    let barrier = Some(Arc::new(Barrier::new(parties)));
+    let mut process_inner = process.inner_exclusive_access();
+    if let Some(id) = process_inner
+        .barrier_list
+        .iter()
+        .position(|item| item.is_none())
+    {
+        process_inner.barrier_list[id] = barrier;
+        id as isize
+    } else {
+        process_inner.barrier_list.push(barrier);
+        process_inner.barrier_list.len() as isize - 1
+    }
+}
+
+/// Wait until all parties have arrived, return 1 in the thread arriving last
+/// and 0 in the others
+pub fn sys_barrier_wait(barrier_id: usize) -> isize {
+    let process = current_process();
+    let barrier = match process.inner_exclusive_access().get_barrier(barrier_id) {
+        Some(barrier) => barrier,
+        None => return -1,
+    };
+    let ret = deadlock_check(ResourceId::Barrier(barrier_id), false);
+    if ret != 0 {
+        return ret;
+    }
+    barrier.wait() as isize
+}
+
+/// Free the id of a barrier for reuse
+///
+/// Return -1 for an unknown id and -2 while tasks are waiting at it.
+pub fn sys_barrier_destroy(barrier_id: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let barrier = match process_inner.get_barrier(barrier_id) {
+        Some(barrier) => barrier,
+        None => return -1,
+    };
+    if barrier.has_waiters() {
+        return -2;
+    }
+    process_inner.barrier_list[barrier_id] = None;
+    0
+}
+
+/// Read the futex word at `uaddr`, `None` if it is misaligned or not mapped
+fn futex_value(token: usize, uaddr: usize) -> Option<u32> {
+    if uaddr % core::mem::size_of::<u32>() != 0 {
+        return None;
+    }
+    let page_table = PageTable::from_token(token);
+    let va = VirtAddr::from(uaddr);
+    match page_table.translate(va.floor()) {
+        Some(pte) if pte.is_valid() => page_table.translate_va(va).map(|pa| *pa.get_ref::<u32>()),
+        _ => None,
+    }
+}
+
+/// Block until woken up if the word at `uaddr` still holds `val`
+///
+/// A non-zero `timeout_ms` bounds the wait. Return 0 when woken up, -1 for a
+/// bad address, -2 if the word no longer holds `val` and -3 on timeout.
+pub fn sys_futex_wait(uaddr: usize, val: u32, timeout_ms: usize) -> isize {
+    let token = current_user_token();
+    match futex_value(token, uaddr) {
+        None => return -1,
+        Some(cur) if cur != val => return -2,
+        _ => {}
+    }
+    let process = current_process();
+    let task = current_task().unwrap();
+    process
+        .inner_exclusive_access()
+        .futex_table
+        .push(uaddr, Arc::clone(&task));
+    if timeout_ms > 0 {
+        add_timer(get_time_ms() + timeout_ms, Arc::clone(&task));
+    }
+    block_current_and_run_next();
+    // only the timer leaves us in the wait queue
+    let timed_out = process.inner_exclusive_access().futex_table.remove(&task);
+    if timeout_ms > 0 && !timed_out {
+        remove_timer(&task);
+    }
+    if timed_out {
+        -3
+    } else {
+        0
+    }
+}
+
+/// Wake up at most `count` tasks waiting on `uaddr`, return how many were woken
+pub fn sys_futex_wake(uaddr: usize, count: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    process_inner.futex_table.wake(uaddr, count) as isize
+}
+
+/// Wake up at most `count` tasks waiting on `uaddr` and move the others to `uaddr2`
+///
+/// Nothing is done unless the word at `uaddr2` still holds `val2`, which lets
+/// a condvar requeue its waiters onto a mutex only while the mutex is held.
+/// Return how many tasks were woken, -1 for a bad address and -2 if the word
+/// at `uaddr2` changed.
+pub fn sys_futex_requeue(uaddr: usize, count: usize, uaddr2: usize, val2: u32) -> isize {
+    let token = current_user_token();
+    match futex_value(token, uaddr2) {
+        None => return -1,
+        Some(cur) if cur != val2 => return -2,
+        _ => {}
+    }
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    process_inner.futex_table.requeue(uaddr, count, uaddr2) as isize
+}
+
+/// Copy the threads and resources behind the last request refused with
+/// -0xDEAD to `report`, return -1 if no request has been refused yet
+pub fn sys_deadlock_report(report: *mut DeadlockReport) -> isize {
+    let token = current_user_token();
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    match process_inner.deadlock_report {
+        Some(last) => {
+            *translated_refmut(token, report) = last;
+            0
+        }
+        None => -1,
+    }
+}
+
+/// 0 turns deadlock handling off, 1 turns on detection and 2 avoidance
+pub fn sys_enable_deadlock_detect(mode: usize) -> isize {
+    let mode = match mode {
+        0 => DeadlockMode::Off,
+        1 => DeadlockMode::Detect,
+        2 => DeadlockMode::Avoid,
+        _ => return -1,
+    };
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    process_inner.deadlock_mode = mode;
+    // requests delayed by avoidance go on unchecked once it is turned off
+    process_inner.wake_avoidance_waiters();
+    0
+}
+
+/// Declare that the current thread holds at most `max` units of a resource
+/// at once, as used by deadlock avoidance
+///
+/// `kind` is 0 for a mutex and 1 for a semaphore. A `max` of 0 drops the
+/// claim. Return -1 for an unknown resource.
+pub fn sys_deadlock_claim(kind: usize, id: usize, max: usize) -> isize {
+    let resource = match ResourceId::from_user(kind, id) {
+        Some(resource) => resource,
+        None => return -1,
+    };
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let exists = match resource {
+        ResourceId::Mutex(id) => process_inner.get_mutex(id).is_some(),
+        ResourceId::Semaphore(id) => process_inner.get_semaphore(id).is_some(),
+        ResourceId::RwLock(_) | ResourceId::Barrier(_) => false,
+    };
+    if !exists {
+        return -1;
+    }
+    process_inner.set_max_claim(resource, max);
+    0
+}
+
+/// Apply the deadlock handling of the process before the current thread asks
+/// for one unit of `resource`, return 0 if it may go on
+///
+/// Detection refuses an unsafe request with -0xDEAD. Avoidance delays it until
+/// it can be granted safely, giving up with -3 after `timeout_ms` if given, and
+/// refuses a request beyond the maximum claim with -1.
+fn deadlock_policy(resource: ResourceId, timeout_ms: Option<usize>) -> isize {
+    let expire_ms = timeout_ms.map(|ms| get_time_ms() + ms);
+    let task = current_task().unwrap();
+    loop {
         let process = current_process();
         let mut process_inner = process.inner_exclusive_access();
-        process_inner.is_enable_deadlock_detection = true;
-        return 0;
+        match process_inner.deadlock_mode {
+            DeadlockMode::Off => return 0,
+            DeadlockMode::Detect => {
+                return if process_inner.deadlock_detection(resource, false) {
+                    0
+                } else {
+                    -0xDEAD
+                };
+            }
+            DeadlockMode::Avoid => match process_inner.deadlock_avoidance(resource) {
+                Avoidance::Grant => return 0,
+                Avoidance::ExceedsClaim => return -1,
+                Avoidance::Wait => {}
+            },
+        }
+        if expire_ms.map_or(false, |ms| get_time_ms() >= ms) {
+            return -3;
+        }
+        process_inner.avoidance_waiters.push_back(Arc::clone(&task));
+        drop(process_inner);
+        if let Some(ms) = expire_ms {
+            add_timer(ms, Arc::clone(&task));
+        }
+        block_current_and_run_next();
+        // check again whether woken by a release or by the timer
+        process.inner_exclusive_access().remove_avoidance_waiter(&task);
+        if expire_ms.is_some() {
+            remove_timer(&task);
+        }
+    }
+}
+
+/// Deadlock detection for rwlocks and barriers, which avoidance leaves alone,
+/// return -0xDEAD if the request is refused and 0 otherwise
+fn deadlock_check(resource: ResourceId, exclusive: bool) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    if process_inner.deadlock_mode == DeadlockMode::Detect
+        && !process_inner.deadlock_detection(resource, exclusive)
+    {
+        -0xDEAD
+    } else {
+        0
     }
 }
@@ -0,0 +1,380 @@
+//! Resource-allocation model of a process used for deadlock handling
+//!
+//! Every mutex and semaphore of a process is a resource type. A snapshot of
+//! who holds how many units and who is blocked asking for how many is taken
+//! whenever a thread asks for a resource, and the Banker's safety check is
+//! run over it to see whether all threads could still finish.
+//!
+//! Detection counts what blocked threads are waiting for as their remaining
+//! need and refuses a request that leaves some thread unable to finish.
+//! Avoidance counts the maximum claims threads declared in advance instead,
+//! and delays a request until granting it keeps the process safe.
+//!
+//! Reader-writer locks and barriers only take part in detection. A rwlock
+//! has one unit per thread, a reader takes one and a writer all of them. At
+//! a barrier every thread that has not arrived yet is taken to hold one unit
+//! it gives back by arriving, and the waiting threads need as many units as
+//! parties are missing. So all parties have to exist before the first one
+//! waits when detection is on.
+
+use super::process::ProcessControlBlockInner;
+use super::{current_task, wakeup_task, TaskControlBlock};
+use crate::sync::Mutex;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// How a process deals with deadlocks
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DeadlockMode {
+    Off,
+    /// Refuse requests that leave an unsafe state with -0xDEAD
+    Detect,
+    /// Delay requests until they can be granted safely
+    Avoid,
+}
+
+/// A resource taking part in deadlock handling
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ResourceId {
+    Mutex(usize),
+    Semaphore(usize),
+    RwLock(usize),
+    Barrier(usize),
+}
+
+/// Outcome of the avoidance check of a request
+pub enum Avoidance {
+    Grant,
+    /// The resource is taken or granting it now would be unsafe
+    Wait,
+    /// The request goes beyond the maximum claim of the thread
+    ExceedsClaim,
+}
+
+impl ResourceId {
+    /// Inverse of [`ResourceId::to_user`] for the resources that can be claimed
+    pub fn from_user(kind: usize, id: usize) -> Option<Self> {
+        match kind {
+            0 => Some(ResourceId::Mutex(id)),
+            1 => Some(ResourceId::Semaphore(id)),
+            _ => None,
+        }
+    }
+
+    /// `[kind, id]` as reported to user space, kind 0 is a mutex, 1 a
+    /// semaphore, 2 a rwlock and 3 a barrier
+    pub fn to_user(self) -> [usize; 2] {
+        match self {
+            ResourceId::Mutex(id) => [0, id],
+            ResourceId::Semaphore(id) => [1, id],
+            ResourceId::RwLock(id) => [2, id],
+            ResourceId::Barrier(id) => [3, id],
+        }
+    }
+}
+
+/// Most threads and resources a [`DeadlockReport`] can list
+pub const DEADLOCK_REPORT_LEN: usize = 16;
+
+/// Threads and resources of the unsafe state found by the last refused request
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DeadlockReport {
+    /// Number of valid entries in `tids`
+    pub tid_count: usize,
+    pub tids: [usize; DEADLOCK_REPORT_LEN],
+    /// Number of valid entries in `resources`
+    pub resource_count: usize,
+    /// See [`ResourceId::to_user`]
+    pub resources: [[usize; 2]; DEADLOCK_REPORT_LEN],
+}
+
+/// Snapshot of the resources of a process
+struct AllocationModel {
+    threads: usize,
+    resources: Vec<ResourceId>,
+    available: Vec<usize>,
+    /// Units of each resource held by each tid
+    allocation: Vec<Vec<usize>>,
+    /// Units of each resource each blocked tid is waiting for
+    request: Vec<Vec<usize>>,
+}
+
+impl AllocationModel {
+    fn new(inner: &ProcessControlBlockInner) -> Self {
+        let mut model = Self {
+            threads: inner.tasks.len(),
+            resources: Vec::new(),
+            available: Vec::new(),
+            allocation: Vec::new(),
+            request: Vec::new(),
+        };
+        for (id, mutex) in inner.mutex_list.iter().enumerate() {
+            if let Some(mutex) = mutex {
+                let mut allocation = vec![0; model.threads];
+                if let Some(tid) = mutex.get_allocate_tid() {
+                    allocation[tid] = 1;
+                }
+                model.push(
+                    ResourceId::Mutex(id),
+                    mutex.get_count(),
+                    allocation,
+                    mutex.get_waiting_tids().unwrap_or_default(),
+                );
+            }
+        }
+        for (id, sem) in inner.semaphore_list.iter().enumerate() {
+            if let Some(sem) = sem {
+                model.push(
+                    ResourceId::Semaphore(id),
+                    sem.get_count(),
+                    sem.get_allocated_tids().unwrap_or_default(),
+                    sem.get_waiting_tids().unwrap_or_default(),
+                );
+            }
+        }
+        for (id, rwlock) in inner.rwlock_list.iter().enumerate() {
+            if let Some(rwlock) = rwlock {
+                let units = model.threads;
+                let (holders, write) = rwlock.get_holders();
+                let mut allocation = vec![0; units];
+                for tid in holders {
+                    allocation[tid] += if write { units } else { 1 };
+                }
+                let mut request = vec![0; units];
+                for (tid, write) in rwlock.get_waiting() {
+                    request[tid] += if write { units } else { 1 };
+                }
+                let held: usize = allocation.iter().sum();
+                model.push(
+                    ResourceId::RwLock(id),
+                    units.saturating_sub(held) as isize,
+                    allocation,
+                    request,
+                );
+            }
+        }
+        for (id, barrier) in inner.barrier_list.iter().enumerate() {
+            if let Some(barrier) = barrier {
+                let (parties, waiting) = barrier.get_waiting();
+                // a thread that exited, joined or not, never arrives
+                let mut allocation: Vec<usize> = inner
+                    .tasks
+                    .iter()
+                    .map(|task| {
+                        task.as_ref().map_or(0, |task| {
+                            let task_inner = task.inner_exclusive_access();
+                            (task_inner.res.is_some() && task_inner.exit_code.is_none()) as usize
+                        })
+                    })
+                    .collect();
+                let mut request = vec![0; model.threads];
+                for &tid in waiting.iter() {
+                    allocation[tid] = 0;
+                    request[tid] = parties - waiting.len();
+                }
+                model.push(ResourceId::Barrier(id), 0, allocation, request);
+            }
+        }
+        // a thread waiting on a condvar needs its mutex back to go on
+        for condvar in inner.condvar_list.iter().flatten() {
+            for (tid, mutex) in condvar.get_waiting() {
+                let id = inner.mutex_list.iter().position(|other| {
+                    other
+                        .as_ref()
+                        .map_or(false, |other| same_mutex(other, &mutex))
+                });
+                if let Some(r) = id.and_then(|id| model.index(ResourceId::Mutex(id))) {
+                    model.request[r][tid] += 1;
+                }
+            }
+        }
+        model
+    }
+
+    fn push(
+        &mut self,
+        id: ResourceId,
+        available: isize,
+        mut allocation: Vec<usize>,
+        mut request: Vec<usize>,
+    ) {
+        allocation.resize(self.threads, 0);
+        request.resize(self.threads, 0);
+        self.resources.push(id);
+        self.available.push(available.max(0) as usize);
+        self.allocation.push(allocation);
+        self.request.push(request);
+    }
+
+    fn index(&self, id: ResourceId) -> Option<usize> {
+        self.resources.iter().position(|res| *res == id)
+    }
+
+    /// What each thread may still ask for given its maximum claims
+    fn claimed_need(&self, max_claims: &BTreeMap<(usize, ResourceId), usize>) -> Vec<Vec<usize>> {
+        let mut need = vec![vec![0; self.threads]; self.resources.len()];
+        for (&(tid, id), &max) in max_claims.iter() {
+            if let Some(r) = self.index(id).filter(|_| tid < self.threads) {
+                need[r][tid] = max.saturating_sub(self.allocation[r][tid]);
+            }
+        }
+        need
+    }
+
+    /// Banker's safety check, return the tids that can never finish when each
+    /// thread still needs `need` before it releases everything it holds
+    fn unfinished(&self, need: &[Vec<usize>]) -> Vec<usize> {
+        let mut work = self.available.clone();
+        let mut finish = vec![false; self.threads];
+        while let Some(t) = (0..self.threads)
+            .find(|&t| !finish[t] && (0..self.resources.len()).all(|r| need[r][t] <= work[r]))
+        {
+            for (r, units) in work.iter_mut().enumerate() {
+                *units += self.allocation[r][t];
+            }
+            finish[t] = true;
+        }
+        (0..self.threads).filter(|&t| !finish[t]).collect()
+    }
+
+    /// Report the unfinished tids and every resource they hold or need
+    fn report(&self, tids: &[usize], need: &[Vec<usize>]) -> DeadlockReport {
+        let mut report = DeadlockReport {
+            tid_count: 0,
+            tids: [0; DEADLOCK_REPORT_LEN],
+            resource_count: 0,
+            resources: [[0; 2]; DEADLOCK_REPORT_LEN],
+        };
+        for &tid in tids.iter().take(DEADLOCK_REPORT_LEN) {
+            report.tids[report.tid_count] = tid;
+            report.tid_count += 1;
+        }
+        let involved = (0..self.resources.len()).filter(|&r| {
+            tids.iter()
+                .any(|&t| self.allocation[r][t] > 0 || need[r][t] > 0)
+        });
+        for r in involved.take(DEADLOCK_REPORT_LEN) {
+            report.resources[report.resource_count] = self.resources[r].to_user();
+            report.resource_count += 1;
+        }
+        report
+    }
+}
+
+fn current_tid() -> usize {
+    let task = current_task().unwrap();
+    let task_inner = task.inner_exclusive_access();
+    task_inner.res.as_ref().unwrap().tid
+}
+
+fn same_mutex(a: &Arc<dyn Mutex>, b: &Arc<dyn Mutex>) -> bool {
+    Arc::as_ptr(a) as *const u8 == Arc::as_ptr(b) as *const u8
+}
+
+impl ProcessControlBlockInner {
+    /// Check whether the current thread may ask for one unit of `resource`,
+    /// or for a rwlock to write if `exclusive`
+    ///
+    /// The request is refused if, counting it in, some threads could never
+    /// finish. Those threads and the resources involved are then kept in
+    /// `deadlock_report`.
+    pub fn deadlock_detection(&mut self, resource: ResourceId, exclusive: bool) -> bool {
+        let mut model = AllocationModel::new(self);
+        let tid = current_tid();
+        let mut need = model.request.clone();
+        if let Some(r) = model.index(resource) {
+            match resource {
+                ResourceId::RwLock(_) if exclusive => need[r][tid] += model.threads,
+                ResourceId::Barrier(id) => {
+                    let (parties, waiting) = self.get_barrier(id).unwrap().get_waiting();
+                    // the last party to arrive never waits
+                    let missing = parties.saturating_sub(waiting.len() + 1);
+                    if missing == 0 {
+                        return true;
+                    }
+                    model.allocation[r][tid] = 0;
+                    for waiter in waiting.into_iter().chain(Some(tid)) {
+                        need[r][waiter] = missing;
+                    }
+                }
+                _ => need[r][tid] += 1,
+            }
+        }
+        let unfinished = model.unfinished(&need);
+        if unfinished.is_empty() {
+            return true;
+        }
+        self.deadlock_report = Some(model.report(&unfinished, &need));
+        false
+    }
+
+    /// Banker's algorithm for one unit of `resource` asked by the current thread
+    pub fn deadlock_avoidance(&self, resource: ResourceId) -> Avoidance {
+        let mut model = AllocationModel::new(self);
+        let tid = current_tid();
+        let r = match model.index(resource) {
+            Some(r) => r,
+            None => return Avoidance::ExceedsClaim,
+        };
+        let mut need = model.claimed_need(&self.max_claims);
+        if need[r][tid] == 0 {
+            return Avoidance::ExceedsClaim;
+        }
+        if model.available[r] == 0 {
+            return Avoidance::Wait;
+        }
+        // pretend to grant it and see whether everyone can still finish
+        model.available[r] -= 1;
+        model.allocation[r][tid] += 1;
+        need[r][tid] -= 1;
+        if model.unfinished(&need).is_empty() {
+            Avoidance::Grant
+        } else {
+            Avoidance::Wait
+        }
+    }
+
+    /// Declare that the current thread holds at most `max` units of `resource` at once
+    pub fn set_max_claim(&mut self, resource: ResourceId, max: usize) {
+        let tid = current_tid();
+        if max == 0 {
+            self.max_claims.remove(&(tid, resource));
+        } else {
+            self.max_claims.insert((tid, resource), max);
+        }
+        self.wake_avoidance_waiters();
+    }
+
+    /// Forget the claims of a thread that exits or on a destroyed resource
+    pub fn clear_claims(&mut self, tid: Option<usize>, resource: Option<ResourceId>) {
+        self.max_claims.retain(|&(t, id), _| {
+            !(tid.map_or(true, |tid| tid == t) && resource.map_or(true, |res| res == id))
+        });
+        self.wake_avoidance_waiters();
+    }
+
+    /// Let the threads delayed by avoidance check again, called whenever
+    /// resources are released or claims change
+    pub fn wake_avoidance_waiters(&mut self) {
+        for task in self.avoidance_waiters.drain(..) {
+            wakeup_task(task);
+        }
+    }
+
+    /// Remove a task delayed by avoidance, return whether it was waiting
+    pub fn remove_avoidance_waiter(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        if let Some(idx) = self
+            .avoidance_waiters
+            .iter()
+            .position(|waiting| Arc::ptr_eq(waiting, task))
+        {
+            self.avoidance_waiters.remove(idx);
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -4,30 +4,84 @@
 //! Other CPU process monitoring functions are in Processor.
 
 
-use super::TaskControlBlock;
+use super::{ProcessControlBlock, TaskControlBlock};
+use crate::config::BIG_STRIDE;
 use crate::sync::UPSafeCell;
-use alloc::collections::VecDeque;
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::vec::Vec;
 use alloc::sync::Arc;
+use core::cmp::Ordering;
 use lazy_static::*;
 
+/// A task in the ready queue, ordered by pass and then by the order it was
+/// added in
+struct ReadyTask {
+    pass: usize,
+    seq: usize,
+    task: Arc<TaskControlBlock>,
+}
+
+impl PartialEq for ReadyTask {
+    fn eq(&self, other: &Self) -> bool {
+        (self.pass, self.seq) == (other.pass, other.seq)
+    }
+}
+
+impl Eq for ReadyTask {}
+
+impl PartialOrd for ReadyTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ReadyTask {
+    /// Reversed, so the max-heap pops the smallest pass first
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.pass, other.seq).cmp(&(self.pass, self.seq))
+    }
+}
+
+/// A stride scheduler, tasks of equal pass run in FIFO order.
 pub struct TaskManager {
-    ready_queue: VecDeque<Arc<TaskControlBlock>>,
+    ready_queue: BinaryHeap<ReadyTask>,
+    /// Pass of the task fetched last
+    current_pass: usize,
+    /// Number of tasks added so far, to keep equal passes in FIFO order
+    seq: usize,
 }
 
-/// A simple FIFO scheduler.
 impl TaskManager {
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new(),
+            ready_queue: BinaryHeap::new(),
+            current_pass: 0,
+            seq: 0,
         }
     }
     /// Add process back to ready queue
     pub fn add(&mut self, task: Arc<TaskControlBlock>) {
-        self.ready_queue.push_back(task);
+        // new and long blocked tasks start from the current pass instead of
+        // taking over the CPU until they catch up
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.pass = task_inner.pass.max(self.current_pass);
+        let pass = task_inner.pass;
+        drop(task_inner);
+        self.seq += 1;
+        self.ready_queue.push(ReadyTask {
+            pass,
+            seq: self.seq,
+            task,
+        });
     }
-    /// Take a process out of the ready queue
+    /// Take the process with the smallest pass out of the ready queue
     pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
-        self.ready_queue.pop_front()
+        let ReadyTask { pass, task, .. } = self.ready_queue.pop()?;
+        let mut task_inner = task.inner_exclusive_access();
+        self.current_pass = pass;
+        task_inner.pass += (BIG_STRIDE / task_inner.effective_priority).max(1);
+        drop(task_inner);
+        Some(task)
     }
 }
 
@@ -35,6 +89,9 @@ lazy_static! {
     /// TASK_MANAGER instance through lazy_static!
     pub static ref TASK_MANAGER: UPSafeCell<TaskManager> =
         unsafe { UPSafeCell::new(TaskManager::new()) };
+    /// Processes that have not exited yet, by pid
+    pub static ref PID2PCB: UPSafeCell<BTreeMap<usize, Arc<ProcessControlBlock>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
 }
 
 pub fn add_task(task: Arc<TaskControlBlock>) {
@@ -44,3 +101,20 @@ pub fn add_task(task: Arc<TaskControlBlock>) {
 pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
     TASK_MANAGER.exclusive_access().fetch()
 }
+
+pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    PID2PCB.exclusive_access().get(&pid).cloned()
+}
+
+/// Get the pids of the processes that have not exited yet
+pub fn pids() -> Vec<usize> {
+    PID2PCB.exclusive_access().keys().copied().collect()
+}
+
+pub fn insert_into_pid2process(pid: usize, process: Arc<ProcessControlBlock>) {
+    PID2PCB.exclusive_access().insert(pid, process);
+}
+
+pub fn remove_from_pid2process(pid: usize) {
+    PID2PCB.exclusive_access().remove(&pid);
+}
@@ -10,9 +10,11 @@
 //! might not be what you expect.
 
 mod context;
+mod deadlock;
 mod id;
 pub mod kthread;
 mod manager;
+mod priority;
 mod process;
 mod processor;
 pub mod stackless_coroutine;
@@ -22,15 +24,17 @@ mod task;
 
 pub use crate::syscall::process::TaskInfo;
 use crate::{
-    fs::{open_file, OpenFlags},
+    fs::{open_inode, OpenFlags},
     task::id::TaskUserRes,
 };
 use alloc::{sync::Arc, vec::Vec};
 pub use context::TaskContext;
+pub use deadlock::{Avoidance, DeadlockMode, DeadlockReport, ResourceId};
 pub use id::{kstack_alloc, pid_alloc, KernelStack, PidHandle};
 pub use kthread::kernel_stackful_coroutine_test;
 use lazy_static::*;
-pub use manager::add_task;
+pub use manager::{add_task, insert_into_pid2process, pid2process, pids, remove_from_pid2process};
+pub use priority::update_current_priorities;
 use manager::fetch_task;
 use process::ProcessControlBlock;
 pub use processor::{
@@ -50,6 +54,19 @@ pub fn block_current_and_run_next() {
     schedule(task_cx_ptr);
 }
 
+/// Wake up a blocked task, do nothing if it has been woken up already
+///
+/// A task blocked with a timer may be woken by both the timer and whoever it
+/// waits for, the status check keeps it from entering the ready queue twice.
+pub fn wakeup_task(task: Arc<TaskControlBlock>) {
+    let mut task_inner = task.inner_exclusive_access();
+    if task_inner.task_status == TaskStatus::Blocking {
+        task_inner.task_status = TaskStatus::Ready;
+        drop(task_inner);
+        add_task(task);
+    }
+}
+
 /// Make current task suspended and switch to the next task
 pub fn suspend_current_and_run_next() {
     // There must be an application running.
@@ -78,6 +95,9 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     let mut task_inner = task.inner_exclusive_access();
     let process = task.process.upgrade().unwrap();
     let tid = task_inner.res.as_ref().unwrap().tid;
+    let killed = task_inner.killed;
+    // a finished thread asks for nothing more
+    process.inner_exclusive_access().clear_claims(Some(tid), None);
     // Record exit code
     task_inner.exit_code = Some(exit_code);
     task_inner.res = None;
@@ -88,10 +108,14 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     drop(task);
     // debug!("task {} dropped", tid);
 
-    if tid == 0 {
+    // a main thread killed by exec leaves the process to the calling thread
+    if tid == 0 && !killed {
         let mut process_inner = process.inner_exclusive_access();
         // mark this process as a zombie process
         process_inner.is_zombie = true;
+        // nobody can send it messages any more
+        remove_from_pid2process(process.getpid());
+        process_inner.mailbox.clear();
         // record exit code of main process
         process_inner.exit_code = exit_code;
 
@@ -139,13 +163,25 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     schedule(&mut _unused as *mut _);
 }
 
+/// Whether the current task has been asked to exit by another thread
+pub fn current_task_killed() -> bool {
+    current_task().unwrap().inner_exclusive_access().killed
+}
+
+/// Exit the current task if it has been killed, called before returning to user mode
+pub fn exit_current_if_killed() {
+    if current_task_killed() {
+        exit_current_and_run_next(-1);
+    }
+}
+
 lazy_static! {
     /// Creation of initial process
     ///
     /// the name "initproc" may be changed to any other app name like "usertests",
     /// but we have user_shell, so we don't need to change it.
     pub static ref INITPROC: Arc<ProcessControlBlock> = {
-        let inode = open_file("ch8b_initproc", OpenFlags::RDONLY).unwrap();
+        let inode = open_inode("ch8b_initproc", OpenFlags::RDONLY).unwrap();
         let v = inode.read_all();
         ProcessControlBlock::new(v.as_slice())
     };
@@ -0,0 +1,48 @@
+//! Priority inheritance over the mutexes of a process
+//!
+//! A thread holding a mutex runs with the highest priority of the threads
+//! waiting for it. The holder may itself wait for another mutex, so the
+//! boost is pushed along such chains until nothing changes any more.
+
+use super::current_process;
+use super::process::ProcessControlBlockInner;
+use alloc::vec::Vec;
+
+impl ProcessControlBlockInner {
+    /// Recompute the effective priorities of all threads of the process
+    pub fn update_priorities(&self) {
+        let mut priorities: Vec<usize> = self
+            .tasks
+            .iter()
+            .map(|task| task.as_ref().map_or(0, |task| task.inner_exclusive_access().priority))
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for mutex in self.mutex_list.iter().flatten() {
+                let holder = match mutex.get_allocate_tid() {
+                    Some(tid) if tid < priorities.len() => tid,
+                    _ => continue,
+                };
+                let waiting = mutex.get_waiting_tids().unwrap_or_default();
+                for (tid, count) in waiting.into_iter().enumerate() {
+                    if count > 0 && priorities[tid] > priorities[holder] {
+                        priorities[holder] = priorities[tid];
+                        changed = true;
+                    }
+                }
+            }
+        }
+        for (task, priority) in self.tasks.iter().zip(priorities) {
+            if let Some(task) = task {
+                task.inner_exclusive_access().effective_priority = priority;
+            }
+        }
+    }
+}
+
+/// Recompute the effective priorities in the current process, called
+/// whenever a mutex changes hands or gains or loses a waiter
+pub fn update_current_priorities() {
+    current_process().inner_exclusive_access().update_priorities();
+}
@@ -1,10 +1,19 @@
-use super::id::RecycleAllocator;
-use super::{add_task, pid_alloc, PidHandle, TaskControlBlock};
-use crate::fs::{File, Stdin, Stdout};
-use crate::mm::{translated_refmut, MemorySet, KERNEL_SPACE};
-use crate::sync::{Condvar, Mutex, Semaphore, UPSafeCell};
+use super::deadlock::{DeadlockMode, DeadlockReport, ResourceId};
+use super::id::{trap_cx_bottom_from_tid, ustack_bottom_from_tid, RecycleAllocator, TaskUserRes};
+use super::{
+    add_task, insert_into_pid2process, pid_alloc, suspend_current_and_run_next, wakeup_task,
+    PidHandle, TaskControlBlock,
+};
+use crate::config::MAX_FD;
+use crate::fs::{remove_fifo_waiter, FdFlags, File, FileDescriptor, Stdin, Stdout};
+use crate::mm::{translated_refmut, MemorySet, VirtAddr, KERNEL_SPACE};
+use crate::sync::{
+    Barrier, Condvar, FutexTable, Mailbox, Mutex, RwLock, Semaphore, UPSafeCell,
+};
 use crate::task::current_task;
+use crate::timer::remove_timer;
 use crate::trap::{trap_handler, TrapContext};
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use alloc::vec;
@@ -26,16 +35,27 @@ pub struct ProcessControlBlockInner {
     pub parent: Option<Weak<ProcessControlBlock>>,
     pub children: Vec<Arc<ProcessControlBlock>>,
     pub exit_code: i32,
-    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    pub fd_table: Vec<Option<FileDescriptor>>,
     pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
     pub task_res_allocator: RecycleAllocator,
     pub mutex_list: Vec<Option<Arc<dyn Mutex>>>,
     pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
     pub condvar_list: Vec<Option<Arc<Condvar>>>,
-    pub is_enable_deadlock_detection: bool,
-    pub sem_work: Vec<usize>,
-    pub sem_alloc: Vec<Vec<usize>>,
-    pub sem_need: Vec<Vec<usize>>,
+    pub rwlock_list: Vec<Option<Arc<RwLock>>>,
+    pub barrier_list: Vec<Option<Arc<Barrier>>>,
+    pub futex_table: FutexTable,
+    pub deadlock_mode: DeadlockMode,
+    pub deadlock_report: Option<DeadlockReport>,
+    /// Maximum claims declared for deadlock avoidance, keyed by tid and resource
+    pub max_claims: BTreeMap<(usize, ResourceId), usize>,
+    /// Threads whose requests are delayed by deadlock avoidance
+    pub avoidance_waiters: VecDeque<Arc<TaskControlBlock>>,
+    /// Messages other processes sent to this one
+    pub mailbox: Mailbox,
+    /// Normalized absolute path of the working directory
+    pub cwd: String,
+    /// Number of calls of each syscall, keyed by syscall id
+    pub syscall_counts: BTreeMap<usize, usize>,
 }
 
 impl ProcessControlBlockInner {
@@ -44,12 +64,15 @@ impl ProcessControlBlockInner {
         self.memory_set.token()
     }
 
-    pub fn alloc_fd(&mut self) -> usize {
+    /// Get the lowest free fd, `None` if [`MAX_FD`] fds are open
+    pub fn alloc_fd(&mut self) -> Option<usize> {
         if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
-            fd
-        } else {
+            Some(fd)
+        } else if self.fd_table.len() < MAX_FD {
             self.fd_table.push(None);
-            self.fd_table.len() - 1
+            Some(self.fd_table.len() - 1)
+        } else {
+            None
         }
     }
 
@@ -61,178 +84,30 @@ impl ProcessControlBlockInner {
         self.task_res_allocator.dealloc(tid)
     }
 
-    pub fn thread_count(&self) -> usize {
-        self.tasks.len()
+    /// `None` if there is no such mutex or it has been destroyed
+    pub fn get_mutex(&self, id: usize) -> Option<Arc<dyn Mutex>> {
+        self.mutex_list.get(id).cloned().flatten()
     }
 
-    pub fn get_task(&self, tid: usize) -> Arc<TaskControlBlock> {
-        self.tasks[tid].as_ref().unwrap().clone()
+    /// `None` if there is no such semaphore or it has been destroyed
+    pub fn get_semaphore(&self, id: usize) -> Option<Arc<Semaphore>> {
+        self.semaphore_list.get(id).cloned().flatten()
     }
-    // pub fn sem_deadlock(&self) -> bool{
-    //     let l_tasks = self.tasks.len();
-    //     let mut Finish = vec![false; l_tasks];
-    //     let mut Need = self.sem_need.clone();
-    //     let mut Work = self.sem_work.clone();
-    //     let mut Allocation = self.sem_alloc.clone();
-
-    //     while let Some(t) = find_thread(&Need, &Work, &Finish, l_tasks){
-    //         for i in 0..l_mutex_len{
-    //             Work[i] = Work[i] + Allocation[i][t] as isize; 
-    //         }
-    //         Finish[t] = true;
-    //     }
-    //     check_Finish(&Finish)
-    // }
-    pub fn deadlock_detection(&self, id: usize) -> bool{
-        let l_tasks = self.tasks.len();
-        let l_mutex_len = self.mutex_list.len();
-        let l_semphore_len = self.semaphore_list.len();
-
-        if l_mutex_len> 0{
-            let mut Work = vec![0;l_mutex_len];
-            let mut Finish = vec![false; l_tasks];
-            let mut Need = vec![vec![0;l_tasks];l_mutex_len];
-            let mut Allocation = vec![vec![0;l_tasks];l_mutex_len];
-            for i in 0..l_mutex_len{
-                if let Some(mutex_obj) = &self.mutex_list[i] {
-                    Work[i] = mutex_obj.get_count();
-                }
-            }
-            for i in 0..l_mutex_len{
-                if let Some(mutex_obj) = &self.mutex_list[i] {
-                    if let Some(res) = mutex_obj.get_waiting_tids(){
-                        Need[i] =  res;
-                    }
-                    if let Some(res) = mutex_obj.get_allocate_tid(){
-                        Allocation[i][res] = 1;
-                    }
-                }
-            }
-            let c_task = current_task().unwrap();
-            let current_task_inner = c_task.inner_exclusive_access();
-            let current_task_res = current_task_inner.res.as_ref().unwrap();
-            let c_tid = current_task_res.tid;
-            Need[id][c_tid] += 1;
-
-            while let Some(t) = find_thread(&Need, &Work, &Finish, l_tasks){
-                for i in 0..l_mutex_len{
-                    Work[i] = Work[i] + Allocation[i][t]; 
-                }
-                Finish[t] = true;
-            }
-            check_Finish(&Finish)
 
-        }else if l_semphore_len > 0{
-            // println!("109");
-            let mut Work = vec![0;l_semphore_len];
-            let mut Finish = vec![false; l_tasks];
-            let mut Need = Vec::new();
-            let mut Allocation = Vec::new();
-            for i in 0..l_semphore_len{
-                if let Some(sem_obj) = &self.semaphore_list[i] {
-                    Work[i] = sem_obj.get_count();
-                }  
-            }
-            for i in 0..l_semphore_len{
-                
-                if let Some(sem_obj) = &self.semaphore_list[i] {
-                    if let Some(res) = sem_obj.get_waiting_tids(){
-                        Need.push(res);
-                    }else{
-                        Need.push(Vec::new());
-                    }
-                    if let Some(res) = sem_obj.get_allocated_tids(){
-                        Allocation.push(res);
-                    }else{
-                        Allocation.push(Vec::new());
-                    }
-                }else{
-                    Need.push(Vec::new());
-                    Allocation.push(Vec::new());
-                }
-            }
-            let c_task = current_task().unwrap();
-            let current_task_inner = c_task.inner_exclusive_access();
-            let current_task_res = current_task_inner.res.as_ref().unwrap();
-            let c_tid = current_task_res.tid;
-            while  Need[id].len() < c_tid + 1{
-                Need[id].push(0);
-            }
-            // println!("Need index {}----{}",id,c_tid);
-            Need[id][c_tid] += 1;
-            drop(current_task_inner);
-
-            // println!("-----------------work-------------------");
-            // for i in 0..l_semphore_len{
-            //     print!(" {}",Work[i]);
-            // }
-            // println!("");
-            // println!("------------------need------------------------");
-            // for i in 0..l_semphore_len{
-            //     for j in 0..Need[i].len(){
-            //         print!("{} ",Need[i][j]);
-            //     }
-            //     println!("");
-            // }
-            // println!("--------------------------Allo------------------------");
-            // for i in 0..l_semphore_len{
-            //     for j in 0..Allocation[i].len(){
-            //         print!("{} ",Allocation[i][j]);
-            //     }
-            //     println!("");
-            // }
-            // println!("---------------------------------------------------");
-            // println!("130");
-            
-
-            // println!("140");
-            while let Some(t) = find_thread(&Need, &Work, &Finish, l_tasks){
-                for i in 0..l_semphore_len{
-                    if t < Allocation[i].len(){
-                        Work[i] = Work[i] + Allocation[i][t] as isize; 
-                    } 
-                }
-                Finish[t] = true;
-            }
-            // println!("147");
-            // println!("--------{}------------",check_Finish(&Finish));
-            check_Finish(&Finish)
-        }else{
-            true
-        }
+    /// `None` if there is no such condvar or it has been destroyed
+    pub fn get_condvar(&self, id: usize) -> Option<Arc<Condvar>> {
+        self.condvar_list.get(id).cloned().flatten()
     }
-}
 
-pub fn find_thread(need: &Vec<Vec<usize>>, work: &Vec<isize>, finish: &Vec<bool>, threads: usize) -> Option<usize>{
-    let l1 = work.len();
-    let mut flag = false;
-
-    for t in 0..threads{
-        if !finish[t] {
-            flag = false;
-            for i in 0..l1{
-                if need[i].len() > t{
-                    if need[i][t] > work[i] as usize{
-                        flag = true;
-                        break;
-                    }
-                }
-            }
-            if !flag{
-                return Some(t);
-            }
-        }
+    /// `None` if there is no such rwlock or it has been destroyed
+    pub fn get_rwlock(&self, id: usize) -> Option<Arc<RwLock>> {
+        self.rwlock_list.get(id).cloned().flatten()
     }
-    return None;  
-}
-pub fn check_Finish(finish: &Vec<bool>)-> bool{
-    let l = finish.len();
-    for i in 0..l{
-        if !finish[i]{
-            return false;
-        }
+
+    /// `None` if there is no such barrier or it has been destroyed
+    pub fn get_barrier(&self, id: usize) -> Option<Arc<Barrier>> {
+        self.barrier_list.get(id).cloned().flatten()
     }
-    return true;
 }
 
 impl ProcessControlBlock {
@@ -257,21 +132,27 @@ impl ProcessControlBlock {
                     exit_code: 0,
                     fd_table: vec![
                         // 0 -> stdin
-                        Some(Arc::new(Stdin)),
+                        Some(FileDescriptor::new(Arc::new(Stdin), FdFlags::empty())),
                         // 1 -> stdout
-                        Some(Arc::new(Stdout)),
+                        Some(FileDescriptor::new(Arc::new(Stdout), FdFlags::empty())),
                         // 2 -> stderr
-                        Some(Arc::new(Stdout)),
+                        Some(FileDescriptor::new(Arc::new(Stdout), FdFlags::empty())),
                     ],
                     tasks: Vec::new(),
                     task_res_allocator: RecycleAllocator::new(),
                     mutex_list: Vec::new(),
                     semaphore_list: Vec::new(),
                     condvar_list: Vec::new(),
-                    is_enable_deadlock_detection: false,
-                    sem_work: Vec::new(),
-                    sem_alloc: Vec::new(),
-                    sem_need: Vec::new(),
+                    rwlock_list: Vec::new(),
+                    barrier_list: Vec::new(),
+                    futex_table: FutexTable::new(),
+                    deadlock_mode: DeadlockMode::Off,
+                    deadlock_report: None,
+                    max_claims: BTreeMap::new(),
+                    avoidance_waiters: VecDeque::new(),
+                    mailbox: Mailbox::new(),
+                    cwd: String::from("/"),
+                    syscall_counts: BTreeMap::new(),
                 })
             },
         });
@@ -298,6 +179,7 @@ impl ProcessControlBlock {
         let mut process_inner = process.inner_exclusive_access();
         process_inner.tasks.push(Some(Arc::clone(&task)));
         drop(process_inner);
+        insert_into_pid2process(process.getpid(), Arc::clone(&process));
         // add main thread to scheduler
         add_task(task);
         process
@@ -305,21 +187,33 @@ impl ProcessControlBlock {
 
     // LAB5 HINT: How to initialize deadlock data structures?
     /// Load a new elf to replace the original application address space and start execution
-    /// Only support processes with a single thread.
+    /// Other threads are terminated first, the calling thread becomes the main thread.
     pub fn exec(self: &Arc<Self>, elf_data: &[u8], args: Vec<String>) {
-        assert_eq!(self.inner_exclusive_access().thread_count(), 1);
+        self.kill_other_threads();
         // memory_set with elf program headers/trampoline/trap context/user stack
         let (memory_set, ustack_base, entry_point) = MemorySet::from_elf(elf_data);
         let new_token = memory_set.token();
-        // substitute memory_set
-        self.inner_exclusive_access().memory_set = memory_set;
+        let task = current_task().unwrap();
+        // release tid and user resource of the calling thread in the old memory_set
+        let old_res = task.inner_exclusive_access().res.take();
+        drop(old_res);
+        // substitute memory_set, all tids are free again
+        let mut inner = self.inner_exclusive_access();
+        inner.memory_set = memory_set;
+        inner.tasks = vec![Some(Arc::clone(&task))];
+        inner.task_res_allocator = RecycleAllocator::new();
+        for fd in inner.fd_table.iter_mut() {
+            if fd.as_ref().map_or(false, |fd| fd.flags.contains(FdFlags::CLOEXEC)) {
+                fd.take();
+            }
+        }
+        drop(inner);
         // then we alloc user resource for main thread again
         // since memory_set has been changed
-        let task = self.inner_exclusive_access().get_task(0);
+        let res = TaskUserRes::new(Arc::clone(self), ustack_base, true);
         let mut task_inner = task.inner_exclusive_access();
-        task_inner.res.as_mut().unwrap().ustack_base = ustack_base;
-        task_inner.res.as_mut().unwrap().alloc_user_res();
-        task_inner.trap_cx_ppn = task_inner.res.as_mut().unwrap().trap_cx_ppn();
+        task_inner.trap_cx_ppn = res.trap_cx_ppn();
+        task_inner.res = Some(res);
         // push arguments on user stack
         let mut user_sp = task_inner.res.as_mut().unwrap().ustack_top();
         user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
@@ -360,16 +254,40 @@ impl ProcessControlBlock {
 
     // LAB5 HINT: How to initialize deadlock data structures?
     /// Fork from parent to child
-    /// Only support processes with a single thread.
+    /// Only the calling thread is duplicated, it becomes the main thread of the child.
     pub fn fork(self: &Arc<Self>) -> Arc<Self> {
         let mut parent = self.inner_exclusive_access();
-        assert_eq!(parent.thread_count(), 1);
+        let caller = current_task().unwrap();
+        let caller_inner = caller.inner_exclusive_access();
+        let caller_res = caller_inner.res.as_ref().unwrap();
+        let (tid, ustack_base) = (caller_res.tid, caller_res.ustack_base());
         // clone parent's memory_set completely including trampoline/ustacks/trap_cxs
-        let memory_set = MemorySet::from_existed_user(&parent.memory_set);
+        let mut memory_set = MemorySet::from_existed_user(&parent.memory_set);
+        // the other threads are not duplicated, drop their ustacks and trap_cxs in the child
+        let other_tids: Vec<usize> = parent
+            .tasks
+            .iter()
+            .flatten()
+            .filter(|task| !Arc::ptr_eq(task, &caller))
+            .filter_map(|task| task.inner_exclusive_access().res.as_ref().map(|res| res.tid))
+            .collect();
+        for other_tid in other_tids {
+            let ustack_bottom_va: VirtAddr = ustack_bottom_from_tid(ustack_base, other_tid).into();
+            memory_set.remove_area_with_start_vpn(ustack_bottom_va.into());
+            if other_tid != 0 {
+                let trap_cx_bottom_va: VirtAddr = trap_cx_bottom_from_tid(other_tid).into();
+                memory_set.remove_area_with_start_vpn(trap_cx_bottom_va.into());
+            }
+        }
+        // the calling thread takes tid 0 in the child and keeps its trap_cx there
+        if tid != 0 {
+            let trap_cx_bottom_va: VirtAddr = trap_cx_bottom_from_tid(tid).into();
+            memory_set.remove_area_with_start_vpn(trap_cx_bottom_va.into());
+        }
         // alloc a pid
         let pid = pid_alloc();
         // copy fd table
-        let mut new_fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = Vec::new();
+        let mut new_fd_table: Vec<Option<FileDescriptor>> = Vec::new();
         for fd in parent.fd_table.iter() {
             if let Some(file) = fd {
                 new_fd_table.push(Some(file.clone()));
@@ -393,25 +311,26 @@ impl ProcessControlBlock {
                     mutex_list: Vec::new(),
                     semaphore_list: Vec::new(),
                     condvar_list: Vec::new(),
-                    is_enable_deadlock_detection: false,
-                    sem_work: Vec::new(),
-                    sem_alloc: Vec::new(),
-                    sem_need: Vec::new(),
+                    rwlock_list: Vec::new(),
+                    barrier_list: Vec::new(),
+                    futex_table: FutexTable::new(),
+                    deadlock_mode: DeadlockMode::Off,
+                    deadlock_report: None,
+                    max_claims: BTreeMap::new(),
+                    avoidance_waiters: VecDeque::new(),
+                    mailbox: Mailbox::new(),
+                    cwd: parent.cwd.clone(),
+                    syscall_counts: BTreeMap::new(),
                 })
             },
         });
         // add child
         parent.children.push(Arc::clone(&child));
         // create main thread of child process
+        // ustack_base is moved so that tid 0 finds the ustack of the calling thread
         let task = Arc::new(TaskControlBlock::new(
             Arc::clone(&child),
-            parent
-                .get_task(0)
-                .inner_exclusive_access()
-                .res
-                .as_ref()
-                .unwrap()
-                .ustack_base(),
+            ustack_bottom_from_tid(ustack_base, tid),
             // here we do not allocate trap_cx or ustack again
             // but mention that we allocate a new kernel_stack here
             false,
@@ -420,16 +339,71 @@ impl ProcessControlBlock {
         let mut child_inner = child.inner_exclusive_access();
         child_inner.tasks.push(Some(Arc::clone(&task)));
         drop(child_inner);
-        // modify kernel_stack_top in trap_cx of this thread
+        // copy trap_cx of the calling thread and modify kernel_stack_top in it
         let task_inner = task.inner_exclusive_access();
+        task_inner
+            .trap_cx_ppn
+            .get_bytes_array()
+            .copy_from_slice(caller_inner.trap_cx_ppn.get_bytes_array());
         let trap_cx = task_inner.get_trap_cx();
         trap_cx.kernel_sp = task.kernel_stack.get_top();
         drop(task_inner);
+        insert_into_pid2process(child.getpid(), Arc::clone(&child));
         // add this thread to scheduler
         add_task(task);
         child
     }
 
+    /// Ask every other thread to exit and wait until all of them have exited
+    fn kill_other_threads(&self) {
+        let task = current_task().unwrap();
+        loop {
+            let inner = self.inner_exclusive_access();
+            let others: Vec<Arc<TaskControlBlock>> = inner
+                .tasks
+                .iter()
+                .flatten()
+                .filter(|other| !Arc::ptr_eq(other, &task))
+                .filter(|other| other.inner_exclusive_access().exit_code.is_none())
+                .cloned()
+                .collect();
+            if others.is_empty() {
+                break;
+            }
+            let mutex_list: Vec<Arc<dyn Mutex>> = inner.mutex_list.iter().flatten().cloned().collect();
+            let semaphore_list: Vec<Arc<Semaphore>> =
+                inner.semaphore_list.iter().flatten().cloned().collect();
+            let condvar_list: Vec<Arc<Condvar>> =
+                inner.condvar_list.iter().flatten().cloned().collect();
+            let rwlock_list: Vec<Arc<RwLock>> = inner.rwlock_list.iter().flatten().cloned().collect();
+            let barrier_list: Vec<Arc<Barrier>> =
+                inner.barrier_list.iter().flatten().cloned().collect();
+            let files: Vec<Arc<dyn File + Send + Sync>> =
+                inner.fd_table.iter().flatten().map(|fd| Arc::clone(&fd.file)).collect();
+            drop(inner);
+            for other in others.iter() {
+                other.inner_exclusive_access().killed = true;
+                // a blocked thread has to be woken up to reach its exit
+                let blocked = remove_timer(other)
+                    || mutex_list.iter().any(|mutex| mutex.remove_waiter(other))
+                    || semaphore_list.iter().any(|sem| sem.remove_waiter(other))
+                    || condvar_list.iter().any(|condvar| condvar.remove_waiter(other))
+                    || rwlock_list.iter().any(|rwlock| rwlock.remove_waiter(other))
+                    || barrier_list.iter().any(|barrier| barrier.remove_waiter(other))
+                    || self.inner_exclusive_access().futex_table.remove(other)
+                    || self.inner_exclusive_access().remove_avoidance_waiter(other)
+                    || self.inner_exclusive_access().mailbox.remove_waiter(other)
+                    || files.iter().any(|file| file.unregister_waiter(other))
+                    || remove_fifo_waiter(other);
+                if blocked {
+                    wakeup_task(Arc::clone(other));
+                }
+            }
+            drop(others);
+            suspend_current_and_run_next();
+        }
+    }
+
     pub fn getpid(&self) -> usize {
         self.pid.0
     }
@@ -452,10 +426,16 @@ impl ProcessControlBlock {
                     mutex_list: Vec::new(),
                     semaphore_list: Vec::new(),
                     condvar_list: Vec::new(),
-                    is_enable_deadlock_detection: false,
-                    sem_work: Vec::new(),
-                    sem_alloc: Vec::new(),
-                    sem_need: Vec::new(),
+                    rwlock_list: Vec::new(),
+                    barrier_list: Vec::new(),
+                    futex_table: FutexTable::new(),
+                    deadlock_mode: DeadlockMode::Off,
+                    deadlock_report: None,
+                    max_claims: BTreeMap::new(),
+                    avoidance_waiters: VecDeque::new(),
+                    mailbox: Mailbox::new(),
+                    cwd: String::from("/"),
+                    syscall_counts: BTreeMap::new(),
                 })
             },
         });
@@ -2,6 +2,7 @@
 
 use super::id::TaskUserRes;
 use super::{kstack_alloc, KernelStack, ProcessControlBlock, TaskContext};
+use crate::config::DEFAULT_PRIORITY;
 use crate::trap::TrapContext;
 use crate::{mm::PhysPageNum, sync::UPSafeCell};
 use alloc::sync::{Arc, Weak};
@@ -34,6 +35,16 @@ pub struct TaskControlBlockInner {
     pub exit_code: Option<i32>,
     /// Tid and ustack will be deallocated when this goes None
     pub res: Option<TaskUserRes>,
+    /// Set when another thread of the process asks this one to exit,
+    /// it exits on its way back to user mode
+    pub killed: bool,
+    /// Priority set by the thread itself
+    pub priority: usize,
+    /// Priority used for scheduling, raised above `priority` while a thread
+    /// of higher priority waits for a mutex this one holds
+    pub effective_priority: usize,
+    /// Stride scheduling pass, the ready task with the smallest one runs next
+    pub pass: usize,
 }
 
 /// Simple access to its internal fields
@@ -73,6 +84,10 @@ impl TaskControlBlock {
                     task_cx: TaskContext::goto_trap_return(kstack_top),
                     task_status: TaskStatus::Ready,
                     exit_code: None,
+                    killed: false,
+                    priority: DEFAULT_PRIORITY,
+                    effective_priority: DEFAULT_PRIORITY,
+                    pass: 0,
                 })
             },
         }
@@ -124,13 +139,17 @@ impl TaskControlBlock {
                     task_cx: context,
                     task_status: TaskStatus::Ready,
                     exit_code: None,
+                    killed: false,
+                    priority: DEFAULT_PRIORITY,
+                    effective_priority: DEFAULT_PRIORITY,
+                    pass: 0,
                 })
             },
         }
     }
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 /// task status: UnInit, Ready, Running, Exited
 pub enum TaskStatus {
     UnInit,
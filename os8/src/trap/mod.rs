@@ -18,7 +18,7 @@ use crate::config::TRAMPOLINE;
 use crate::syscall::syscall;
 use crate::task::{
     current_trap_cx, current_trap_cx_user_va, current_user_token, exit_current_and_run_next,
-    suspend_current_and_run_next,
+    exit_current_if_killed, suspend_current_and_run_next,
 };
 use crate::timer::{check_timer, set_next_trigger};
 use riscv::register::{
@@ -100,6 +100,7 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    exit_current_if_killed();
     trap_return();
 }
 
@@ -14,7 +14,7 @@ const BS: u8 = 0x08u8;
 use alloc::string::String;
 use alloc::vec::Vec;
 use user_lib::console::getchar;
-use user_lib::{close, dup, exec, flush, fork, open, waitpid, OpenFlags};
+use user_lib::{close, dup2, exec, flush, fork, open, waitpid, OpenFlags};
 
 #[no_mangle]
 pub fn main() -> i32 {
@@ -77,21 +77,21 @@ pub fn main() -> i32 {
                                 return -4;
                             }
                             let input_fd = input_fd as usize;
-                            close(0);
-                            assert_eq!(dup(input_fd), 0);
+                            assert_eq!(dup2(input_fd, 0), 0);
                             close(input_fd);
                         }
                         // output redirection
                         if !output.is_empty() {
-                            let output_fd =
-                                open(output.as_str(), OpenFlags::CREATE | OpenFlags::WRONLY);
+                            let output_fd = open(
+                                output.as_str(),
+                                OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+                            );
                             if output_fd == -1 {
                                 println!("Error when opening file {}", output);
                                 return -4;
                             }
                             let output_fd = output_fd as usize;
-                            close(1);
-                            assert_eq!(dup(output_fd), 1);
+                            assert_eq!(dup2(output_fd, 1), 1);
                             close(output_fd);
                         }
                         // child process
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::{
+    deadlock_claim, enable_deadlock_avoid, mutex_blocking_create, mutex_lock, mutex_unlock,
+    RESOURCE_MUTEX,
+};
+use user_lib::{exit, sleep_blocking, thread_create, waittid};
+
+// thread 1 takes mutex A then B, thread 2 takes B then A
+// 理想结果：线程 2 申请 B 会导致不安全状态，被推迟到线程 1 释放 A、B 之后，两个线程都正常退出
+
+const A: usize = 0;
+const B: usize = 1;
+
+static FIRST_DONE: AtomicBool = AtomicBool::new(false);
+
+fn claim_both() {
+    assert_eq!(deadlock_claim(RESOURCE_MUTEX, A, 1), 0);
+    assert_eq!(deadlock_claim(RESOURCE_MUTEX, B, 1), 0);
+}
+
+fn first() -> ! {
+    claim_both();
+    assert_eq!(mutex_lock(A), 0);
+    sleep_blocking(50);
+    assert_eq!(mutex_lock(B), 0);
+    FIRST_DONE.store(true, Ordering::SeqCst);
+    mutex_unlock(B);
+    mutex_unlock(A);
+    exit(0)
+}
+
+fn second() -> ! {
+    claim_both();
+    sleep_blocking(20);
+    assert_eq!(mutex_lock(B), 0);
+    assert!(FIRST_DONE.load(Ordering::SeqCst));
+    assert_eq!(mutex_lock(A), 0);
+    mutex_unlock(A);
+    mutex_unlock(B);
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    enable_deadlock_avoid(true);
+    assert_eq!(mutex_blocking_create(), A as isize);
+    assert_eq!(mutex_blocking_create(), B as isize);
+    // requests beyond the declared claims are refused
+    assert_eq!(mutex_lock(A), -1);
+    assert_eq!(deadlock_claim(RESOURCE_MUTEX, 2, 1), -1);
+    let t1 = thread_create(first as usize, 0);
+    let t2 = thread_create(second as usize, 0);
+    assert_eq!(waittid(t1 as usize), 0);
+    assert_eq!(waittid(t2 as usize), 0);
+    println!("deadlock avoid test OK!");
+    0
+}
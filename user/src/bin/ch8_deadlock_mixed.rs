@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    deadlock_report, enable_deadlock_detect, mutex_blocking_create, mutex_lock, mutex_unlock,
+    semaphore_create, semaphore_down, semaphore_up, DeadlockReport, RESOURCE_MUTEX,
+    RESOURCE_SEMAPHORE,
+};
+use user_lib::{exit, sleep_blocking, thread_create, waittid};
+
+// thread 1 holds the mutex and waits for the semaphore, thread 2 holds the
+// semaphore and asks for the mutex
+// 理想结果：检测到死锁，报告中包含线程 1、2 以及互斥锁 0、信号量 0
+
+fn first() -> ! {
+    assert_eq!(mutex_lock(0), 0);
+    sleep_blocking(20);
+    assert_eq!(semaphore_down(0), 0);
+    semaphore_up(0);
+    mutex_unlock(0);
+    exit(0)
+}
+
+fn second() -> ! {
+    assert_eq!(semaphore_down(0), 0);
+    sleep_blocking(50);
+    assert_eq!(mutex_lock(0), -0xdead);
+    let mut report = DeadlockReport::new();
+    assert_eq!(deadlock_report(&mut report), 0);
+    assert_eq!(report.tids(), &[1, 2]);
+    assert_eq!(report.resources(), &[[RESOURCE_MUTEX, 0], [RESOURCE_SEMAPHORE, 0]]);
+    semaphore_up(0);
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    enable_deadlock_detect(true);
+    let mut report = DeadlockReport::new();
+    assert_eq!(deadlock_report(&mut report), -1);
+    assert_eq!(mutex_blocking_create(), 0);
+    assert_eq!(semaphore_create(1), 0);
+    let t1 = thread_create(first as usize, 0);
+    let t2 = thread_create(second as usize, 0);
+    assert_eq!(waittid(t1 as usize), 0);
+    assert_eq!(waittid(t2 as usize), 0);
+    println!("deadlock test mixed OK!");
+    0
+}
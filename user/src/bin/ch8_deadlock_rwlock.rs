@@ -0,0 +1,65 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    barrier_create, barrier_wait, deadlock_report, enable_deadlock_detect, mutex_blocking_create,
+    mutex_lock, mutex_unlock, rwlock_create, rwlock_read, rwlock_unlock, rwlock_write,
+    DeadlockReport, RESOURCE_MUTEX, RESOURCE_RWLOCK,
+};
+use user_lib::{exit, sleep_blocking, thread_create, waittid};
+
+// thread 1 reads under the rwlock and waits for the mutex, thread 2 holds
+// the mutex and asks to write
+// 理想结果：检测到死锁，报告中包含线程 1、2 以及互斥锁 0、读写锁 0
+// then the main thread waits at a barrier for a thread that exited instead
+// 理想结果：检测到死锁
+
+fn first() -> ! {
+    assert_eq!(rwlock_read(0), 0);
+    sleep_blocking(20);
+    assert_eq!(mutex_lock(0), 0);
+    mutex_unlock(0);
+    assert_eq!(rwlock_unlock(0), 0);
+    exit(0)
+}
+
+fn second() -> ! {
+    assert_eq!(mutex_lock(0), 0);
+    sleep_blocking(50);
+    assert_eq!(rwlock_write(0), -0xdead);
+    let mut report = DeadlockReport::new();
+    assert_eq!(deadlock_report(&mut report), 0);
+    assert_eq!(report.tids(), &[1, 2]);
+    assert_eq!(
+        report.resources(),
+        &[[RESOURCE_MUTEX, 0], [RESOURCE_RWLOCK, 0]]
+    );
+    mutex_unlock(0);
+    exit(0)
+}
+
+fn leaver() -> ! {
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    enable_deadlock_detect(true);
+    assert_eq!(mutex_blocking_create(), 0);
+    assert_eq!(rwlock_create(false), 0);
+    let t1 = thread_create(first as usize, 0);
+    let t2 = thread_create(second as usize, 0);
+    assert_eq!(waittid(t1 as usize), 0);
+    assert_eq!(waittid(t2 as usize), 0);
+    // the thread has exited but is not joined yet
+    assert_eq!(barrier_create(2), 0);
+    let t3 = thread_create(leaver as usize, 0);
+    sleep_blocking(20);
+    assert_eq!(barrier_wait(0), -0xdead);
+    assert_eq!(waittid(t3 as usize), 0);
+    println!("deadlock test rwlock OK!");
+    0
+}
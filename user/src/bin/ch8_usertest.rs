@@ -17,16 +17,46 @@ const TESTS: &[&str] = &[
     "ch5b_forktest2\0",
     "ch6b_filetest_simple\0",
     "ch7b_pipetest\0",
+    "ch8_deadlock_avoid\0",
+    "ch8_deadlock_mixed\0",
     "ch8_deadlock_mutex1\0",
+    "ch8_deadlock_rwlock\0",
     "ch8_deadlock_sem1\0",
     "ch8_deadlock_sem2\0",
+    "ch8b_barrier\0",
+    "ch8b_bounded_buffer\0",
+    "ch8b_condvar_broadcast\0",
     "ch8b_mpsc_sem\0",
+    "ch8b_futex_sync\0",
+    "ch8b_mail\0",
+    "ch8b_socket\0",
+    "ch8b_poll\0",
+    "ch8b_nonblock\0",
+    "ch8b_pipe_size\0",
+    "ch8b_fifo\0",
+    "ch8b_seek\0",
+    "ch8b_dir\0",
+    "ch8b_dup2\0",
+    "ch8b_mount\0",
+    "ch8b_tmpfs\0",
+    "ch8b_dev\0",
+    "ch8b_proc\0",
+    "ch8b_symlink\0",
+    "ch8b_rename\0",
+    "ch8b_longname\0",
+    "ch8b_fd_access\0",
     "ch8b_phil_din_mutex\0",
+    "ch8b_priority_inherit\0",
+    "ch8b_race_adder_futex\0",
     "ch8b_race_adder_mutex_spin\0",
+    "ch8b_rwlock\0",
+    "ch8b_sync_destroy\0",
     "ch8b_sync_sem\0",
+    "ch8b_sync_timeout\0",
     "ch8b_test_condvar\0",
     "ch8b_threads\0",
     "ch8b_threads_arg\0",
+    "ch8b_thread_fork_exec\0",
 ];
 
 const TEST_NUM: usize = TESTS.len();
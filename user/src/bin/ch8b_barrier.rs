@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{barrier_create, barrier_destroy, barrier_wait, exit, thread_create, waittid};
+
+/// 正确输出：
+/// barrier test passed!
+
+// every thread stores its round in its slot, and after the barrier all
+// slots must hold the same round
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 5;
+
+static SLOTS: [AtomicUsize; THREADS] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+static LEADERS: AtomicUsize = AtomicUsize::new(0);
+
+fn worker(idx: usize) -> ! {
+    for round in 1..=ROUNDS {
+        SLOTS[idx].store(round, Ordering::SeqCst);
+        if barrier_wait(0) == 1 {
+            LEADERS.fetch_add(1, Ordering::SeqCst);
+        }
+        for slot in SLOTS.iter() {
+            assert_eq!(slot.load(Ordering::SeqCst), round);
+        }
+        // nobody may start the next round before everyone has checked this one
+        if barrier_wait(0) == 1 {
+            LEADERS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(barrier_create(0), -1);
+    assert_eq!(barrier_create(THREADS), 0);
+    let mut tids = [0; THREADS];
+    for (idx, tid) in tids.iter_mut().enumerate() {
+        *tid = thread_create(worker as usize, idx);
+    }
+    for tid in tids {
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    assert_eq!(LEADERS.load(Ordering::SeqCst), 2 * ROUNDS);
+    assert_eq!(barrier_destroy(0), 0);
+    assert_eq!(barrier_wait(0), -1);
+    println!("barrier test passed!");
+    0
+}
@@ -0,0 +1,100 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    condvar_broadcast, condvar_create, condvar_signal, condvar_wait, mutex_blocking_create,
+    mutex_lock, mutex_unlock,
+};
+use user_lib::{exit, thread_create, waittid};
+
+/// 正确输出：
+/// bounded buffer test passed!
+
+// producers and consumers share a small ring buffer, once the producers are
+// done a broadcast tells all consumers to shut down
+
+const CAPACITY: usize = 4;
+const PRODUCERS: usize = 2;
+const CONSUMERS: usize = 3;
+const ITEMS: usize = 200;
+
+const MUTEX: usize = 0;
+const NOT_EMPTY: usize = 0;
+const NOT_FULL: usize = 1;
+
+static mut BUFFER: [usize; CAPACITY] = [0; CAPACITY];
+static mut HEAD: usize = 0;
+static mut LEN: usize = 0;
+static mut SHUTDOWN: bool = false;
+static mut CONSUMED: [usize; CONSUMERS] = [0; CONSUMERS];
+static mut SUM: usize = 0;
+
+unsafe fn producer(idx: usize) -> ! {
+    for i in 0..ITEMS {
+        mutex_lock(MUTEX);
+        while LEN == CAPACITY {
+            condvar_wait(NOT_FULL, MUTEX);
+        }
+        BUFFER[(HEAD + LEN) % CAPACITY] = idx * ITEMS + i;
+        LEN += 1;
+        condvar_signal(NOT_EMPTY);
+        mutex_unlock(MUTEX);
+    }
+    exit(0)
+}
+
+unsafe fn consumer(idx: usize) -> ! {
+    loop {
+        mutex_lock(MUTEX);
+        while LEN == 0 && !SHUTDOWN {
+            condvar_wait(NOT_EMPTY, MUTEX);
+        }
+        if LEN == 0 {
+            mutex_unlock(MUTEX);
+            exit(0);
+        }
+        SUM += BUFFER[HEAD];
+        HEAD = (HEAD + 1) % CAPACITY;
+        LEN -= 1;
+        CONSUMED[idx] += 1;
+        condvar_signal(NOT_FULL);
+        mutex_unlock(MUTEX);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mutex_blocking_create() as usize, MUTEX);
+    assert_eq!(condvar_create() as usize, NOT_EMPTY);
+    assert_eq!(condvar_create() as usize, NOT_FULL);
+    let mut consumers = [0; CONSUMERS];
+    for (idx, tid) in consumers.iter_mut().enumerate() {
+        *tid = thread_create(consumer as usize, idx);
+    }
+    let mut producers = [0; PRODUCERS];
+    for (idx, tid) in producers.iter_mut().enumerate() {
+        *tid = thread_create(producer as usize, idx);
+    }
+    for tid in producers {
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    mutex_lock(MUTEX);
+    unsafe {
+        SHUTDOWN = true;
+    }
+    condvar_broadcast(NOT_EMPTY);
+    mutex_unlock(MUTEX);
+    for tid in consumers {
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    let total = PRODUCERS * ITEMS;
+    unsafe {
+        assert_eq!(CONSUMED.iter().sum::<usize>(), total);
+        assert_eq!(SUM, total * (total - 1) / 2);
+    }
+    println!("bounded buffer test passed!");
+    0
+}
@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    condvar_broadcast, condvar_create, condvar_signal, condvar_wait, mutex_blocking_create,
+    mutex_lock, mutex_unlock,
+};
+use user_lib::{exit, sleep_blocking, thread_create, waittid};
+
+/// 正确输出：
+/// condvar broadcast test passed!
+
+// waiters check the condition in a loop, so that being woken up without it
+// holding (by a signal meant for another waiter) does no harm
+
+const WAITERS: usize = 3;
+const CONDVAR_ID: usize = 0;
+const MUTEX_ID: usize = 0;
+
+static mut OPEN: bool = false;
+static mut PASSED: usize = 0;
+
+unsafe fn waiter() -> ! {
+    mutex_lock(MUTEX_ID);
+    while !OPEN {
+        condvar_wait(CONDVAR_ID, MUTEX_ID);
+    }
+    // woken holding the mutex again
+    PASSED += 1;
+    mutex_unlock(MUTEX_ID);
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(condvar_create() as usize, CONDVAR_ID);
+    assert_eq!(mutex_blocking_create() as usize, MUTEX_ID);
+    let mut tids = [0; WAITERS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(waiter as usize, 0);
+    }
+    sleep_blocking(50);
+    // a wakeup without the condition sends the waiter back to sleep
+    mutex_lock(MUTEX_ID);
+    condvar_signal(CONDVAR_ID);
+    mutex_unlock(MUTEX_ID);
+    sleep_blocking(50);
+    assert_eq!(unsafe { PASSED }, 0);
+    mutex_lock(MUTEX_ID);
+    unsafe {
+        OPEN = true;
+    }
+    assert_eq!(condvar_broadcast(CONDVAR_ID), WAITERS as isize);
+    // nobody gets past the wait while we hold the mutex
+    sleep_blocking(20);
+    assert_eq!(unsafe { PASSED }, 0);
+    mutex_unlock(MUTEX_ID);
+    for tid in tids {
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    assert_eq!(unsafe { PASSED }, WAITERS);
+    assert_eq!(condvar_broadcast(CONDVAR_ID), 0);
+    assert_eq!(condvar_broadcast(1), -1);
+    println!("condvar broadcast test passed!");
+    0
+}
@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, getdents, open, read, write, Dirents, OpenFlags, DT_CHR};
+
+/// 正确输出：
+/// hello from /dev/console
+/// dev test passed!
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let null = open("/dev/null\0", OpenFlags::RDWR);
+    assert!(null > 0);
+    let null = null as usize;
+    let mut buf = [7u8; 64];
+    assert_eq!(write(null, b"discarded"), 9);
+    assert_eq!(read(null, &mut buf), 0);
+    close(null);
+
+    let zero = open("/dev/zero\0", OpenFlags::RDONLY);
+    assert!(zero > 0);
+    let zero = zero as usize;
+    assert_eq!(read(zero, &mut buf), 64);
+    assert!(buf.iter().all(|byte| *byte == 0));
+    // opened for reading only
+    assert_eq!(write(zero, b"x"), -1);
+    close(zero);
+
+    let random = open("/dev/random\0", OpenFlags::RDONLY);
+    assert!(random > 0);
+    let random = random as usize;
+    let mut other = [0u8; 64];
+    assert_eq!(read(random, &mut buf), 64);
+    assert_eq!(read(random, &mut other), 64);
+    assert_ne!(buf, other);
+    close(random);
+
+    let console = open("/dev/console\0", OpenFlags::WRONLY);
+    assert!(console > 0);
+    let line = b"hello from /dev/console\n";
+    assert_eq!(write(console as usize, line), line.len() as isize);
+    close(console as usize);
+
+    // devices are listed, and no new ones can be created
+    assert_eq!(
+        open(
+            "/dev/null\0",
+            OpenFlags::CREATE | OpenFlags::EXCL | OpenFlags::WRONLY
+        ),
+        -1
+    );
+    assert_eq!(
+        open("/dev/nothing\0", OpenFlags::CREATE | OpenFlags::WRONLY),
+        -1
+    );
+    let dir = open("/dev\0", OpenFlags::RDONLY);
+    assert!(dir > 0);
+    let mut entries = [0u8; 256];
+    let len = getdents(dir as usize, &mut entries);
+    assert!(len > 0);
+    let mut count = 0;
+    for dirent in Dirents::new(&entries, len as usize) {
+        assert_eq!(dirent.d_type, DT_CHR);
+        count += 1;
+    }
+    assert_eq!(count, 4);
+    close(dir as usize);
+    println!("dev test passed!");
+    0
+}
@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{chdir, close, fork, getcwd, getdents, mkdir, mkfifo, open, read, waitpid, write};
+use user_lib::{Dirents, OpenFlags, DT_DIR, DT_FIFO, DT_REG};
+
+/// 正确输出：
+/// dir test passed!
+
+fn cwd(buf: &mut [u8]) -> &str {
+    let len = getcwd(buf);
+    assert!(len > 0);
+    core::str::from_utf8(&buf[..len as usize - 1]).unwrap()
+}
+
+/// Whether the directory at `path` holds `name` of type `d_type`
+fn has_entry(path: &str, name: &str, d_type: u8) -> bool {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    let mut buf = [0u8; 256];
+    let mut found = false;
+    loop {
+        let len = getdents(fd, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        for dirent in Dirents::new(&buf, len as usize) {
+            if dirent.name == name {
+                assert_eq!(dirent.d_type, d_type);
+                found = true;
+            }
+        }
+    }
+    close(fd);
+    found
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 64];
+    assert_eq!(cwd(&mut buf), "/");
+    // the directory is left over from an earlier run or made now
+    mkdir("dir0\0");
+    assert_eq!(mkdir("dir0\0"), -1);
+    assert!(has_entry("/\0", "dir0", DT_DIR));
+    assert_eq!(chdir("dir0\0"), 0);
+    assert_eq!(cwd(&mut buf), "/dir0");
+
+    // relative paths start at the working directory
+    let fd = open(
+        "file0\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"inside"), 6);
+    close(fd as usize);
+    mkfifo("fifo0\0");
+    mkdir("sub\0");
+    assert!(has_entry(".\0", "file0", DT_REG));
+    assert!(has_entry("/dir0\0", "fifo0", DT_FIFO));
+    assert!(has_entry("/dir0/./sub/..\0", "sub", DT_DIR));
+    assert!(!has_entry("/\0", "file0", DT_REG));
+
+    // directories are not written, read like files or made inside files
+    assert_eq!(open("sub\0", OpenFlags::WRONLY), -1);
+    assert_eq!(mkdir("file0/sub\0"), -1);
+    assert_eq!(chdir("file0\0"), -1);
+    assert_eq!(chdir("missing\0"), -1);
+    let fd = open("file0\0", OpenFlags::RDONLY) as usize;
+    assert_eq!(getdents(fd, &mut buf), -1);
+    close(fd);
+    let fd = open("sub\0", OpenFlags::RDONLY) as usize;
+    assert_eq!(read(fd, &mut buf), 0);
+    assert_eq!(getdents(fd, &mut buf), 0);
+    close(fd);
+    // an entry that does not fit is an error
+    let fd = open("/\0", OpenFlags::RDONLY) as usize;
+    assert_eq!(getdents(fd, &mut buf[..8]), -1);
+    close(fd);
+    assert_eq!(getcwd(&mut buf[..5]), -1);
+
+    // children inherit the working directory
+    let pid = fork();
+    if pid == 0 {
+        let mut buf = [0u8; 64];
+        assert_eq!(cwd(&mut buf), "/dir0");
+        assert_eq!(chdir("sub\0"), 0);
+        assert_eq!(cwd(&mut buf), "/dir0/sub");
+        return 0;
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(cwd(&mut buf), "/dir0");
+
+    assert_eq!(chdir("..\0"), 0);
+    assert_eq!(chdir("..\0"), 0);
+    assert_eq!(cwd(&mut buf), "/");
+    let fd = open("dir0/file0\0", OpenFlags::RDONLY) as usize;
+    assert_eq!(read(fd, &mut buf), 6);
+    assert_eq!(&buf[..6], b"inside");
+    close(fd);
+    println!("dir test passed!");
+    0
+}
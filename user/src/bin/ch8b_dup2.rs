@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, dup, dup2, dup3, fcntl, pipe, read, write, OpenFlags};
+use user_lib::{EMFILE, FD_CLOEXEC, F_GETFD, MAX_FD};
+
+/// 正确输出：
+/// dup2 test passed!
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    let (read_end, write_end) = (fds[0], fds[1]);
+    // a fd past the end of the table
+    assert_eq!(dup2(write_end, 40), 40);
+    assert_eq!(write(40, b"far"), 3);
+    let mut buf = [0u8; 8];
+    assert_eq!(read(read_end, &mut buf), 3);
+    assert_eq!(&buf[..3], b"far");
+    // an open fd is replaced
+    assert_eq!(dup2(read_end, 40), 40);
+    assert_eq!(write(write_end, b"near"), 4);
+    assert_eq!(read(40, &mut buf), 4);
+    assert_eq!(&buf[..4], b"near");
+    // the same fd is left alone by dup2 and refused by dup3
+    assert_eq!(dup2(40, 40), 40);
+    assert_eq!(dup3(40, 40, OpenFlags::empty()), -1);
+    assert_eq!(dup2(41, 42), -1);
+    assert_eq!(dup2(42, 42), -1);
+    assert_eq!(dup3(read_end, 41, OpenFlags::CLOEXEC), 41);
+    assert_eq!(fcntl(41, F_GETFD, 0), FD_CLOEXEC as isize);
+    assert_eq!(dup3(read_end, 41, OpenFlags::empty()), 41);
+    assert_eq!(fcntl(41, F_GETFD, 0), 0);
+    assert_eq!(dup3(read_end, 42, OpenFlags::RDWR), -1);
+    close(41);
+    close(40);
+
+    // the fd table is limited
+    assert_eq!(dup2(read_end, MAX_FD), EMFILE);
+    assert_eq!(dup2(read_end, MAX_FD - 1), (MAX_FD - 1) as isize);
+    let mut opened = 0;
+    loop {
+        let fd = dup(read_end);
+        if fd < 0 {
+            assert_eq!(fd, EMFILE);
+            break;
+        }
+        opened += 1;
+    }
+    assert!(opened > 0 && opened < MAX_FD);
+    assert_eq!(pipe(&mut fds), EMFILE);
+    for fd in 3..MAX_FD {
+        if fd != read_end && fd != write_end {
+            close(fd);
+        }
+    }
+    assert!(dup(read_end) > 0);
+    close(read_end);
+    close(write_end);
+    println!("dup2 test passed!");
+    0
+}
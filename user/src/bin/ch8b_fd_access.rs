@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, open, pipe, read, unlink, write, OpenFlags};
+
+/// 正确输出：
+/// fd access test passed!
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 8];
+    // a file is read and written only as it was opened
+    let fd = open("/tmp/access0\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"data"), 4);
+    assert_eq!(read(fd as usize, &mut buf), -1);
+    close(fd as usize);
+    let fd = open("/tmp/access0\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"more"), -1);
+    assert_eq!(read(fd as usize, &mut buf), 4);
+    close(fd as usize);
+    assert_eq!(unlink("/tmp/access0\0"), 0);
+
+    // so is each end of a pipe, and a device
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    assert_eq!(write(fds[0], b"x"), -1);
+    assert_eq!(read(fds[1], &mut buf), -1);
+    close(fds[0]);
+    close(fds[1]);
+    let fd = open("/dev/null\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"x"), -1);
+    close(fd as usize);
+    println!("fd access test passed!");
+    0
+}
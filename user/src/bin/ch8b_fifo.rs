@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, mkfifo, open, read, sleep_blocking, waitpid, write, OpenFlags};
+
+/// 正确输出：
+/// fifo test passed!
+
+const FIFO: &str = "fifo0\0";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // the fifo is left from an earlier run as there is no unlink
+    mkfifo(FIFO);
+    assert_eq!(mkfifo(FIFO), -1);
+    assert_eq!(open(FIFO, OpenFlags::RDWR), -1);
+    // no reader yet
+    assert_eq!(open(FIFO, OpenFlags::WRONLY | OpenFlags::NONBLOCK), -1);
+    let pid = fork();
+    if pid == 0 {
+        // waits for the reader
+        let fd = open(FIFO, OpenFlags::WRONLY);
+        assert!(fd >= 0);
+        assert_eq!(write(fd as usize, b"hello"), 5);
+        close(fd as usize);
+        exit(0);
+    }
+    sleep_blocking(20);
+    let fd = open(FIFO, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut buf = [0u8; 5];
+    assert_eq!(read(fd, &mut buf), 5);
+    assert_eq!(&buf, b"hello");
+    // end of file once the writer is gone
+    assert_eq!(read(fd, &mut buf), 0);
+    close(fd);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    // the reader waits first, and still opens once a writer has come and
+    // gone before it runs again
+    let pid = fork();
+    if pid == 0 {
+        sleep_blocking(20);
+        let fd = open(FIFO, OpenFlags::WRONLY);
+        assert!(fd >= 0);
+        assert_eq!(write(fd as usize, b"world"), 5);
+        close(fd as usize);
+        exit(0);
+    }
+    let fd = open(FIFO, OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    assert_eq!(read(fd, &mut buf), 5);
+    assert_eq!(&buf, b"world");
+    assert_eq!(read(fd, &mut buf), 0);
+    close(fd);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    // a reader that does not wait sees no data of the earlier pipe
+    let fd = open(FIFO, OpenFlags::RDONLY | OpenFlags::NONBLOCK);
+    assert!(fd >= 0);
+    assert_eq!(read(fd as usize, &mut buf), 0);
+    close(fd as usize);
+    println!("fifo test passed!");
+    0
+}
@@ -0,0 +1,84 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::sync::atomic::AtomicU32;
+use user_lib::{exit, futex_wait, get_time, sleep_blocking, thread_create, waittid};
+use user_lib::{Condvar, Mutex, RwLock};
+
+/// 正确输出：
+/// futex timeout OK!
+/// futex condvar OK!
+/// futex rwlock OK!
+/// futex sync test passed!
+
+const THREAD_COUNT: usize = 8;
+const ROUNDS: usize = 100;
+
+static READY: Mutex<usize> = Mutex::new(0);
+static GO: Condvar = Condvar::new();
+static TABLE: RwLock<[usize; 4]> = RwLock::new([0; 4]);
+
+fn waiter() -> ! {
+    let mut ready = READY.lock();
+    while *ready == 0 {
+        ready = GO.wait(ready);
+    }
+    *ready += 1;
+    exit(0)
+}
+
+fn reader_writer(id: usize) -> ! {
+    for round in 0..ROUNDS {
+        if round % 4 == id % 4 {
+            let mut table = TABLE.write();
+            for slot in table.iter_mut() {
+                *slot += 1;
+            }
+        } else {
+            // writers update every slot at once
+            let table = TABLE.read();
+            assert!(table.iter().all(|slot| *slot == table[0]));
+        }
+    }
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let futex = AtomicU32::new(0);
+    assert_eq!(futex_wait(&futex, 1, 0), -2);
+    let start = get_time();
+    assert_eq!(futex_wait(&futex, 0, 100), -3);
+    assert!(get_time() - start >= 100);
+    println!("futex timeout OK!");
+
+    let mut v = Vec::new();
+    for _ in 0..THREAD_COUNT {
+        v.push(thread_create(waiter as usize, 0) as usize);
+    }
+    sleep_blocking(50);
+    *READY.lock() = 1;
+    GO.notify_all();
+    for tid in v.drain(..) {
+        assert_eq!(waittid(tid), 0);
+    }
+    assert_eq!(*READY.lock(), THREAD_COUNT + 1);
+    println!("futex condvar OK!");
+
+    for id in 0..THREAD_COUNT {
+        v.push(thread_create(reader_writer as usize, id) as usize);
+    }
+    for tid in v.drain(..) {
+        assert_eq!(waittid(tid), 0);
+    }
+    let writes = THREAD_COUNT * ROUNDS / 4;
+    assert!(TABLE.read().iter().all(|slot| *slot == writes));
+    println!("futex rwlock OK!");
+    println!("futex sync test passed!");
+    0
+}
@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use user_lib::{close, getdents, open, read_file, rename, write_file, Dirents, OpenFlags};
+
+/// 正确输出：
+/// longname test passed!
+
+/// Whether the directory at `path` lists `name`
+fn listed(path: &str, name: &str) -> bool {
+    let dir = open(path, OpenFlags::RDONLY);
+    assert!(dir > 0);
+    let mut entries = [0u8; 512];
+    let mut found = false;
+    loop {
+        let len = getdents(dir as usize, &mut entries);
+        if len <= 0 {
+            break;
+        }
+        for dirent in Dirents::new(&entries, len as usize) {
+            if dirent.name == name {
+                found = true;
+            }
+        }
+    }
+    close(dir as usize);
+    found
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // on easy-fs, where the files are left over from an earlier run or made
+    // now, names take up to 255 bytes and keep their dots
+    let name: String = format!("{}.tar.gz", "l".repeat(248));
+    assert_eq!(name.len(), 255);
+    let path = format!("/{}\0", name);
+    assert_eq!(write_file(&path, b"long"), 4);
+    let mut buf = [0u8; 16];
+    assert_eq!(read_file(&path, &mut buf), 4);
+    assert_eq!(&buf[..4], b"long");
+    assert!(listed("/\0", &name));
+    let too_long = format!("/{}\0", "t".repeat(256));
+    assert_eq!(write_file(&too_long, b"no"), -1);
+    assert_eq!(read_file(&too_long, &mut buf), -1);
+
+    // and are renamed to and from as long ones
+    let old = format!("/{}.old\0", "r".repeat(240));
+    let new = format!("/{}.new\0", "r".repeat(240));
+    assert_eq!(write_file(&old, b"renamed"), 7);
+    assert_eq!(rename(&old, &new), 0);
+    assert_eq!(read_file(&old, &mut buf), -1);
+    assert_eq!(read_file(&new, &mut buf), 7);
+    assert_eq!(&buf[..7], b"renamed");
+    assert_eq!(rename(&new, &too_long), -1);
+
+    // the apps packed in are listed by their names
+    assert!(listed("/\0", "ch2b_hello_world"));
+    println!("longname test passed!");
+    0
+}
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, getpid, waitpid};
+use user_lib::{mail_read, mail_read_blocking, mail_write, MAILBOX_CAPACITY, MAIL_MAX_LEN};
+
+/// 正确输出：
+/// mail test passed!
+
+fn own_mailbox() {
+    let pid = getpid() as usize;
+    let mut buf = [0u8; MAIL_MAX_LEN + 44];
+    assert_eq!(mail_read(&mut buf), -1);
+    assert_eq!(mail_read(&mut []), -1);
+    assert_eq!(mail_write(pid, b"hello"), 5);
+    assert_eq!(mail_read(&mut []), 0);
+    assert_eq!(mail_read(&mut buf), 5);
+    assert_eq!(&buf[..5], b"hello");
+    // long messages are cut off and what does not fit is dropped
+    let long = [b'x'; MAIL_MAX_LEN + 44];
+    assert_eq!(mail_write(pid, &long), MAIL_MAX_LEN as isize);
+    assert_eq!(mail_write(pid, b"next"), 4);
+    assert_eq!(mail_read(&mut buf[..10]), 10);
+    assert_eq!(mail_read(&mut buf), 4);
+    assert_eq!(&buf[..4], b"next");
+    for i in 0..MAILBOX_CAPACITY {
+        assert_eq!(mail_write(pid, &[i as u8]), 1);
+    }
+    assert_eq!(mail_write(pid, b"full"), -1);
+    assert_eq!(mail_write(pid, &[]), -1);
+    for i in 0..MAILBOX_CAPACITY {
+        assert_eq!(mail_read(&mut buf), 1);
+        assert_eq!(buf[0], i as u8);
+    }
+    assert_eq!(mail_write(pid, &[]), 0);
+    assert_eq!(mail_write(usize::MAX, b"nobody"), -1);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    own_mailbox();
+    let parent = getpid() as usize;
+    let child = fork();
+    if child == 0 {
+        let mut buf = [0u8; 4];
+        assert_eq!(mail_read_blocking(&mut buf), 4);
+        assert_eq!(&buf, b"ping");
+        assert_eq!(mail_write(parent, b"pong"), 4);
+        exit(0);
+    }
+    let child = child as usize;
+    assert_eq!(mail_write(child, b"ping"), 4);
+    let mut buf = [0u8; 4];
+    assert_eq!(mail_read_blocking(&mut buf), 4);
+    assert_eq!(&buf, b"pong");
+    let mut exit_code = 0;
+    assert_eq!(waitpid(child, &mut exit_code), child as isize);
+    assert_eq!(exit_code, 0);
+    // the mailbox is gone with the process
+    assert_eq!(mail_write(child, b"late"), -1);
+    println!("mail test passed!");
+    0
+}
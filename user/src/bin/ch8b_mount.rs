@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{chdir, close, mkdir, mount, open, read, umount, write, OpenFlags};
+
+/// 正确输出：
+/// mount test passed!
+
+/// Mount the second disk on `/mnt0` and use it, if the disk is there
+fn second_disk() {
+    if mount("/dev/vdb\0", "/mnt0\0", "easyfs\0") != 0 {
+        println!("no second disk, skipping its part");
+        return;
+    }
+    assert_eq!(mount("/dev/vdb\0", "/mnt0\0", "easyfs\0"), -1);
+    let fd = open(
+        "/mnt0/file0\0",
+        OpenFlags::CREATE | OpenFlags::RDWR | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"second"), 6);
+    // the open file keeps the filesystem busy
+    assert_eq!(umount("/mnt0\0"), -2);
+    close(fd as usize);
+    // relative paths cross the mount point too
+    assert_eq!(chdir("/mnt0\0"), 0);
+    let fd = open("file0\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buf = [0u8; 8];
+    assert_eq!(read(fd as usize, &mut buf), 6);
+    assert_eq!(&buf[..6], b"second");
+    close(fd as usize);
+    assert_eq!(chdir("/\0"), 0);
+    assert_eq!(umount("/mnt0\0"), 0);
+    assert_eq!(open("/mnt0/file0\0", OpenFlags::RDONLY), -1);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // the directory is left over from an earlier run or made now
+    mkdir("mnt0\0");
+    // the root disk is mounted at / already
+    assert_eq!(mount("/dev/vda\0", "/mnt0\0", "easyfs\0"), -1);
+    assert_eq!(mount("/dev/vdb\0", "/mnt0\0", "nofs\0"), -1);
+    assert_eq!(mount("/dev/vdb\0", "/missing\0", "easyfs\0"), -1);
+    let fd = open("file0\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(mount("/dev/vdb\0", "/file0\0", "easyfs\0"), -1);
+    assert_eq!(umount("/mnt0\0"), -1);
+    assert_eq!(umount("/\0"), -2);
+    second_disk();
+    println!("mount test passed!");
+    0
+}
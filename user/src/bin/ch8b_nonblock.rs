@@ -0,0 +1,87 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use user_lib::{
+    close, dup, exec, exit, fcntl, fork, pipe, pipe2, read, set_pipe_size, waitpid, write,
+};
+use user_lib::{OpenFlags, FD_CLOEXEC, F_GETFD, F_GETFL, F_SETFD, F_SETFL};
+
+/// 正确输出：
+/// nonblock test passed!
+
+/// Run again by exec with the fd that has to stay open and the one that
+/// has to be closed
+fn after_exec(kept: &str, closed: &str) -> i32 {
+    let kept: usize = kept.parse().unwrap();
+    let closed: usize = closed.parse().unwrap();
+    assert_eq!(fcntl(closed, F_GETFD, 0), -1);
+    assert_eq!(write(kept, b"x"), 1);
+    0
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc == 3 {
+        return after_exec(argv[1], argv[2]);
+    }
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe2(&mut fds, OpenFlags::NONBLOCK), 0);
+    assert_eq!(set_pipe_size(fds[1], 32), 32);
+    let mut buf = [0u8; 64];
+    assert_eq!(read(fds[0], &mut buf), -2);
+    // a full pipe takes what fits, then nothing
+    assert_eq!(write(fds[1], &[1u8; 40]), 32);
+    assert_eq!(write(fds[1], &[1u8; 1]), -2);
+    assert_eq!(read(fds[0], &mut buf), 32);
+    assert_eq!(read(fds[0], &mut buf), -2);
+    assert_eq!(
+        fcntl(fds[0], F_GETFL, 0),
+        OpenFlags::NONBLOCK.bits() as isize
+    );
+    assert_eq!(fcntl(fds[0], F_SETFL, 0), 0);
+    assert_eq!(fcntl(fds[0], F_GETFL, 0), 0);
+    close(fds[1]);
+    // end of file instead of waiting
+    assert_eq!(read(fds[0], &mut buf), 0);
+    close(fds[0]);
+    assert_eq!(fcntl(fds[0], F_GETFL, 0), -1);
+
+    // close-on-exec is dropped by dup
+    assert_eq!(pipe2(&mut fds, OpenFlags::CLOEXEC), 0);
+    assert_eq!(fcntl(fds[1], F_GETFD, 0), FD_CLOEXEC as isize);
+    let kept = dup(fds[1]) as usize;
+    assert_eq!(fcntl(kept, F_GETFD, 0), 0);
+    let mut other = [0usize; 2];
+    assert_eq!(pipe(&mut other), 0);
+    assert_eq!(fcntl(other[1], F_GETFD, 0), 0);
+    assert_eq!(fcntl(other[1], F_SETFD, FD_CLOEXEC), 0);
+    let pid = fork();
+    if pid == 0 {
+        let kept = format!("{}\0", kept);
+        let closed = format!("{}\0", other[1]);
+        let args = [
+            "ch8b_nonblock\0".as_ptr(),
+            kept.as_ptr(),
+            closed.as_ptr(),
+            core::ptr::null::<u8>(),
+        ];
+        exec("ch8b_nonblock\0", &args);
+        exit(-1);
+    }
+    close(fds[1]);
+    close(kept);
+    close(other[1]);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    assert_eq!(read(fds[0], &mut buf[..1]), 1);
+    assert_eq!(buf[0], b'x');
+    assert_eq!(read(other[0], &mut buf[..1]), 0);
+    println!("nonblock test passed!");
+    0
+}
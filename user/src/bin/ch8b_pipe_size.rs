@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::PIPE_MAX_SIZE;
+use user_lib::{close, exit, fork, pipe, pipe_size, read, set_pipe_size, waitpid, write};
+
+/// 正确输出：
+/// pipe size test passed!
+
+const CHUNK: usize = 2048;
+const TOTAL: usize = 128 * CHUNK;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    assert_eq!(pipe_size(fds[0]), 4096);
+    assert_eq!(pipe_size(0), -1);
+    assert_eq!(set_pipe_size(fds[1], 0), -1);
+    assert_eq!(set_pipe_size(fds[1], PIPE_MAX_SIZE + 1), -1);
+    assert_eq!(set_pipe_size(fds[1], 32), 32);
+    // data wrapping around the end of the buffer survives a resize
+    let mut data = [0u8; 40];
+    data.iter_mut().enumerate().for_each(|(i, byte)| *byte = i as u8);
+    assert_eq!(write(fds[1], &data[..20]), 20);
+    let mut buf = [0u8; 40];
+    assert_eq!(read(fds[0], &mut buf[..10]), 10);
+    assert_eq!(write(fds[1], &data[20..40]), 20);
+    assert_eq!(set_pipe_size(fds[0], 29), -1);
+    assert_eq!(set_pipe_size(fds[0], 30), 30);
+    assert_eq!(pipe_size(fds[1]), 30);
+    assert_eq!(read(fds[0], &mut buf[..30]), 30);
+    assert_eq!(&buf[..30], &data[10..40]);
+    assert_eq!(set_pipe_size(fds[0], PIPE_MAX_SIZE), PIPE_MAX_SIZE as isize);
+
+    let pid = fork();
+    if pid == 0 {
+        close(fds[0]);
+        let mut chunk = [0u8; CHUNK];
+        for i in 0..TOTAL / CHUNK {
+            chunk.iter_mut().for_each(|byte| *byte = i as u8);
+            assert_eq!(write(fds[1], &chunk), CHUNK as isize);
+        }
+        close(fds[1]);
+        exit(0);
+    }
+    close(fds[1]);
+    let mut chunk = [0u8; CHUNK];
+    for i in 0..TOTAL / CHUNK {
+        assert_eq!(read(fds[0], &mut chunk), CHUNK as isize);
+        assert!(chunk.iter().all(|byte| *byte == i as u8));
+    }
+    assert_eq!(read(fds[0], &mut chunk), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // writing fails once the read end is gone
+    assert_eq!(pipe(&mut fds), 0);
+    close(fds[0]);
+    assert_eq!(write(fds[1], b"lost"), -1);
+    assert_eq!(write(fds[1], b""), 0);
+    close(fds[1]);
+    println!("pipe size test passed!");
+    0
+}
@@ -0,0 +1,82 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, exit, fork, get_time, pipe, read, sleep_blocking, waitpid, write};
+use user_lib::{poll, select, FdSet, PollEvents, PollFd};
+
+/// 正确输出：
+/// poll test passed!
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut a = [0usize; 2];
+    let mut b = [0usize; 2];
+    assert_eq!(pipe(&mut a), 0);
+    assert_eq!(pipe(&mut b), 0);
+    let mut fds = [
+        PollFd::new(a[0], PollEvents::IN),
+        PollFd::new(b[0], PollEvents::IN),
+        PollFd::new(b[1], PollEvents::OUT),
+    ];
+    // only the write end is ready
+    assert_eq!(poll(&mut fds, 0), 1);
+    assert_eq!(fds[2].revents, PollEvents::OUT);
+    assert_eq!(write(b[1], b"b"), 1);
+    assert_eq!(poll(&mut fds[..2], 0), 1);
+    assert!(fds[0].revents.is_empty());
+    assert_eq!(fds[1].revents, PollEvents::IN);
+    let mut buf = [0u8; 1];
+    assert_eq!(read(b[0], &mut buf), 1);
+    // timeout
+    let start = get_time();
+    assert_eq!(poll(&mut fds[..2], 30), 0);
+    assert!(get_time() - start >= 30);
+    // fds that are not open
+    let mut bad = [
+        PollFd::new(99, PollEvents::IN),
+        PollFd::new(0, PollEvents::IN),
+    ];
+    bad[1].fd = -1;
+    assert_eq!(poll(&mut bad, 0), 1);
+    assert_eq!(bad[0].revents, PollEvents::NVAL);
+    assert!(bad[1].revents.is_empty());
+    let mut set = FdSet::new();
+    set.insert(99);
+    assert_eq!(select(100, Some(&mut set), None, 0), -1);
+
+    let pid = fork();
+    if pid == 0 {
+        close(a[0]);
+        close(b[0]);
+        sleep_blocking(20);
+        assert_eq!(write(a[1], b"a"), 1);
+        sleep_blocking(20);
+        close(a[1]);
+        close(b[1]);
+        exit(0);
+    }
+    close(a[1]);
+    close(b[1]);
+    // woken up by the write of the child
+    let mut readfds = FdSet::new();
+    readfds.insert(a[0]);
+    readfds.insert(b[0]);
+    assert_eq!(select(b[0] + 1, Some(&mut readfds), None, -1), 1);
+    assert!(readfds.contains(a[0]));
+    assert!(!readfds.contains(b[0]));
+    assert_eq!(read(a[0], &mut buf), 1);
+    assert_eq!(buf[0], b'a');
+    // and by the child closing both pipes
+    let mut fds = [PollFd::new(a[0], PollEvents::IN)];
+    assert_eq!(poll(&mut fds, -1), 1);
+    assert!(fds[0].revents.contains(PollEvents::HUP));
+    assert_eq!(read(a[0], &mut buf), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("poll test passed!");
+    0
+}
@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use user_lib::{exit, mutex_blocking_create, mutex_lock, mutex_unlock};
+use user_lib::{set_priority, thread_create, waittid, yield_};
+
+/// 正确输出：
+/// priority inheritance test passed!
+
+// a low priority thread holds the lock the high priority thread waits for
+// while a medium priority thread keeps the CPU busy. Running with the
+// priority of the waiter, the holder is done before medium got much further.
+
+const WORK: usize = 100;
+
+static LOW_LOCKED: AtomicBool = AtomicBool::new(false);
+static HIGH_WAITING: AtomicBool = AtomicBool::new(false);
+static DONE: AtomicBool = AtomicBool::new(false);
+static MEDIUM_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn low() -> ! {
+    set_priority(2);
+    assert_eq!(mutex_lock(0), 0);
+    LOW_LOCKED.store(true, Ordering::SeqCst);
+    for _ in 0..WORK {
+        yield_();
+    }
+    mutex_unlock(0);
+    exit(0)
+}
+
+fn medium() -> ! {
+    set_priority(64);
+    while !DONE.load(Ordering::SeqCst) {
+        if HIGH_WAITING.load(Ordering::SeqCst) {
+            MEDIUM_COUNT.fetch_add(1, Ordering::SeqCst);
+        }
+        yield_();
+    }
+    exit(0)
+}
+
+fn high() -> ! {
+    set_priority(64);
+    while !LOW_LOCKED.load(Ordering::SeqCst) {
+        yield_();
+    }
+    HIGH_WAITING.store(true, Ordering::SeqCst);
+    assert_eq!(mutex_lock(0), 0);
+    DONE.store(true, Ordering::SeqCst);
+    mutex_unlock(0);
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mutex_blocking_create(), 0);
+    let tids = [
+        thread_create(low as usize, 0),
+        thread_create(medium as usize, 0),
+        thread_create(high as usize, 0),
+    ];
+    for tid in tids {
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    let count = MEDIUM_COUNT.load(Ordering::SeqCst);
+    // without inheritance medium runs about 32 times as often as low
+    assert!(count < 4 * WORK, "medium ran {} times", count);
+    println!("priority inheritance test passed!");
+    0
+}
@@ -0,0 +1,133 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use user_lib::{
+    close, exit, fork, getdents, getpid, mkdir, open, read, waitpid, write, Dirents, OpenFlags,
+    DT_DIR,
+};
+
+/// 正确输出：
+/// proc test passed!
+
+/// Read the whole file at `path`, which ends with a NUL
+fn read_file(path: &str) -> Option<String> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut content = String::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let len = read(fd as usize, &mut buf);
+        if len <= 0 {
+            break;
+        }
+        content.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap());
+    }
+    close(fd as usize);
+    Some(content)
+}
+
+/// The value of the line `key: value` in `content`
+fn field<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(": "))
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid();
+    let status = read_file("/proc/self/status\0").unwrap();
+    assert_eq!(field(&status, "pid"), Some(format!("{}", pid).as_str()));
+    assert_eq!(field(&status, "threads"), Some("1"));
+    assert_eq!(field(&status, "cwd"), Some("/"));
+    assert_eq!(
+        read_file(&format!("/proc/{}/status\0", pid)).unwrap(),
+        status
+    );
+
+    // the parent of a child is this process
+    let child = fork();
+    if child == 0 {
+        let status = read_file("/proc/self/status\0").unwrap();
+        assert_eq!(field(&status, "ppid"), Some(format!("{}", pid).as_str()));
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, 0);
+    // an exited process is gone
+    assert!(read_file(&format!("/proc/{}/status\0", child)).is_none());
+
+    let threads = read_file("/proc/self/threads\0").unwrap();
+    assert_eq!(threads.lines().count(), 1);
+    assert!(threads.starts_with("0 Running"));
+
+    let fds = read_file("/proc/self/fds\0").unwrap();
+    assert!(fds.lines().any(|line| line == "0 r- other"));
+    assert!(fds.lines().any(|line| line.starts_with("3 r- inode")));
+
+    // code is readable and executable, the stack readable and writable
+    let maps = read_file("/proc/self/maps\0").unwrap();
+    assert!(maps.lines().any(|line| line.ends_with(" r-xu")));
+    assert!(maps.lines().any(|line| line.ends_with(" rw-u")));
+
+    // this process has called fork (220) once and waitpid (260) at least once
+    let syscalls = read_file("/proc/self/syscalls\0").unwrap();
+    assert!(syscalls.lines().any(|line| line == "220 1"));
+    assert!(syscalls.lines().any(|line| line.starts_with("260 ")));
+
+    let meminfo = read_file("/proc/meminfo\0").unwrap();
+    let total: usize = field(&meminfo, "total_frames").unwrap().parse().unwrap();
+    let free: usize = field(&meminfo, "free_frames").unwrap().parse().unwrap();
+    assert!(free > 0 && free < total);
+    let blockcache = read_file("/proc/blockcache\0").unwrap();
+    assert!(field(&blockcache, "misses").unwrap() != "0");
+    assert!(read_file("/proc/uptime\0").unwrap().contains('.'));
+    let mounts = read_file("/proc/mounts\0").unwrap();
+    assert!(mounts.lines().any(|line| line == "/dev/vda / easyfs"));
+    assert!(mounts.lines().any(|line| line == "none /proc procfs"));
+
+    // nothing in procfs can be written or created
+    assert_eq!(open("/proc/self/status\0", OpenFlags::WRONLY), -1);
+    assert_eq!(
+        open("/proc/new\0", OpenFlags::CREATE | OpenFlags::WRONLY),
+        -1
+    );
+    assert_eq!(mkdir("/proc/new\0"), -1);
+    let fd = open("/proc/uptime\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"0"), -1);
+    close(fd as usize);
+
+    // the directory of this process is listed
+    let dir = open("/proc\0", OpenFlags::RDONLY);
+    assert!(dir > 0);
+    let name = format!("{}", pid);
+    let mut found = false;
+    let mut entries = [0u8; 512];
+    loop {
+        let len = getdents(dir as usize, &mut entries);
+        if len <= 0 {
+            break;
+        }
+        for dirent in Dirents::new(&entries, len as usize) {
+            if dirent.name == name.as_str() {
+                assert_eq!(dirent.d_type, DT_DIR);
+                found = true;
+            }
+        }
+    }
+    assert!(found);
+    close(dir as usize);
+    println!("proc test passed!");
+    0
+}
@@ -0,0 +1,105 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{
+    close, ftruncate, mkdir, open, pread, read_file, rename, rmdir, symlink, unlink, write_file,
+    OpenFlags,
+};
+
+/// 正确输出：
+/// rename test passed!
+
+/// Shrink and grow the file at `path`, which holds `len` bytes
+fn truncate_test(path: &str, len: usize) {
+    // on the heap, the stack is too small for both
+    let mut data = vec![0u8; len];
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = (i % 251) as u8 + 1;
+    }
+    assert_eq!(write_file(path, &data), len as isize);
+    let fd = open(path, OpenFlags::RDWR);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(ftruncate(fd, 100), 0);
+    let mut buf = vec![0u8; len];
+    assert_eq!(pread(fd, &mut buf, 0), 100);
+    assert_eq!(&buf[..100], &data[..100]);
+    // the space added reads as zeros
+    assert_eq!(ftruncate(fd, len), 0);
+    assert_eq!(pread(fd, &mut buf, 0), len as isize);
+    assert_eq!(&buf[..100], &data[..100]);
+    assert!(buf[100..].iter().all(|byte| *byte == 0));
+    assert_eq!(ftruncate(fd, 0), 0);
+    assert_eq!(pread(fd, &mut buf, 0), 0);
+    // growing past what the filesystem holds fails, and takes no space
+    assert_eq!(ftruncate(fd, 3 << 30), -1);
+    assert_eq!(pread(fd, &mut buf, 0), 0);
+    assert_eq!(ftruncate(fd, len), 0);
+    assert_eq!(pread(fd, &mut buf, 0), len as isize);
+    close(fd);
+    // only a file open for writing is truncated
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(ftruncate(fd as usize, 0), -1);
+    close(fd as usize);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 64];
+    // a config is saved by writing a temporary file and renaming it, on
+    // easy-fs, where the config is left over from an earlier run or made now
+    assert_eq!(write_file("/rename_cfg\0", b"old"), 3);
+    assert_eq!(write_file("/rename_cfg.tmp\0", b"new config"), 10);
+    // not while the config is open, as easy-fs frees a file replaced
+    let fd = open("/rename_cfg\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(rename("/rename_cfg.tmp\0", "/rename_cfg\0"), -1);
+    assert_eq!(pread(fd as usize, &mut buf, 0), 3);
+    assert_eq!(&buf[..3], b"old");
+    close(fd as usize);
+    assert_eq!(rename("/rename_cfg.tmp\0", "/rename_cfg\0"), 0);
+    assert_eq!(read_file("/rename_cfg\0", &mut buf), 10);
+    assert_eq!(&buf[..10], b"new config");
+    assert_eq!(open("/rename_cfg.tmp\0", OpenFlags::RDONLY), -1);
+    assert_eq!(rename("/rename_cfg.tmp\0", "/rename_cfg\0"), -1);
+    truncate_test("/rename_trunc\0", 5000);
+
+    // files and directories move within and across directories in tmpfs
+    assert_eq!(mkdir("/tmp/rename_a\0"), 0);
+    assert_eq!(mkdir("/tmp/rename_b\0"), 0);
+    assert_eq!(write_file("/tmp/rename_a/file\0", b"moved"), 5);
+    assert_eq!(rename("/tmp/rename_a/file\0", "/tmp/rename_b/moved\0"), 0);
+    assert_eq!(open("/tmp/rename_a/file\0", OpenFlags::RDONLY), -1);
+    assert_eq!(rename("/tmp/rename_b\0", "/tmp/rename_a/dir\0"), 0);
+    assert_eq!(read_file("/tmp/rename_a/dir/moved\0", &mut buf), 5);
+    assert_eq!(&buf[..5], b"moved");
+    truncate_test("/tmp/rename_a/trunc\0", 5000);
+
+    // a directory is not moved inside itself or replaced, and nothing
+    // leaves its filesystem or moves a mount point
+    assert_eq!(rename("/tmp/rename_a\0", "/tmp/rename_a/dir/sub\0"), -1);
+    assert_eq!(symlink("rename_a/dir\0", "/tmp/rename_link\0"), 0);
+    assert_eq!(rename("/tmp/rename_a\0", "/tmp/rename_link/sub\0"), -1);
+    assert_eq!(unlink("/tmp/rename_link\0"), 0);
+    assert_eq!(
+        rename("/tmp/rename_a/dir/moved\0", "/tmp/rename_a/dir\0"),
+        -1
+    );
+    assert_eq!(rename("/tmp/rename_a/dir/moved\0", "/rename_moved\0"), -1);
+    assert_eq!(rename("/tmp\0", "/tmp2\0"), -1);
+    assert_eq!(read_file("/tmp/rename_a/dir/moved\0", &mut buf), 5);
+
+    assert_eq!(unlink("/tmp/rename_a/dir/moved\0"), 0);
+    assert_eq!(unlink("/tmp/rename_a/trunc\0"), 0);
+    assert_eq!(rmdir("/tmp/rename_a/dir\0"), 0);
+    assert_eq!(rmdir("/tmp/rename_a\0"), 0);
+    println!("rename test passed!");
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use user_lib::{exit, sleep_blocking, thread_create, waittid};
+use user_lib::{rwlock_create, rwlock_destroy, rwlock_read, rwlock_unlock, rwlock_write};
+
+/// 正确输出：
+/// rwlock test passed!
+
+// main holds a read lock while a writer waits and then another reader comes:
+// with rwlock 0 preferring writers the reader gets in after the writer,
+// with rwlock 1 preferring readers it gets in right away
+
+static WRITTEN: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+
+fn writer(id: usize) -> ! {
+    assert_eq!(rwlock_write(id), 0);
+    WRITTEN[id].store(true, Ordering::SeqCst);
+    assert_eq!(rwlock_unlock(id), 0);
+    exit(0)
+}
+
+fn reader(id: usize) -> ! {
+    sleep_blocking(20);
+    assert_eq!(rwlock_read(id), 0);
+    // preferring writers means the writer has been first
+    assert_eq!(WRITTEN[id].load(Ordering::SeqCst), id == 0);
+    assert_eq!(rwlock_unlock(id), 0);
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(rwlock_create(true), 0);
+    assert_eq!(rwlock_create(false), 1);
+    assert_eq!(rwlock_unlock(0), -1);
+    for id in 0..2 {
+        assert_eq!(rwlock_read(id), 0);
+        let w = thread_create(writer as usize, id);
+        let r = thread_create(reader as usize, id);
+        sleep_blocking(50);
+        assert!(!WRITTEN[id].load(Ordering::SeqCst));
+        assert_eq!(rwlock_destroy(id), -2);
+        assert_eq!(rwlock_unlock(id), 0);
+        assert_eq!(waittid(w as usize), 0);
+        assert_eq!(waittid(r as usize), 0);
+        assert!(WRITTEN[id].load(Ordering::SeqCst));
+    }
+    assert_eq!(rwlock_destroy(0), 0);
+    assert_eq!(rwlock_read(0), -1);
+    println!("rwlock test passed!");
+    0
+}
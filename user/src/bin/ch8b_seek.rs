@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, lseek, open, pipe, pread, pwrite, read, write, OpenFlags};
+use user_lib::{SEEK_CUR, SEEK_END, SEEK_SET};
+
+/// 正确输出：
+/// seek test passed!
+
+const FNAME: &str = "/tmp/seek0\0";
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open(
+        FNAME,
+        OpenFlags::CREATE | OpenFlags::RDWR | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"hello world"), 11);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 11);
+    assert_eq!(lseek(fd, 6, SEEK_SET), 6);
+    let mut buf = [0u8; 32];
+    assert_eq!(read(fd, &mut buf[..5]), 5);
+    assert_eq!(&buf[..5], b"world");
+    assert_eq!(lseek(fd, -5, SEEK_END), 6);
+    assert_eq!(lseek(fd, -7, SEEK_CUR), -1);
+    assert_eq!(lseek(fd, 0, 3), -1);
+    // positional writes and reads leave the offset alone
+    assert_eq!(pwrite(fd, b"WORLD", 6), 5);
+    assert_eq!(pread(fd, &mut buf[..11], 0), 11);
+    assert_eq!(&buf[..11], b"hello WORLD");
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 6);
+    // a gap left by seeking past the end reads as zeros
+    assert_eq!(lseek(fd, 20, SEEK_SET), 20);
+    assert_eq!(write(fd, b"!"), 1);
+    assert_eq!(pread(fd, &mut buf, 11), 10);
+    assert!(buf[..9].iter().all(|byte| *byte == 0));
+    assert_eq!(buf[9], b'!');
+    close(fd);
+
+    // create neither truncates nor opens an existing file with excl
+    assert_eq!(
+        open(
+            FNAME,
+            OpenFlags::CREATE | OpenFlags::EXCL | OpenFlags::WRONLY
+        ),
+        -1
+    );
+    let fd = open(
+        FNAME,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::APPEND,
+    );
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(write(fd, b"tail"), 4);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 25);
+    assert_eq!(pread(fd, &mut buf, 0), -1);
+    close(fd);
+    let fd = open(FNAME, OpenFlags::RDONLY) as usize;
+    assert_eq!(read(fd, &mut buf), 25);
+    assert_eq!(&buf[..5], b"hello");
+    assert_eq!(&buf[21..25], b"tail");
+    close(fd);
+
+    // no offset goes past the largest file, of tmpfs or easy-fs
+    for path in [FNAME, "/seek1\0"] {
+        let fd = open(path, OpenFlags::CREATE | OpenFlags::RDWR);
+        assert!(fd > 0);
+        let fd = fd as usize;
+        assert_eq!(lseek(fd, 1 << 33, SEEK_SET), -1);
+        assert_eq!(pwrite(fd, b"x", 1 << 33), -1);
+        assert_eq!(pread(fd, &mut buf, 1 << 33), 0);
+        close(fd);
+    }
+
+    // pipes cannot seek
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+    assert_eq!(lseek(fds[0], 0, SEEK_SET), -1);
+    assert_eq!(pwrite(fds[1], b"x", 0), -1);
+    close(fds[0]);
+    close(fds[1]);
+    println!("seek test passed!");
+    0
+}
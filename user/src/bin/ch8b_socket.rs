@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{accept, bind, close, connect, listen, recv, recv_fd, send, send_fd, socket};
+use user_lib::{exit, fork, pipe, read, waitpid, write, SOCK_DGRAM, SOCK_STREAM};
+
+/// 正确输出：
+/// socket test passed!
+
+fn datagrams() {
+    let server = socket(SOCK_DGRAM) as usize;
+    let client = socket(SOCK_DGRAM) as usize;
+    assert_eq!(bind(server, "dgram\0"), 0);
+    let other = socket(SOCK_DGRAM) as usize;
+    assert_eq!(bind(other, "dgram\0"), -1);
+    assert_eq!(connect(client, "nobody\0"), -1);
+    assert_eq!(listen(server, 1), -1);
+    assert_eq!(connect(client, "dgram\0"), 0);
+    // message boundaries are kept
+    assert_eq!(send(client, b"ab"), 2);
+    assert_eq!(send(client, b"cde"), 3);
+    let mut buf = [0u8; 8];
+    assert_eq!(recv(server, &mut buf), 2);
+    assert_eq!(&buf[..2], b"ab");
+    assert_eq!(recv(server, &mut buf[..1]), 1);
+    assert_eq!(buf[0], b'c');
+    // a socket is not passed over itself or to itself
+    assert_eq!(send_fd(client, client), -1);
+    assert_eq!(send_fd(client, server), -1);
+    close(server);
+    close(client);
+    close(other);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    datagrams();
+    let listener = socket(SOCK_STREAM) as usize;
+    assert_eq!(listen(listener, 1), -1);
+    assert_eq!(bind(listener, "echo\0"), 0);
+    assert_eq!(listen(listener, 1), 0);
+    let pid = fork();
+    if pid == 0 {
+        let conn = socket(SOCK_STREAM) as usize;
+        assert_eq!(connect(conn, "echo\0"), 0);
+        assert_eq!(send(conn, b"hello"), 5);
+        let mut buf = [0u8; 16];
+        assert_eq!(recv(conn, &mut buf), 5);
+        assert_eq!(&buf[..5], b"hello");
+        let fd = recv_fd(conn);
+        assert!(fd >= 0);
+        assert_eq!(read(fd as usize, &mut buf[..4]), 4);
+        assert_eq!(&buf[..4], b"pipe");
+        // the peer is gone
+        assert_eq!(recv(conn, &mut buf), 0);
+        exit(0);
+    }
+    let conn = accept(listener);
+    assert!(conn >= 0);
+    let conn = conn as usize;
+    let mut buf = [0u8; 16];
+    assert_eq!(recv(conn, &mut buf), 5);
+    assert_eq!(send(conn, &buf[..5]), 5);
+    assert_eq!(send_fd(conn, conn), -1);
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(write(pipe_fd[1], b"pipe"), 4);
+    assert_eq!(send_fd(conn, pipe_fd[0]), 0);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    close(conn);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    close(listener);
+    println!("socket test passed!");
+    0
+}
@@ -0,0 +1,135 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    chdir, close, exec, exit, fork, getdents, lstat, mkdir, open, read_file, readlink, rmdir, stat,
+    symlink, unlink, waitpid, write, Dirents, OpenFlags, Stat, StatMode, DT_LNK,
+};
+
+/// 正确输出：
+/// Hello, world from user mode program!
+/// symlink test passed!
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // on easy-fs, the link is left over from an earlier run or made now
+    let fd = open(
+        "/symlink_target0\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"target"), 6);
+    close(fd as usize);
+    symlink("symlink_target0\0", "/symlink0\0");
+    assert_eq!(symlink("elsewhere\0", "/symlink0\0"), -1);
+    let mut buf = [0u8; 32];
+    let len = readlink("/symlink0\0", &mut buf);
+    assert_eq!(&buf[..len as usize], b"symlink_target0");
+    // the target is cut to fit
+    assert_eq!(readlink("/symlink0\0", &mut buf[..4]), 4);
+    assert_eq!(&buf[..4], b"syml");
+    assert_eq!(readlink("/symlink_target0\0", &mut buf), -1);
+    assert_eq!(read_file("/symlink0\0", &mut buf), 6);
+    assert_eq!(&buf[..6], b"target");
+
+    // stat follows the link, lstat does not
+    let link = Stat::new();
+    let target = Stat::new();
+    let followed = Stat::new();
+    assert_eq!(lstat("/symlink0\0", &link), 0);
+    assert_eq!(stat("/symlink_target0\0", &target), 0);
+    assert_eq!(stat("/symlink0\0", &followed), 0);
+    assert_eq!(link.mode, StatMode::LNK);
+    assert_eq!(target.mode, StatMode::FILE);
+    assert_eq!(followed.mode, StatMode::FILE);
+    assert_eq!(followed.ino, target.ino);
+    assert_ne!(link.ino, target.ino);
+    // and so does open with NOFOLLOW
+    assert_eq!(
+        open("/symlink0\0", OpenFlags::RDONLY | OpenFlags::NOFOLLOW),
+        -1
+    );
+    let fd = open(
+        "/symlink_target0\0",
+        OpenFlags::RDONLY | OpenFlags::NOFOLLOW,
+    );
+    assert!(fd > 0);
+    close(fd as usize);
+
+    // links are listed as such
+    let dir = open("/\0", OpenFlags::RDONLY);
+    assert!(dir > 0);
+    let mut entries = [0u8; 256];
+    let mut found = false;
+    loop {
+        let len = getdents(dir as usize, &mut entries);
+        if len <= 0 {
+            break;
+        }
+        for dirent in Dirents::new(&entries, len as usize) {
+            if dirent.name == "symlink0" {
+                assert_eq!(dirent.d_type, DT_LNK);
+                found = true;
+            }
+        }
+    }
+    assert!(found);
+    close(dir as usize);
+
+    // a link to a directory leads inside it, relative to the link
+    assert_eq!(mkdir("/tmp/symlink_dir\0"), 0);
+    let fd = open(
+        "/tmp/symlink_dir/file\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY,
+    );
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"inside"), 6);
+    close(fd as usize);
+    assert_eq!(symlink("symlink_dir\0", "/tmp/dir_link\0"), 0);
+    assert_eq!(read_file("/tmp/dir_link/file\0", &mut buf), 6);
+    assert_eq!(&buf[..6], b"inside");
+    assert_eq!(chdir("/tmp/dir_link\0"), 0);
+    assert_eq!(read_file("file\0", &mut buf), 6);
+    assert_eq!(chdir("/\0"), 0);
+
+    // a binary is run through a link
+    assert_eq!(symlink("/ch2b_hello_world\0", "/tmp/hello\0"), 0);
+    let pid = fork();
+    if pid == 0 {
+        exec("/tmp/hello\0", &[core::ptr::null::<u8>()]);
+        exit(-1);
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // links pointing at each other, or at nothing, lead nowhere
+    assert_eq!(symlink("loop1\0", "/tmp/loop0\0"), 0);
+    assert_eq!(symlink("loop0\0", "/tmp/loop1\0"), 0);
+    assert_eq!(open("/tmp/loop0\0", OpenFlags::RDONLY), -1);
+    assert_eq!(stat("/tmp/loop0\0", &followed), -1);
+    assert_eq!(lstat("/tmp/loop0\0", &link), 0);
+    assert_eq!(symlink("missing\0", "/tmp/dangling\0"), 0);
+    assert_eq!(stat("/tmp/dangling\0", &followed), -1);
+    assert_eq!(lstat("/tmp/dangling\0", &link), 0);
+
+    // unlink removes a link, not what it points to
+    for path in [
+        "/tmp/dir_link\0",
+        "/tmp/hello\0",
+        "/tmp/loop0\0",
+        "/tmp/loop1\0",
+        "/tmp/dangling\0",
+    ] {
+        assert_eq!(unlink(path), 0);
+    }
+    assert_eq!(lstat("/tmp/dir_link\0", &link), -1);
+    assert_eq!(read_file("/tmp/symlink_dir/file\0", &mut buf), 6);
+    assert_eq!(unlink("/tmp/symlink_dir/file\0"), 0);
+    assert_eq!(rmdir("/tmp/symlink_dir\0"), 0);
+    println!("symlink test passed!");
+    0
+}
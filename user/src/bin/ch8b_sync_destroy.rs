@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    condvar_create, condvar_destroy, condvar_signal, condvar_wait, mutex_blocking_create,
+    mutex_create, mutex_destroy, mutex_lock, mutex_trylock, mutex_unlock, semaphore_create,
+    semaphore_destroy, semaphore_down, semaphore_trydown, semaphore_up,
+};
+use user_lib::{exit, sleep_blocking, thread_create, waittid};
+
+/// 正确输出：
+/// mutex destroy OK!
+/// semaphore destroy OK!
+/// condvar destroy OK!
+/// sync destroy test passed!
+
+fn sem_waiter() -> ! {
+    semaphore_down(0);
+    exit(0)
+}
+
+fn condvar_waiter() -> ! {
+    mutex_lock(0);
+    condvar_wait(0, 0);
+    mutex_unlock(0);
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mutex_create(), 0);
+    assert_eq!(mutex_blocking_create(), 1);
+    assert_eq!(mutex_destroy(0), 0);
+    assert_eq!(mutex_destroy(0), -1);
+    assert_eq!(mutex_lock(0), -1);
+    assert_eq!(mutex_trylock(100), -1);
+    // the freed id is handed out again
+    assert_eq!(mutex_blocking_create(), 0);
+    mutex_lock(1);
+    assert_eq!(mutex_destroy(1), -2);
+    mutex_unlock(1);
+    assert_eq!(mutex_destroy(1), 0);
+    println!("mutex destroy OK!");
+
+    assert_eq!(semaphore_create(0), 0);
+    let tid = thread_create(sem_waiter as usize, 0);
+    sleep_blocking(20);
+    assert_eq!(semaphore_destroy(0), -2);
+    semaphore_up(0);
+    assert_eq!(waittid(tid as usize), 0);
+    assert_eq!(semaphore_destroy(0), 0);
+    assert_eq!(semaphore_trydown(0), -1);
+    println!("semaphore destroy OK!");
+
+    assert_eq!(condvar_create(), 0);
+    let tid = thread_create(condvar_waiter as usize, 0);
+    sleep_blocking(20);
+    assert_eq!(condvar_destroy(0), -2);
+    mutex_lock(0);
+    condvar_signal(0);
+    mutex_unlock(0);
+    assert_eq!(waittid(tid as usize), 0);
+    assert_eq!(condvar_destroy(0), 0);
+    assert_eq!(condvar_destroy(0), -1);
+    println!("condvar destroy OK!");
+    println!("sync destroy test passed!");
+    0
+}
@@ -0,0 +1,90 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    condvar_create, condvar_signal, condvar_timedwait, mutex_blocking_create, mutex_create,
+    mutex_lock, mutex_timedlock, mutex_trylock, mutex_unlock, semaphore_create,
+    semaphore_timeddown, semaphore_trydown, semaphore_up,
+};
+use user_lib::{exit, get_time, sleep_blocking, thread_create, waittid};
+
+/// 正确输出：
+/// mutex timeout OK!
+/// semaphore timeout OK!
+/// condvar timeout OK!
+/// sync timeout test passed!
+
+const BLOCKING_MUTEX: usize = 0;
+const SPIN_MUTEX: usize = 1;
+const SEM: usize = 0;
+const CONDVAR: usize = 0;
+
+fn contend() -> ! {
+    for mutex_id in [BLOCKING_MUTEX, SPIN_MUTEX] {
+        assert_eq!(mutex_trylock(mutex_id), -2);
+        let start = get_time();
+        assert_eq!(mutex_timedlock(mutex_id, 50), -3);
+        assert!(get_time() - start >= 50);
+    }
+    // main unlocks both mutexes after this thread gives up
+    assert_eq!(mutex_timedlock(BLOCKING_MUTEX, 5000), 0);
+    assert_eq!(mutex_timedlock(SPIN_MUTEX, 5000), 0);
+    mutex_unlock(SPIN_MUTEX);
+    mutex_unlock(BLOCKING_MUTEX);
+    exit(0)
+}
+
+fn up_later() -> ! {
+    sleep_blocking(20);
+    semaphore_up(SEM);
+    exit(0)
+}
+
+fn signal_later() -> ! {
+    sleep_blocking(20);
+    mutex_lock(BLOCKING_MUTEX);
+    condvar_signal(CONDVAR);
+    mutex_unlock(BLOCKING_MUTEX);
+    exit(0)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mutex_blocking_create(), BLOCKING_MUTEX as isize);
+    assert_eq!(mutex_create(), SPIN_MUTEX as isize);
+    assert_eq!(semaphore_create(0), SEM as isize);
+    assert_eq!(condvar_create(), CONDVAR as isize);
+
+    mutex_lock(BLOCKING_MUTEX);
+    mutex_lock(SPIN_MUTEX);
+    let tid = thread_create(contend as usize, 0);
+    sleep_blocking(200);
+    mutex_unlock(SPIN_MUTEX);
+    mutex_unlock(BLOCKING_MUTEX);
+    assert_eq!(waittid(tid as usize), 0);
+    println!("mutex timeout OK!");
+
+    assert_eq!(semaphore_trydown(SEM), -2);
+    assert_eq!(semaphore_timeddown(SEM, 50), -3);
+    let tid = thread_create(up_later as usize, 0);
+    assert_eq!(semaphore_timeddown(SEM, 300), 0);
+    // the timer of the wait above must be gone and not cut this sleep short
+    let start = get_time();
+    sleep_blocking(300);
+    assert!(get_time() - start >= 300);
+    assert_eq!(waittid(tid as usize), 0);
+    println!("semaphore timeout OK!");
+
+    mutex_lock(BLOCKING_MUTEX);
+    assert_eq!(condvar_timedwait(CONDVAR, BLOCKING_MUTEX, 50), -3);
+    let tid = thread_create(signal_later as usize, 0);
+    assert_eq!(condvar_timedwait(CONDVAR, BLOCKING_MUTEX, 5000), 0);
+    mutex_unlock(BLOCKING_MUTEX);
+    assert_eq!(waittid(tid as usize), 0);
+    println!("condvar timeout OK!");
+    println!("sync timeout test passed!");
+    0
+}
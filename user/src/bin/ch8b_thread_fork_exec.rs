@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, exit, fork, thread_create, waitpid, waittid, yield_};
+
+/// 正确输出：
+/// child forked from a thread
+/// Hello, world from user mode program!
+/// thread fork/exec test passed!
+
+fn fork_in_thread() -> ! {
+    let pid = fork();
+    if pid == 0 {
+        // only the forking thread exists in the child
+        println!("child forked from a thread");
+        exit(7);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    exit(0)
+}
+
+fn spin_forever() -> ! {
+    loop {
+        yield_();
+    }
+}
+
+fn exec_in_thread() -> ! {
+    exec("ch2b_hello_world\0", &[core::ptr::null::<u8>()]);
+    panic!("unreachable!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let tid = thread_create(fork_in_thread as usize, 0);
+    assert_eq!(waittid(tid as usize), 0);
+    let pid = fork();
+    if pid == 0 {
+        // exec terminates the spinning thread and the main thread
+        thread_create(spin_forever as usize, 0);
+        thread_create(exec_in_thread as usize, 0);
+        spin_forever();
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("thread fork/exec test passed!");
+    0
+}
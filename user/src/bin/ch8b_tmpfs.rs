@@ -0,0 +1,98 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, getdents, lseek, mkdir, mount, open, pwrite, read, rmdir, umount, unlink, write,
+};
+use user_lib::{Dirents, OpenFlags, SEEK_END, SEEK_SET};
+
+/// 正确输出：
+/// tmpfs test passed!
+
+/// Number of entries in the directory at `path`
+fn count_entries(path: &str) -> usize {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buf = [0u8; 256];
+    let mut count = 0;
+    loop {
+        let len = getdents(fd as usize, &mut buf);
+        assert!(len >= 0);
+        if len == 0 {
+            break;
+        }
+        count += Dirents::new(&buf, len as usize).count();
+    }
+    close(fd as usize);
+    count
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // /tmp is empty at boot, but may hold files of other tests
+    let before = count_entries("/tmp\0");
+    let fd = open(
+        "/tmp/scratch\0",
+        OpenFlags::CREATE | OpenFlags::RDWR | OpenFlags::EXCL,
+    );
+    assert!(fd > 0);
+    let fd = fd as usize;
+    // data spanning pages, after a gap that reads as zeros
+    let data = [0x5au8; 5000];
+    assert_eq!(lseek(fd, 3000, SEEK_SET), 3000);
+    assert_eq!(write(fd, &data), 5000);
+    let mut buf = [1u8; 8000];
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(read(fd, &mut buf), 8000);
+    assert!(buf[..3000].iter().all(|byte| *byte == 0));
+    assert!(buf[3000..].iter().all(|byte| *byte == 0x5a));
+    assert_eq!(count_entries("/tmp\0"), before + 1);
+    // a write far past the end takes no memory and leaves the file as it is
+    assert_eq!(lseek(fd, 1 << 40, SEEK_SET), -1);
+    assert_eq!(pwrite(fd, b"x", 1 << 40), -1);
+    assert_eq!(lseek(fd, 0, SEEK_END), 8000);
+
+    // an unlinked file stays readable while it is open
+    assert_eq!(unlink("/tmp/scratch\0"), 0);
+    assert_eq!(unlink("/tmp/scratch\0"), -1);
+    assert_eq!(open("/tmp/scratch\0", OpenFlags::RDONLY), -1);
+    assert_eq!(lseek(fd, 7990, SEEK_SET), 7990);
+    assert_eq!(read(fd, &mut buf), 10);
+    close(fd);
+    assert_eq!(count_entries("/tmp\0"), before);
+
+    // directories go with rmdir once they are empty
+    assert_eq!(mkdir("/tmp/dir\0"), 0);
+    let fd = open("/tmp/dir/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(unlink("/tmp/dir\0"), -1);
+    assert_eq!(rmdir("/tmp/dir\0"), -1);
+    assert_eq!(rmdir("/tmp/dir/file\0"), -1);
+    assert_eq!(unlink("/tmp/dir/file\0"), 0);
+    assert_eq!(rmdir("/tmp/dir\0"), 0);
+
+    // another tmpfs inside starts empty and takes its data with it
+    assert_eq!(mkdir("/tmp/inner\0"), 0);
+    assert_eq!(mount("none\0", "/tmp/inner\0", "tmpfs\0"), 0);
+    assert_eq!(count_entries("/tmp/inner\0"), 0);
+    let fd = open("/tmp/inner/file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(rmdir("/tmp/inner\0"), -1);
+    assert_eq!(umount("/tmp\0"), -2);
+    assert_eq!(umount("/tmp/inner\0"), 0);
+    assert_eq!(open("/tmp/inner/file\0", OpenFlags::RDONLY), -1);
+    assert_eq!(rmdir("/tmp/inner\0"), 0);
+
+    // easy-fs cannot remove files
+    let fd = open("tmpfs0\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(unlink("tmpfs0\0"), -1);
+    println!("tmpfs test passed!");
+    0
+}
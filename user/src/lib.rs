@@ -6,6 +6,7 @@
 #[macro_use]
 pub mod console;
 mod lang_items;
+mod sync;
 mod syscall;
 
 extern crate alloc;
@@ -15,7 +16,10 @@ extern crate bitflags;
 
 use alloc::vec::Vec;
 use buddy_system_allocator::LockedHeap;
+use core::convert::TryInto;
+use core::sync::atomic::AtomicU32;
 pub use console::{flush, STDIN, STDOUT};
+pub use sync::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 pub use syscall::*;
 
 const USER_HEAP_SIZE: usize = 16384;
@@ -80,8 +84,18 @@ bitflags! {
         const RDONLY = 0;
         const WRONLY = 1 << 0;
         const RDWR = 1 << 1;
+        /// with `CREATE`, fail if the file exists
+        const EXCL = 1 << 7;
         const CREATE = 1 << 9;
         const TRUNC = 1 << 10;
+        /// reads and writes return -2 instead of waiting
+        const NONBLOCK = 1 << 11;
+        /// every write goes to the end of the file
+        const APPEND = 1 << 12;
+        /// fail if the path is a symbolic link
+        const NOFOLLOW = 1 << 17;
+        /// the fd is closed by exec
+        const CLOEXEC = 1 << 19;
     }
 }
 
@@ -131,6 +145,51 @@ impl TaskInfo {
     }
 }
 
+/// Most threads and resources a [`DeadlockReport`] can list
+pub const DEADLOCK_REPORT_LEN: usize = 16;
+/// Resource kinds in [`DeadlockReport::resources`]
+pub const RESOURCE_MUTEX: usize = 0;
+pub const RESOURCE_SEMAPHORE: usize = 1;
+pub const RESOURCE_RWLOCK: usize = 2;
+pub const RESOURCE_BARRIER: usize = 3;
+
+/// Threads and resources of the unsafe state found by the last request
+/// refused with -0xDEAD
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeadlockReport {
+    pub tid_count: usize,
+    pub tids: [usize; DEADLOCK_REPORT_LEN],
+    pub resource_count: usize,
+    /// `[kind, id]` of each resource
+    pub resources: [[usize; 2]; DEADLOCK_REPORT_LEN],
+}
+
+impl DeadlockReport {
+    pub fn new() -> Self {
+        DeadlockReport {
+            tid_count: 0,
+            tids: [0; DEADLOCK_REPORT_LEN],
+            resource_count: 0,
+            resources: [[0; 2]; DEADLOCK_REPORT_LEN],
+        }
+    }
+
+    pub fn tids(&self) -> &[usize] {
+        &self.tids[..self.tid_count]
+    }
+
+    pub fn resources(&self) -> &[[usize; 2]] {
+        &self.resources[..self.resource_count]
+    }
+}
+
+impl Default for DeadlockReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct Stat {
@@ -171,6 +230,69 @@ bitflags! {
         const DIR   = 0o040000;
         /// ordinary regular file
         const FILE  = 0o100000;
+        /// named pipe
+        const FIFO  = 0o010000;
+        /// character device
+        const CHR   = 0o020000;
+        /// symbolic link
+        const LNK   = 0o120000;
+    }
+}
+
+bitflags! {
+    /// Events to poll for, with the bits of `struct pollfd`
+    pub struct PollEvents: u16 {
+        const IN    = 0x001;
+        const OUT   = 0x004;
+        /// reported without being asked for
+        const ERR   = 0x008;
+        /// reported without being asked for
+        const HUP   = 0x010;
+        /// the fd is not open
+        const NVAL  = 0x020;
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PollFd {
+    /// negative fds are skipped
+    pub fd: i32,
+    pub events: PollEvents,
+    pub revents: PollEvents,
+}
+
+impl PollFd {
+    pub fn new(fd: usize, events: PollEvents) -> Self {
+        Self {
+            fd: fd as i32,
+            events,
+            revents: PollEvents::empty(),
+        }
+    }
+}
+
+pub const FD_SETSIZE: usize = 128;
+
+/// A set of fds for [`select`]
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FdSet {
+    bits: [u64; FD_SETSIZE / 64],
+}
+
+impl FdSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn insert(&mut self, fd: usize) {
+        self.bits[fd / 64] |= 1 << (fd % 64);
+    }
+    pub fn remove(&mut self, fd: usize) {
+        self.bits[fd / 64] &= !(1 << (fd % 64));
+    }
+    pub fn contains(&self, fd: usize) -> bool {
+        self.bits[fd / 64] & (1 << (fd % 64)) != 0
     }
 }
 
@@ -180,6 +302,102 @@ pub fn open(path: &str, flags: OpenFlags) -> isize {
     sys_openat(AT_FDCWD as usize, path, flags.bits, OpenFlags::RDWR.bits)
 }
 
+/// Create a named pipe, return -1 if the path is taken
+///
+/// Opening it waits for the other end unless [`OpenFlags::NONBLOCK`] is
+/// given, and it has to be opened either for reading or for writing.
+pub fn mkfifo(path: &str) -> isize {
+    const S_IFIFO: u32 = 0o010000;
+    sys_mknodat(AT_FDCWD as usize, path, S_IFIFO)
+}
+
+/// Create a directory, return -1 if the path is taken
+pub fn mkdir(path: &str) -> isize {
+    sys_mkdirat(AT_FDCWD as usize, path)
+}
+/// Mount a filesystem of type `fs_type` from `source` on the directory
+/// `target`, like `mount("/dev/vdb\0", "/mnt\0", "easyfs\0")`
+pub fn mount(source: &str, target: &str, fs_type: &str) -> isize {
+    sys_mount(source, target, fs_type, 0)
+}
+/// Unmount the filesystem at `target`, return -2 while it is in use
+pub fn umount(target: &str) -> isize {
+    sys_umount2(target, 0)
+}
+/// Change the working directory, return -1 if `path` is not a directory
+pub fn chdir(path: &str) -> isize {
+    sys_chdir(path)
+}
+/// Copy the working directory with a terminating NUL into `buf`, return its
+/// length with the NUL or -1 if it does not fit
+pub fn getcwd(buf: &mut [u8]) -> isize {
+    sys_getcwd(buf)
+}
+/// Fill `buf` with entries of the directory open at `fd`, return the number
+/// of bytes filled and 0 at the end, parse them with [`Dirents`]
+pub fn getdents(fd: usize, buf: &mut [u8]) -> isize {
+    sys_getdents64(fd, buf)
+}
+
+pub const DT_FIFO: u8 = 1;
+pub const DT_CHR: u8 = 2;
+pub const DT_DIR: u8 = 4;
+pub const DT_REG: u8 = 8;
+pub const DT_LNK: u8 = 10;
+
+/// A directory entry filled by [`getdents`]
+pub struct Dirent<'a> {
+    pub ino: u64,
+    pub d_type: u8,
+    pub name: &'a str,
+}
+
+/// Iterator over the entries [`getdents`] filled into a buffer
+pub struct Dirents<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Dirents<'a> {
+    /// Iterate the first `len` bytes of `buf`, `len` as returned by [`getdents`]
+    pub fn new(buf: &'a [u8], len: usize) -> Self {
+        Self { buf: &buf[..len] }
+    }
+}
+
+impl<'a> Iterator for Dirents<'a> {
+    type Item = Dirent<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let ino = u64::from_le_bytes(self.buf[0..8].try_into().unwrap());
+        let reclen = u16::from_le_bytes(self.buf[16..18].try_into().unwrap()) as usize;
+        let d_type = self.buf[18];
+        let name = &self.buf[19..reclen];
+        let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        let name = core::str::from_utf8(&name[..name_len]).unwrap();
+        self.buf = &self.buf[reclen..];
+        Some(Dirent { ino, d_type, name })
+    }
+}
+
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+/// Move the offset of `fd` relative to `whence`, return the new offset
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    sys_lseek(fd, offset, whence)
+}
+/// Read from `offset` without moving the offset of `fd`
+pub fn pread(fd: usize, buf: &mut [u8], offset: usize) -> isize {
+    sys_pread(fd, buf, offset)
+}
+/// Write at `offset` without moving the offset of `fd`
+pub fn pwrite(fd: usize, buf: &[u8], offset: usize) -> isize {
+    sys_pwrite(fd, buf, offset)
+}
+
 pub fn close(fd: usize) -> isize {
     if fd == STDOUT {
         console::flush();
@@ -195,6 +413,33 @@ pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
 
+/// Create the file at `path`, or empty it, and write `data` to it, return
+/// the length written or -1 if it cannot be opened
+pub fn write_file(path: &str, data: &[u8]) -> isize {
+    let fd = open(
+        path,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    if fd < 0 {
+        return fd;
+    }
+    let len = write(fd as usize, data);
+    close(fd as usize);
+    len
+}
+
+/// Read the file at `path` into `buf`, return the length read or -1 if it
+/// cannot be opened
+pub fn read_file(path: &str, buf: &mut [u8]) -> isize {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return fd;
+    }
+    let len = read(fd as usize, buf);
+    close(fd as usize);
+    len
+}
+
 pub fn link(old_path: &str, new_path: &str) -> isize {
     sys_linkat(AT_FDCWD as usize, old_path, AT_FDCWD as usize, new_path, 0)
 }
@@ -203,14 +448,72 @@ pub fn unlink(path: &str) -> isize {
     sys_unlinkat(AT_FDCWD as usize, path, 0)
 }
 
+const AT_REMOVEDIR: usize = 0x200;
+
+/// Remove an empty directory
+pub fn rmdir(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD as usize, path, AT_REMOVEDIR)
+}
+
+/// Move the file or directory at `old_path` to `new_path`, replacing a file
+/// there
+pub fn rename(old_path: &str, new_path: &str) -> isize {
+    sys_renameat(AT_FDCWD as usize, old_path, AT_FDCWD as usize, new_path)
+}
+
+/// Change the size of the file open at `fd` to `len`, filling the space
+/// added with zeros
+pub fn ftruncate(fd: usize, len: usize) -> isize {
+    sys_ftruncate(fd, len)
+}
+
+/// Create a symbolic link at `path` pointing to `target`
+pub fn symlink(target: &str, path: &str) -> isize {
+    sys_symlinkat(target, AT_FDCWD as usize, path)
+}
+
+/// Copy the target of the symbolic link at `path` to `buf` without a NUL,
+/// return its length
+pub fn readlink(path: &str, buf: &mut [u8]) -> isize {
+    sys_readlinkat(AT_FDCWD as usize, path, buf)
+}
+
+const AT_SYMLINK_NOFOLLOW: usize = 0x100;
+
+/// Get the stat of what `path` leads to, following symbolic links
+pub fn stat(path: &str, st: &Stat) -> isize {
+    sys_fstatat(AT_FDCWD as usize, path, st, 0)
+}
+
+/// Get the stat of `path`, of a symbolic link there itself
+pub fn lstat(path: &str, st: &Stat) -> isize {
+    sys_fstatat(AT_FDCWD as usize, path, st, AT_SYMLINK_NOFOLLOW)
+}
+
 pub fn fstat(fd: usize, st: &Stat) -> isize {
     sys_fstat(fd, st)
 }
 
+/// Most messages a mailbox holds
+pub const MAILBOX_CAPACITY: usize = 16;
+/// Longer messages are cut off at this length
+pub const MAIL_MAX_LEN: usize = 256;
+
+/// Take the oldest message, return -1 if there is none
+///
+/// The part of the message that does not fit into `buf` is dropped. An empty
+/// `buf` only checks for mail and returns 0 if there is some.
 pub fn mail_read(buf: &mut [u8]) -> isize {
-    sys_mail_read(buf)
+    sys_mail_read(buf, 0)
 }
 
+/// Like [`mail_read`] but wait for a message if there is none
+pub fn mail_read_blocking(buf: &mut [u8]) -> isize {
+    sys_mail_read(buf, 1)
+}
+
+/// Send `buf` to process `pid`, return -1 if there is no such process or its
+/// mailbox is full
 pub fn mail_write(pid: usize, buf: &[u8]) -> isize {
     sys_mail_write(pid, buf)
 }
@@ -296,11 +599,116 @@ pub fn spawn(path: &str) -> isize {
     sys_spawn(path)
 }
 
+/// Wait at most `timeout_ms` for events on `fds`, forever if it is negative
+///
+/// Return the number of fds with `revents` set, 0 on timeout.
+pub fn poll(fds: &mut [PollFd], timeout_ms: isize) -> isize {
+    sys_ppoll(fds, timeout_ms)
+}
+/// Wait at most `timeout_ms` until an fd below `nfds` in `readfds` can be
+/// read or one in `writefds` can be written, forever if it is negative
+///
+/// The sets are replaced by the fds that are ready. Return how many there
+/// are, 0 on timeout.
+pub fn select(
+    nfds: usize,
+    readfds: Option<&mut FdSet>,
+    writefds: Option<&mut FdSet>,
+    timeout_ms: isize,
+) -> isize {
+    sys_pselect(nfds.min(FD_SETSIZE), readfds, writefds, timeout_ms)
+}
+
+pub const AF_UNIX: usize = 1;
+pub const SOCK_STREAM: usize = 1;
+pub const SOCK_DGRAM: usize = 2;
+
+/// Create a local socket of kind [`SOCK_STREAM`] or [`SOCK_DGRAM`]
+pub fn socket(kind: usize) -> isize {
+    sys_socket(AF_UNIX, kind, 0)
+}
+/// Bind a socket to `name`, which has to end with `\0`
+pub fn bind(fd: usize, name: &str) -> isize {
+    sys_bind(fd, name)
+}
+pub fn listen(fd: usize, backlog: usize) -> isize {
+    sys_listen(fd, backlog)
+}
+/// Wait for a connection, return the fd of the connected socket
+pub fn accept(fd: usize) -> isize {
+    sys_accept(fd)
+}
+/// Connect to the socket bound to `name`, which has to end with `\0`
+pub fn connect(fd: usize, name: &str) -> isize {
+    sys_connect(fd, name)
+}
+pub fn send(fd: usize, buf: &[u8]) -> isize {
+    sys_sendto(fd, buf)
+}
+/// Return 0 once the peer of a stream socket is closed
+pub fn recv(fd: usize, buf: &mut [u8]) -> isize {
+    sys_recvfrom(fd, buf)
+}
+/// Pass the open file `fd` to the peer of `sockfd`
+pub fn send_fd(sockfd: usize, fd: usize) -> isize {
+    sys_send_fd(sockfd, fd)
+}
+/// Wait for a file passed by the peer of `sockfd`, return its new fd
+pub fn recv_fd(sockfd: usize) -> isize {
+    sys_recv_fd(sockfd)
+}
 pub fn dup(fd: usize) -> isize {
     sys_dup(fd)
 }
+/// Number of fds a process can have open
+pub const MAX_FD: usize = 128;
+/// Returned when a process already has [`MAX_FD`] fds open
+pub const EMFILE: isize = -4;
+/// Duplicate `old_fd` into `new_fd`, closing the file open there first
+///
+/// Nothing happens if they are the same open fd.
+pub fn dup2(old_fd: usize, new_fd: usize) -> isize {
+    if old_fd == new_fd {
+        return if fcntl(old_fd, F_GETFD, 0) < 0 { -1 } else { new_fd as isize };
+    }
+    sys_dup3(old_fd, new_fd, 0)
+}
+/// Like [`dup2`], but [`OpenFlags::CLOEXEC`] may be given in `flags` and
+/// `old_fd` must differ from `new_fd`
+pub fn dup3(old_fd: usize, new_fd: usize, flags: OpenFlags) -> isize {
+    sys_dup3(old_fd, new_fd, flags.bits)
+}
 pub fn pipe(pipe_fd: &mut [usize]) -> isize {
-    sys_pipe(pipe_fd)
+    sys_pipe(pipe_fd, 0)
+}
+/// Create a pipe whose ends take [`OpenFlags::NONBLOCK`] and [`OpenFlags::CLOEXEC`] from `flags`
+pub fn pipe2(pipe_fd: &mut [usize], flags: OpenFlags) -> isize {
+    sys_pipe(pipe_fd, flags.bits)
+}
+
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+pub const FD_CLOEXEC: usize = 1;
+pub const F_SETPIPE_SZ: usize = 1031;
+pub const F_GETPIPE_SZ: usize = 1032;
+/// Largest capacity a pipe can be given
+pub const PIPE_MAX_SIZE: usize = 16 * 4096;
+
+/// Get or set `FD_CLOEXEC` with `F_GETFD`/`F_SETFD`, [`OpenFlags::NONBLOCK`]
+/// with `F_GETFL`/`F_SETFL`, or the capacity of a pipe with
+/// `F_GETPIPE_SZ`/`F_SETPIPE_SZ`
+pub fn fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_fcntl(fd, cmd, arg)
+}
+/// Get the capacity of the pipe at `fd`
+pub fn pipe_size(fd: usize) -> isize {
+    sys_fcntl(fd, F_GETPIPE_SZ, 0)
+}
+/// Set the capacity of the pipe at `fd`, return -1 if the data in it does not fit
+pub fn set_pipe_size(fd: usize, size: usize) -> isize {
+    sys_fcntl(fd, F_SETPIPE_SZ, size)
 }
 
 pub fn task_info(info: &TaskInfo) -> isize {
@@ -333,9 +741,21 @@ pub fn mutex_blocking_create() -> isize {
 pub fn mutex_lock(mutex_id: usize) -> isize {
     sys_mutex_lock(mutex_id)
 }
+/// Return -2 if the mutex is held
+pub fn mutex_trylock(mutex_id: usize) -> isize {
+    sys_mutex_trylock(mutex_id)
+}
+/// Return -3 if the mutex is still held after `timeout_ms`
+pub fn mutex_timedlock(mutex_id: usize, timeout_ms: usize) -> isize {
+    sys_mutex_timedlock(mutex_id, timeout_ms)
+}
 pub fn mutex_unlock(mutex_id: usize) {
     sys_mutex_unlock(mutex_id);
 }
+/// Return -1 for an unknown id and -2 if the mutex is held
+pub fn mutex_destroy(mutex_id: usize) -> isize {
+    sys_mutex_destroy(mutex_id)
+}
 pub fn semaphore_create(res_count: usize) -> isize {
     sys_semaphore_create(res_count)
 }
@@ -345,15 +765,101 @@ pub fn semaphore_up(sem_id: usize) {
 pub fn enable_deadlock_detect(enabled: bool) -> isize {
     sys_enable_deadlock_detect(enabled as usize)
 }
+/// Delay unsafe requests instead of refusing them, threads have to declare
+/// their maximum claims with [`deadlock_claim`] first
+pub fn enable_deadlock_avoid(enabled: bool) -> isize {
+    sys_enable_deadlock_detect(if enabled { 2 } else { 0 })
+}
+/// Declare that the current thread holds at most `max` units of a resource,
+/// `kind` being [`RESOURCE_MUTEX`] or [`RESOURCE_SEMAPHORE`]
+pub fn deadlock_claim(kind: usize, id: usize, max: usize) -> isize {
+    sys_deadlock_claim(kind, id, max)
+}
+/// Return -1 if no request has been refused with -0xDEAD yet
+pub fn deadlock_report(report: &mut DeadlockReport) -> isize {
+    sys_deadlock_report(report)
+}
 pub fn semaphore_down(sem_id: usize) -> isize {
     sys_semaphore_down(sem_id)
 }
+/// Return -2 if no resource is available
+pub fn semaphore_trydown(sem_id: usize) -> isize {
+    sys_semaphore_trydown(sem_id)
+}
+/// Return -3 if no resource became available within `timeout_ms`
+pub fn semaphore_timeddown(sem_id: usize, timeout_ms: usize) -> isize {
+    sys_semaphore_timeddown(sem_id, timeout_ms)
+}
+/// Return -1 for an unknown id and -2 if tasks are blocked on the semaphore
+pub fn semaphore_destroy(sem_id: usize) -> isize {
+    sys_semaphore_destroy(sem_id)
+}
 pub fn condvar_create() -> isize {
     sys_condvar_create(0)
 }
 pub fn condvar_signal(condvar_id: usize) {
     sys_condvar_signal(condvar_id);
 }
+/// Wake up all waiters, return how many there were
+pub fn condvar_broadcast(condvar_id: usize) -> isize {
+    sys_condvar_broadcast(condvar_id)
+}
 pub fn condvar_wait(condvar_id: usize, mutex_id: usize) {
     sys_condvar_wait(condvar_id, mutex_id);
 }
+/// Return -1 for an unknown id and -2 if tasks are waiting on the condvar
+pub fn condvar_destroy(condvar_id: usize) -> isize {
+    sys_condvar_destroy(condvar_id)
+}
+/// Return -3 if not signaled within `timeout_ms`, the mutex is locked again either way
+pub fn condvar_timedwait(condvar_id: usize, mutex_id: usize, timeout_ms: usize) -> isize {
+    sys_condvar_timedwait(condvar_id, mutex_id, timeout_ms)
+}
+/// `writer_preferred` keeps new readers out while a writer is waiting
+pub fn rwlock_create(writer_preferred: bool) -> isize {
+    sys_rwlock_create(writer_preferred)
+}
+pub fn rwlock_read(rwlock_id: usize) -> isize {
+    sys_rwlock_read(rwlock_id)
+}
+pub fn rwlock_write(rwlock_id: usize) -> isize {
+    sys_rwlock_write(rwlock_id)
+}
+/// Return -1 if the current thread holds the rwlock neither for reading nor writing
+pub fn rwlock_unlock(rwlock_id: usize) -> isize {
+    sys_rwlock_unlock(rwlock_id)
+}
+/// Return -1 for an unknown id and -2 if the rwlock is held or waited for
+pub fn rwlock_destroy(rwlock_id: usize) -> isize {
+    sys_rwlock_destroy(rwlock_id)
+}
+pub fn barrier_create(parties: usize) -> isize {
+    sys_barrier_create(parties)
+}
+/// Return 1 in the thread arriving last and 0 in the others
+pub fn barrier_wait(barrier_id: usize) -> isize {
+    sys_barrier_wait(barrier_id)
+}
+/// Return -1 for an unknown id and -2 if tasks are waiting at the barrier
+pub fn barrier_destroy(barrier_id: usize) -> isize {
+    sys_barrier_destroy(barrier_id)
+}
+/// Block while `futex` holds `val`, a non-zero `timeout_ms` bounds the wait
+///
+/// Return 0 when woken up, -2 if `futex` no longer holds `val` and -3 on timeout.
+pub fn futex_wait(futex: &AtomicU32, val: u32, timeout_ms: usize) -> isize {
+    sys_futex_wait(futex as *const _ as usize, val, timeout_ms)
+}
+pub fn futex_wake(futex: &AtomicU32, count: usize) -> isize {
+    sys_futex_wake(futex as *const _ as usize, count)
+}
+/// Wake up at most `count` waiters of `futex` and move the others to `target`,
+/// provided that `target` still holds `target_val`
+pub fn futex_requeue(futex: &AtomicU32, count: usize, target: &AtomicU32, target_val: u32) -> isize {
+    sys_futex_requeue(
+        futex as *const _ as usize,
+        count,
+        target as *const _ as usize,
+        target_val,
+    )
+}
@@ -1,11 +1,15 @@
-use crate::TaskInfo;
+use crate::{DeadlockReport, TaskInfo};
 
-use super::{Stat, TimeVal};
+use super::{FdSet, PollFd, Stat, TimeVal};
 
 pub const SYSCALL_OPENAT: usize = 56;
 pub const SYSCALL_CLOSE: usize = 57;
 pub const SYSCALL_READ: usize = 63;
 pub const SYSCALL_WRITE: usize = 64;
+pub const SYSCALL_PREAD: usize = 67;
+pub const SYSCALL_PWRITE: usize = 68;
+pub const SYSCALL_PSELECT: usize = 72;
+pub const SYSCALL_PPOLL: usize = 73;
 pub const SYSCALL_UNLINKAT: usize = 35;
 pub const SYSCALL_LINKAT: usize = 37;
 pub const SYSCALL_FSTAT: usize = 80;
@@ -15,6 +19,13 @@ pub const SYSCALL_YIELD: usize = 124;
 pub const SYSCALL_GETTIMEOFDAY: usize = 169;
 pub const SYSCALL_GETPID: usize = 172;
 pub const SYSCALL_GETTID: usize = 178;
+pub const SYSCALL_SOCKET: usize = 198;
+pub const SYSCALL_BIND: usize = 200;
+pub const SYSCALL_LISTEN: usize = 201;
+pub const SYSCALL_ACCEPT: usize = 202;
+pub const SYSCALL_CONNECT: usize = 203;
+pub const SYSCALL_SENDTO: usize = 206;
+pub const SYSCALL_RECVFROM: usize = 207;
 pub const SYSCALL_FORK: usize = 220;
 pub const SYSCALL_EXEC: usize = 221;
 pub const SYSCALL_WAITPID: usize = 260;
@@ -24,8 +35,22 @@ pub const SYSCALL_MMAP: usize = 222;
 pub const SYSCALL_SPAWN: usize = 400;
 pub const SYSCALL_MAIL_READ: usize = 401;
 pub const SYSCALL_MAIL_WRITE: usize = 402;
+pub const SYSCALL_GETCWD: usize = 17;
 pub const SYSCALL_DUP: usize = 24;
+pub const SYSCALL_FCNTL: usize = 25;
+pub const SYSCALL_MKNODAT: usize = 33;
+pub const SYSCALL_MKDIRAT: usize = 34;
+pub const SYSCALL_UMOUNT2: usize = 39;
+pub const SYSCALL_MOUNT: usize = 40;
+pub const SYSCALL_CHDIR: usize = 49;
 pub const SYSCALL_PIPE: usize = 59;
+pub const SYSCALL_GETDENTS64: usize = 61;
+pub const SYSCALL_LSEEK: usize = 62;
+pub const SYSCALL_SYMLINKAT: usize = 36;
+pub const SYSCALL_RENAMEAT: usize = 38;
+pub const SYSCALL_FTRUNCATE: usize = 46;
+pub const SYSCALL_READLINKAT: usize = 78;
+pub const SYSCALL_FSTATAT: usize = 79;
 pub const SYSCALL_TASK_INFO: usize = 410;
 pub const SYSCALL_THREAD_CREATE: usize = 460;
 pub const SYSCALL_WAITTID: usize = 462;
@@ -39,6 +64,31 @@ pub const SYSCALL_SEMAPHORE_DOWN: usize = 470;
 pub const SYSCALL_CONDVAR_CREATE: usize = 471;
 pub const SYSCALL_CONDVAR_SIGNAL: usize = 472;
 pub const SYSCALL_CONDVAR_WAIT: usize = 473;
+pub const SYSCALL_FUTEX_WAIT: usize = 474;
+pub const SYSCALL_FUTEX_WAKE: usize = 475;
+pub const SYSCALL_FUTEX_REQUEUE: usize = 476;
+pub const SYSCALL_MUTEX_TRYLOCK: usize = 477;
+pub const SYSCALL_MUTEX_TIMEDLOCK: usize = 478;
+pub const SYSCALL_SEMAPHORE_TRYDOWN: usize = 479;
+pub const SYSCALL_SEMAPHORE_TIMEDDOWN: usize = 480;
+pub const SYSCALL_CONDVAR_TIMEDWAIT: usize = 481;
+pub const SYSCALL_MUTEX_DESTROY: usize = 482;
+pub const SYSCALL_SEMAPHORE_DESTROY: usize = 483;
+pub const SYSCALL_CONDVAR_DESTROY: usize = 484;
+pub const SYSCALL_DEADLOCK_REPORT: usize = 485;
+pub const SYSCALL_DEADLOCK_CLAIM: usize = 486;
+pub const SYSCALL_RWLOCK_CREATE: usize = 487;
+pub const SYSCALL_RWLOCK_READ: usize = 488;
+pub const SYSCALL_RWLOCK_WRITE: usize = 489;
+pub const SYSCALL_RWLOCK_UNLOCK: usize = 490;
+pub const SYSCALL_RWLOCK_DESTROY: usize = 491;
+pub const SYSCALL_BARRIER_CREATE: usize = 492;
+pub const SYSCALL_BARRIER_WAIT: usize = 493;
+pub const SYSCALL_BARRIER_DESTROY: usize = 494;
+pub const SYSCALL_CONDVAR_BROADCAST: usize = 495;
+pub const SYSCALL_SEND_FD: usize = 496;
+pub const SYSCALL_RECV_FD: usize = 497;
+pub const SYSCALL_DUP3: usize = 498;
 
 pub fn syscall(id: usize, args: [usize; 3]) -> isize {
     let mut ret: isize;
@@ -99,6 +149,50 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
+pub fn sys_ppoll(fds: &mut [PollFd], timeout_ms: isize) -> isize {
+    syscall(
+        SYSCALL_PPOLL,
+        [fds.as_mut_ptr() as usize, fds.len(), timeout_ms as usize],
+    )
+}
+
+pub fn sys_pselect(
+    nfds: usize,
+    readfds: Option<&mut FdSet>,
+    writefds: Option<&mut FdSet>,
+    timeout_ms: isize,
+) -> isize {
+    syscall6(
+        SYSCALL_PSELECT,
+        [
+            nfds,
+            readfds.map_or(0, |set| set as *mut _ as usize),
+            writefds.map_or(0, |set| set as *mut _ as usize),
+            timeout_ms as usize,
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    syscall(SYSCALL_LSEEK, [fd, offset as usize, whence])
+}
+
+pub fn sys_pread(fd: usize, buffer: &mut [u8], offset: usize) -> isize {
+    syscall6(
+        SYSCALL_PREAD,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len(), offset, 0, 0],
+    )
+}
+
+pub fn sys_pwrite(fd: usize, buffer: &[u8], offset: usize) -> isize {
+    syscall6(
+        SYSCALL_PWRITE,
+        [fd, buffer.as_ptr() as usize, buffer.len(), offset, 0, 0],
+    )
+}
+
 pub fn sys_linkat(
     old_dirfd: usize,
     old_path: &str,
@@ -119,18 +213,112 @@ pub fn sys_linkat(
     )
 }
 
+pub fn sys_mknodat(dirfd: usize, path: &str, mode: u32) -> isize {
+    syscall(SYSCALL_MKNODAT, [dirfd, path.as_ptr() as usize, mode as usize])
+}
+
+pub fn sys_mkdirat(dirfd: usize, path: &str) -> isize {
+    syscall(SYSCALL_MKDIRAT, [dirfd, path.as_ptr() as usize, 0])
+}
+
+pub fn sys_mount(source: &str, target: &str, fs_type: &str, flags: usize) -> isize {
+    syscall6(
+        SYSCALL_MOUNT,
+        [
+            source.as_ptr() as usize,
+            target.as_ptr() as usize,
+            fs_type.as_ptr() as usize,
+            flags,
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_umount2(target: &str, flags: usize) -> isize {
+    syscall(SYSCALL_UMOUNT2, [target.as_ptr() as usize, flags, 0])
+}
+
+pub fn sys_chdir(path: &str) -> isize {
+    syscall(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_getcwd(buffer: &mut [u8]) -> isize {
+    syscall(SYSCALL_GETCWD, [buffer.as_mut_ptr() as usize, buffer.len(), 0])
+}
+
+pub fn sys_getdents64(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_GETDENTS64,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len()],
+    )
+}
+
 pub fn sys_unlinkat(dirfd: usize, path: &str, flags: usize) -> isize {
     syscall(SYSCALL_UNLINKAT, [dirfd, path.as_ptr() as usize, flags])
 }
 
+pub fn sys_renameat(old_dirfd: usize, old_path: &str, new_dirfd: usize, new_path: &str) -> isize {
+    syscall6(
+        SYSCALL_RENAMEAT,
+        [
+            old_dirfd,
+            old_path.as_ptr() as usize,
+            new_dirfd,
+            new_path.as_ptr() as usize,
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_ftruncate(fd: usize, len: usize) -> isize {
+    syscall(SYSCALL_FTRUNCATE, [fd, len, 0])
+}
+
+pub fn sys_symlinkat(target: &str, new_dirfd: usize, path: &str) -> isize {
+    syscall(
+        SYSCALL_SYMLINKAT,
+        [target.as_ptr() as usize, new_dirfd, path.as_ptr() as usize],
+    )
+}
+
+pub fn sys_readlinkat(dirfd: usize, path: &str, buf: &mut [u8]) -> isize {
+    syscall6(
+        SYSCALL_READLINKAT,
+        [
+            dirfd,
+            path.as_ptr() as usize,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_fstatat(dirfd: usize, path: &str, st: &Stat, flags: usize) -> isize {
+    syscall6(
+        SYSCALL_FSTATAT,
+        [
+            dirfd,
+            path.as_ptr() as usize,
+            st as *const _ as usize,
+            flags,
+            0,
+            0,
+        ],
+    )
+}
+
 pub fn sys_fstat(fd: usize, st: &Stat) -> isize {
     syscall(SYSCALL_FSTAT, [fd, st as *const _ as usize, 0])
 }
 
-pub fn sys_mail_read(buffer: &mut [u8]) -> isize {
+pub fn sys_mail_read(buffer: &mut [u8], flags: usize) -> isize {
     syscall(
         SYSCALL_MAIL_READ,
-        [buffer.as_ptr() as usize, buffer.len(), 0],
+        [buffer.as_ptr() as usize, buffer.len(), flags],
     )
 }
 
@@ -141,6 +329,42 @@ pub fn sys_mail_write(pid: usize, buffer: &[u8]) -> isize {
     )
 }
 
+pub fn sys_socket(domain: usize, kind: usize, protocol: usize) -> isize {
+    syscall(SYSCALL_SOCKET, [domain, kind, protocol])
+}
+
+pub fn sys_bind(fd: usize, name: &str) -> isize {
+    syscall(SYSCALL_BIND, [fd, name.as_ptr() as usize, 0])
+}
+
+pub fn sys_listen(fd: usize, backlog: usize) -> isize {
+    syscall(SYSCALL_LISTEN, [fd, backlog, 0])
+}
+
+pub fn sys_accept(fd: usize) -> isize {
+    syscall(SYSCALL_ACCEPT, [fd, 0, 0])
+}
+
+pub fn sys_connect(fd: usize, name: &str) -> isize {
+    syscall(SYSCALL_CONNECT, [fd, name.as_ptr() as usize, 0])
+}
+
+pub fn sys_sendto(fd: usize, buffer: &[u8]) -> isize {
+    syscall(SYSCALL_SENDTO, [fd, buffer.as_ptr() as usize, buffer.len()])
+}
+
+pub fn sys_recvfrom(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(SYSCALL_RECVFROM, [fd, buffer.as_ptr() as usize, buffer.len()])
+}
+
+pub fn sys_send_fd(sockfd: usize, fd: usize) -> isize {
+    syscall(SYSCALL_SEND_FD, [sockfd, fd, 0])
+}
+
+pub fn sys_recv_fd(sockfd: usize) -> isize {
+    syscall(SYSCALL_RECV_FD, [sockfd, 0, 0])
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
     syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0]);
     panic!("sys_exit never returns!");
@@ -197,8 +421,16 @@ pub fn sys_dup(fd: usize) -> isize {
     syscall(SYSCALL_DUP, [fd, 0, 0])
 }
 
-pub fn sys_pipe(pipe: &mut [usize]) -> isize {
-    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: u32) -> isize {
+    syscall(SYSCALL_DUP3, [old_fd, new_fd, flags as usize])
+}
+
+pub fn sys_pipe(pipe: &mut [usize], flags: u32) -> isize {
+    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, flags as usize, 0])
+}
+
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_FCNTL, [fd, cmd, arg])
 }
 
 pub fn sys_task_info(info: &TaskInfo) -> isize {
@@ -229,6 +461,18 @@ pub fn sys_mutex_unlock(id: usize) -> isize {
     syscall(SYSCALL_MUTEX_UNLOCK, [id, 0, 0])
 }
 
+pub fn sys_mutex_trylock(id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_TRYLOCK, [id, 0, 0])
+}
+
+pub fn sys_mutex_timedlock(id: usize, timeout_ms: usize) -> isize {
+    syscall(SYSCALL_MUTEX_TIMEDLOCK, [id, timeout_ms, 0])
+}
+
+pub fn sys_mutex_destroy(id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_DESTROY, [id, 0, 0])
+}
+
 pub fn sys_semaphore_create(res_count: usize) -> isize {
     syscall(SYSCALL_SEMAPHORE_CREATE, [res_count, 0, 0])
 }
@@ -245,6 +489,26 @@ pub fn sys_semaphore_down(sem_id: usize) -> isize {
     syscall(SYSCALL_SEMAPHORE_DOWN, [sem_id, 0, 0])
 }
 
+pub fn sys_semaphore_trydown(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_TRYDOWN, [sem_id, 0, 0])
+}
+
+pub fn sys_semaphore_timeddown(sem_id: usize, timeout_ms: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_TIMEDDOWN, [sem_id, timeout_ms, 0])
+}
+
+pub fn sys_semaphore_destroy(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_DESTROY, [sem_id, 0, 0])
+}
+
+pub fn sys_deadlock_report(report: &mut DeadlockReport) -> isize {
+    syscall(SYSCALL_DEADLOCK_REPORT, [report as *mut _ as usize, 0, 0])
+}
+
+pub fn sys_deadlock_claim(kind: usize, id: usize, max: usize) -> isize {
+    syscall(SYSCALL_DEADLOCK_CLAIM, [kind, id, max])
+}
+
 pub fn sys_condvar_create(_arg: usize) -> isize {
     syscall(SYSCALL_CONDVAR_CREATE, [_arg, 0, 0])
 }
@@ -253,6 +517,65 @@ pub fn sys_condvar_signal(condvar_id: usize) -> isize {
     syscall(SYSCALL_CONDVAR_SIGNAL, [condvar_id, 0, 0])
 }
 
+pub fn sys_condvar_broadcast(condvar_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_BROADCAST, [condvar_id, 0, 0])
+}
+
 pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
     syscall(SYSCALL_CONDVAR_WAIT, [condvar_id, mutex_id, 0])
 }
+
+pub fn sys_condvar_destroy(condvar_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_DESTROY, [condvar_id, 0, 0])
+}
+
+pub fn sys_condvar_timedwait(condvar_id: usize, mutex_id: usize, timeout_ms: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_TIMEDWAIT, [condvar_id, mutex_id, timeout_ms])
+}
+
+pub fn sys_rwlock_create(writer_preferred: bool) -> isize {
+    syscall(SYSCALL_RWLOCK_CREATE, [writer_preferred as usize, 0, 0])
+}
+
+pub fn sys_rwlock_read(rwlock_id: usize) -> isize {
+    syscall(SYSCALL_RWLOCK_READ, [rwlock_id, 0, 0])
+}
+
+pub fn sys_rwlock_write(rwlock_id: usize) -> isize {
+    syscall(SYSCALL_RWLOCK_WRITE, [rwlock_id, 0, 0])
+}
+
+pub fn sys_rwlock_unlock(rwlock_id: usize) -> isize {
+    syscall(SYSCALL_RWLOCK_UNLOCK, [rwlock_id, 0, 0])
+}
+
+pub fn sys_rwlock_destroy(rwlock_id: usize) -> isize {
+    syscall(SYSCALL_RWLOCK_DESTROY, [rwlock_id, 0, 0])
+}
+
+pub fn sys_barrier_create(parties: usize) -> isize {
+    syscall(SYSCALL_BARRIER_CREATE, [parties, 0, 0])
+}
+
+pub fn sys_barrier_wait(barrier_id: usize) -> isize {
+    syscall(SYSCALL_BARRIER_WAIT, [barrier_id, 0, 0])
+}
+
+pub fn sys_barrier_destroy(barrier_id: usize) -> isize {
+    syscall(SYSCALL_BARRIER_DESTROY, [barrier_id, 0, 0])
+}
+
+pub fn sys_futex_wait(uaddr: usize, val: u32, timeout_ms: usize) -> isize {
+    syscall(SYSCALL_FUTEX_WAIT, [uaddr, val as usize, timeout_ms])
+}
+
+pub fn sys_futex_wake(uaddr: usize, count: usize) -> isize {
+    syscall(SYSCALL_FUTEX_WAKE, [uaddr, count, 0])
+}
+
+pub fn sys_futex_requeue(uaddr: usize, count: usize, uaddr2: usize, val2: u32) -> isize {
+    syscall6(
+        SYSCALL_FUTEX_REQUEUE,
+        [uaddr, count, uaddr2, val2 as usize, 0, 0],
+    )
+}